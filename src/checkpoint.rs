@@ -0,0 +1,87 @@
+/*!
+Saving an [`EGraph`] to disk and loading it back.
+
+See [`EGraph::save`].
+!*/
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::*;
+
+/// An error from [`EGraph::save`] or [`EGraph::load`].
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    /// The checkpoint file couldn't be read or written.
+    #[error("checkpoint file error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The checkpoint couldn't be encoded or decoded.
+    #[error("bad checkpoint: {0}")]
+    Encoding(#[from] bincode::Error),
+}
+
+impl<L, N> EGraph<L, N>
+where
+    L: Language + Serialize + DeserializeOwned,
+    N: Analysis<L> + Serialize + DeserializeOwned,
+    N::Data: Serialize + DeserializeOwned,
+{
+    /// Saves this egraph to `path` in a compact binary format, so a long
+    /// run can be resumed with [`EGraph::load`] after a crash.
+    ///
+    /// Everything is saved, including the union-find, the hashcons, the
+    /// analysis and any explanations.
+    /// The checkpoint is first written next to `path` and then renamed over
+    /// it, so a crash while saving leaves the previous checkpoint intact.
+    ///
+    /// The format is only meant to be read back by the same version of
+    /// `egg` with the same language and analysis.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+    /// let path = std::env::temp_dir().join(format!("egg-doc-{}.ckpt", std::process::id()));
+    ///
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(+ x y)".parse().unwrap())
+    ///     .with_iter_limit(1)
+    ///     .run(rules);
+    /// runner.egraph.save(&path).unwrap();
+    ///
+    /// let egraph = EGraph::<SymbolLang, ()>::load(&path).unwrap();
+    /// let runner = Runner::default().with_egraph(egraph).run(rules);
+    /// assert_eq!(runner.egraph.number_of_classes(), 3);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Loads an egraph saved with [`EGraph::save`].
+    ///
+    /// The returned egraph has been [rebuilt](EGraph::rebuild()), so it is
+    /// ready to search.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut egraph: Self = bincode::deserialize_from(reader)?;
+        egraph.rebuild();
+        Ok(egraph)
+    }
+}
@@ -0,0 +1,437 @@
+/*!
+Loading and running benchmark corpora for reproducible experiments.
+
+See [`Corpus`].
+!*/
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::*;
+
+/// The name of the manifest file that [`Corpus::load`] looks for.
+pub const CORPUS_MANIFEST: &str = "corpus.toml";
+
+/// A suite of benchmark expressions, loaded from a directory with
+/// [`Corpus::load`].
+///
+/// The directory holds one s-expression per file, plus a [`CORPUS_MANIFEST`]
+/// that names the ruleset to use, the [`Runner`] limits, and each
+/// benchmark along with the best cost recorded for it so far:
+///
+/// ```toml
+/// ruleset = "math"
+///
+/// [limits]
+/// iter_limit = 30
+/// node_limit = 10000
+/// time_limit = 5.0 # seconds
+///
+/// [[benchmark]]
+/// file = "simplify_add.sexp"
+/// expected_cost = 1
+///
+/// [[benchmark]]
+/// name = "diff-power"
+/// file = "diff_power.sexp"
+/// ```
+///
+/// Running the corpus with [`Corpus::run`] produces a [`CorpusReport`] that
+/// compares the costs found against those recorded in the manifest, so you
+/// can track how changes to the matcher, scheduler, or rules affect
+/// performance and result quality over time.
+/// This module requires the `corpus` feature.
+///
+/// # Example
+/// ```no_run
+/// use egg::{rewrite as rw, *};
+///
+/// let corpus: Corpus<SymbolLang> = Corpus::load("benches/math").unwrap();
+/// assert_eq!(corpus.ruleset, "math");
+/// let rules: Vec<Rewrite<SymbolLang, ()>> = vec![
+///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rw!("add-0"; "(+ ?a 0)" => "?a"),
+/// ];
+/// let report = corpus.run(&rules, || AstSize).unwrap();
+/// println!("{}", report);
+/// assert_eq!(report.regressions().count(), 0);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Corpus<L> {
+    /// The name of the ruleset the benchmarks should be run with.
+    /// It's up to the caller to map this to actual rules.
+    pub ruleset: String,
+    /// The limits to run each benchmark with.
+    pub limits: CorpusLimits,
+    /// The benchmarks in the order they appear in the manifest.
+    pub benchmarks: Vec<Benchmark<L>>,
+}
+
+/// The [`Runner`] limits of a [`Corpus`].
+///
+/// Limits that are left out of the manifest use the [`Runner`] defaults.
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorpusLimits {
+    /// See [`Runner::with_iter_limit`].
+    pub iter_limit: Option<usize>,
+    /// See [`Runner::with_node_limit`].
+    pub node_limit: Option<usize>,
+    /// See [`Runner::with_time_limit`], in seconds.
+    pub time_limit: Option<f64>,
+}
+
+impl CorpusLimits {
+    /// The time limit as a [`Duration`], failing if it is negative, NaN,
+    /// or too large.
+    fn time_limit(&self) -> Result<Option<Duration>, CorpusError> {
+        match self.time_limit {
+            None => Ok(None),
+            // also false for NaN
+            Some(secs) if secs >= 0.0 && secs < u64::MAX as f64 => {
+                Ok(Some(Duration::from_secs_f64(secs)))
+            }
+            Some(secs) => Err(CorpusError::TimeLimit(secs)),
+        }
+    }
+}
+
+/// A single benchmark in a [`Corpus`].
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Benchmark<L> {
+    /// The name of the benchmark,
+    /// which defaults to the file name without its extension.
+    pub name: String,
+    /// The expression to optimize.
+    pub expr: RecExpr<L>,
+    expected_cost: Option<toml::Value>,
+}
+
+/// An error from loading or running a [`Corpus`].
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Error)]
+pub enum CorpusError {
+    /// A file couldn't be read.
+    #[error("could not read {path:?}: {source}")]
+    Io {
+        /// The file that couldn't be read.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// The manifest isn't valid.
+    #[error("invalid corpus manifest: {0}")]
+    Manifest(#[from] toml::de::Error),
+    /// The time limit isn't a non-negative number of seconds that fits in
+    /// a [`Duration`].
+    #[error("invalid time limit: {0} seconds")]
+    TimeLimit(f64),
+    /// A benchmark file doesn't contain a valid expression.
+    #[error("could not parse {path:?}: {message}")]
+    Parse {
+        /// The benchmark file.
+        path: PathBuf,
+        /// A description of the parse error.
+        message: String,
+    },
+    /// The expected cost of a benchmark doesn't fit the cost function's
+    /// [`Cost`](CostFunction::Cost) type.
+    #[error("invalid expected cost for benchmark {name}: {source}")]
+    ExpectedCost {
+        /// The name of the benchmark.
+        name: String,
+        /// The underlying error.
+        source: toml::de::Error,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    ruleset: String,
+    #[serde(default)]
+    limits: CorpusLimits,
+    #[serde(default, rename = "benchmark")]
+    benchmarks: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    name: Option<String>,
+    file: PathBuf,
+    expected_cost: Option<toml::Value>,
+}
+
+fn read(path: &Path) -> Result<String, CorpusError> {
+    std::fs::read_to_string(path).map_err(|source| CorpusError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+impl<L: FromOp> Corpus<L> {
+    /// Loads the corpus in `dir`, reading the [`CORPUS_MANIFEST`] and every
+    /// benchmark file it lists.
+    /// Benchmark files are resolved relative to `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, CorpusError> {
+        let dir = dir.as_ref();
+        let manifest: Manifest = toml::from_str(&read(&dir.join(CORPUS_MANIFEST))?)?;
+        manifest.limits.time_limit()?;
+
+        let mut benchmarks = Vec::with_capacity(manifest.benchmarks.len());
+        for entry in manifest.benchmarks {
+            let path = dir.join(&entry.file);
+            let expr = read(&path)?
+                .trim()
+                .parse()
+                .map_err(|e| CorpusError::Parse {
+                    path: path.clone(),
+                    message: format!("{:?}", e),
+                })?;
+            let name = entry.name.unwrap_or_else(|| {
+                let stem = entry.file.file_stem().unwrap_or_default();
+                stem.to_string_lossy().into_owned()
+            });
+            benchmarks.push(Benchmark {
+                name,
+                expr,
+                expected_cost: entry.expected_cost,
+            });
+        }
+
+        Ok(Self {
+            ruleset: manifest.ruleset,
+            limits: manifest.limits,
+            benchmarks,
+        })
+    }
+}
+
+impl<L: Language + Display> Corpus<L> {
+    /// Runs every benchmark with the given rules, extracts the best term
+    /// with a cost function made by `make_cost`, and compares its cost to
+    /// the one recorded in the manifest.
+    ///
+    /// Fails if a recorded cost can't be deserialized as a `CF::Cost`, or if
+    /// the time limit is invalid.
+    pub fn run<N, CF>(
+        &self,
+        rules: &[Rewrite<L, N>],
+        mut make_cost: impl FnMut() -> CF,
+    ) -> Result<CorpusReport<CF::Cost>, CorpusError>
+    where
+        N: Analysis<L> + Default,
+        CF: CostFunction<L>,
+        CF::Cost: DeserializeOwned,
+    {
+        let time_limit = self.limits.time_limit()?;
+        let mut results = Vec::with_capacity(self.benchmarks.len());
+        for bench in &self.benchmarks {
+            let expected_cost = match &bench.expected_cost {
+                None => None,
+                Some(value) => Some(
+                    <CF::Cost as Deserialize>::deserialize(value.clone()).map_err(|source| {
+                        CorpusError::ExpectedCost {
+                            name: bench.name.clone(),
+                            source,
+                        }
+                    })?,
+                ),
+            };
+
+            let mut runner = Runner::default().with_expr(&bench.expr);
+            if let Some(limit) = self.limits.iter_limit {
+                runner = runner.with_iter_limit(limit);
+            }
+            if let Some(limit) = self.limits.node_limit {
+                runner = runner.with_node_limit(limit);
+            }
+            if let Some(limit) = time_limit {
+                runner = runner.with_time_limit(limit);
+            }
+            let runner = runner.run(rules);
+
+            let extractor = Extractor::new(&runner.egraph, make_cost());
+            let (best_cost, best) = extractor.find_best(runner.roots[0]);
+            results.push(BenchmarkResult {
+                name: bench.name.clone(),
+                initial_cost: make_cost().cost_rec(&bench.expr),
+                best_cost,
+                expected_cost,
+                best: best.to_string(),
+                report: runner.report(),
+            });
+        }
+        Ok(CorpusReport { results })
+    }
+}
+
+/// The outcome of [`Corpus::run`].
+///
+/// The [`Display`] implementation prints a table with one row per
+/// benchmark.
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CorpusReport<C> {
+    /// The results in the same order as [`Corpus::benchmarks`].
+    pub results: Vec<BenchmarkResult<C>>,
+}
+
+/// The outcome of running a single [`Benchmark`].
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BenchmarkResult<C> {
+    /// The name of the benchmark.
+    pub name: String,
+    /// The cost of the input expression.
+    pub initial_cost: C,
+    /// The cost of the best term found.
+    pub best_cost: C,
+    /// The cost recorded in the manifest, if any.
+    pub expected_cost: Option<C>,
+    /// The best term found.
+    pub best: String,
+    /// The report of the [`Runner`] used for this benchmark.
+    pub report: Report,
+}
+
+/// How a [`BenchmarkResult`] compares to the recorded baseline.
+#[cfg_attr(docsrs, doc(cfg(feature = "corpus")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The best cost is lower than the recorded one.
+    Improved,
+    /// The best cost is the same as the recorded one.
+    Same,
+    /// The best cost is higher than the recorded one, or they can't be
+    /// compared.
+    Regressed,
+    /// No cost was recorded for this benchmark.
+    NoBaseline,
+}
+
+impl<C: PartialOrd> BenchmarkResult<C> {
+    /// Compares the best cost found to the one recorded in the manifest.
+    pub fn comparison(&self) -> Comparison {
+        use std::cmp::Ordering::*;
+        match &self.expected_cost {
+            None => Comparison::NoBaseline,
+            Some(expected) => match self.best_cost.partial_cmp(expected) {
+                Some(Less) => Comparison::Improved,
+                Some(Equal) => Comparison::Same,
+                Some(Greater) | None => Comparison::Regressed,
+            },
+        }
+    }
+}
+
+impl<C: PartialOrd> CorpusReport<C> {
+    /// Returns the results that did worse than their recorded baseline.
+    pub fn regressions(&self) -> impl Iterator<Item = &BenchmarkResult<C>> {
+        self.results
+            .iter()
+            .filter(|r| r.comparison() == Comparison::Regressed)
+    }
+}
+
+impl<C: PartialOrd + Debug> Display for CorpusReport<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for r in &self.results {
+            let expected = match &r.expected_cost {
+                Some(c) => format!("{:?}", c),
+                None => "-".into(),
+            };
+            writeln!(
+                f,
+                "{:<24} {:>10?} -> {:>10?} (expected {:>10}) {:?}, {} iterations, {:.3}s",
+                r.name,
+                r.initial_cost,
+                r.best_cost,
+                expected,
+                r.comparison(),
+                r.report.iterations,
+                r.report.total_time,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rewrite as rw, *};
+
+    #[test]
+    fn run_corpus() {
+        let dir = std::env::temp_dir().join(format!("egg-corpus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(CORPUS_MANIFEST),
+            r#"
+                ruleset = "add"
+                [limits]
+                iter_limit = 5
+                [[benchmark]]
+                file = "zero.sexp"
+                expected_cost = 2
+                [[benchmark]]
+                name = "no-baseline"
+                file = "zero.sexp"
+            "#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("zero.sexp"), "(+ (+ x 0) 0)\n").unwrap();
+
+        let corpus: Corpus<SymbolLang> = Corpus::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(corpus.ruleset, "add");
+        assert_eq!(corpus.limits.iter_limit, Some(5));
+        assert_eq!(corpus.benchmarks[0].name, "zero");
+
+        let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("add-0"; "(+ ?a 0)" => "?a")];
+        let report = corpus.run(rules, || AstSize).unwrap();
+        assert_eq!(report.results[0].initial_cost, 5);
+        assert_eq!(report.results[0].best_cost, 1);
+        assert_eq!(report.results[0].comparison(), Comparison::Improved);
+        assert_eq!(report.results[1].comparison(), Comparison::NoBaseline);
+        assert_eq!(report.regressions().count(), 0);
+    }
+
+    #[test]
+    fn invalid_time_limit() {
+        let dir = std::env::temp_dir().join(format!("egg-corpus-time-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for limit in &["-1.0", "nan", "inf"] {
+            let manifest = format!("ruleset = \"add\"\n[limits]\ntime_limit = {}\n", limit);
+            std::fs::write(dir.join(CORPUS_MANIFEST), manifest).unwrap();
+            let err = Corpus::<SymbolLang>::load(&dir).unwrap_err();
+            assert!(matches!(err, CorpusError::TimeLimit(_)), "{}", limit);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut corpus = Corpus::<SymbolLang> {
+            ruleset: "add".into(),
+            limits: CorpusLimits::default(),
+            benchmarks: vec![],
+        };
+        corpus.limits.time_limit = Some(-1.0);
+        let rules: &[Rewrite<SymbolLang, ()>] = &[];
+        assert!(matches!(
+            corpus.run(rules, || AstSize),
+            Err(CorpusError::TimeLimit(_))
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+/*!
+Counting the terms represented by an [`EGraph`].
+
+See [`EGraph::count_terms`].
+!*/
+
+use num_bigint::BigUint;
+
+use crate::*;
+
+impl<L: Language, N: Analysis<L>> EGraph<L, N> {
+    /// Counts the distinct terms of depth at most `max_depth` represented
+    /// by each eclass, keyed by canonical id.
+    ///
+    /// Leaves have depth 1, like with [`AstDepth`].
+    /// Egraphs with cycles represent infinitely many terms, hence the bound;
+    /// even so, the counts grow exponentially with the depth, so they are
+    /// big integers.
+    /// The count for the roots of an egraph is the usual measure of the
+    /// search space equality saturation has explored.
+    ///
+    /// Requires the `term-count` feature.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let x = egraph.add_expr(&"x".parse().unwrap());
+    /// let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// let y = egraph.add_expr(&"y".parse().unwrap());
+    /// let g = egraph.add_expr(&"(g x x)".parse().unwrap());
+    /// egraph.union(x, fx);
+    /// egraph.union(x, y);
+    /// egraph.rebuild();
+    ///
+    /// let counts = egraph.count_terms(3);
+    /// // x, y, (f x), (f y), (f (f x)), and (f (f y))
+    /// assert_eq!(counts[&egraph.find(x)], BigUint::from(6u32));
+    /// // (g a b) where a and b are among x, y, (f x) and (f y)
+    /// assert_eq!(counts[&egraph.find(g)], BigUint::from(16u32));
+    /// assert_eq!(egraph.count_terms(3).len(), egraph.number_of_classes());
+    /// ```
+    pub fn count_terms(&self, max_depth: usize) -> HashMap<Id, BigUint> {
+        assert!(self.clean, "Must rebuild the egraph before counting terms");
+        let mut counts: HashMap<Id, BigUint> = self
+            .classes()
+            .map(|class| (class.id, BigUint::default()))
+            .collect();
+        for _ in 0..max_depth {
+            // the terms one level deeper than the ones counted so far
+            let deeper: HashMap<Id, BigUint> = self
+                .classes()
+                .map(|class| {
+                    let count = class
+                        .iter()
+                        .map(|node| {
+                            node.fold(BigUint::from(1u32), |product, child| {
+                                product * &counts[&self.find(child)]
+                            })
+                        })
+                        .sum();
+                    (class.id, count)
+                })
+                .collect();
+            counts = deeper;
+        }
+        counts
+    }
+}
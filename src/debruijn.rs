@@ -0,0 +1,482 @@
+/*!
+Helpers for languages with binders, whose variables are de Bruijn indices.
+
+The [`DeBruijn`] trait describes where a language's binders and variables
+are, [`shift`], [`substitute`] and [`beta`] work on terms of such a
+language, and the [`BetaReduce`] and [`Shift`] appliers use them in
+rewrites.
+The trait and the appliers are also exported from the crate root.
+!*/
+
+use crate::*;
+
+/** A [`Language`] with binders, whose variables are de Bruijn indices.
+
+With de Bruijn indices, a variable is the number of binders between it and
+the binder it refers to, so alpha-equivalent terms are identical and
+hashcons to the same e-node.
+The price is that substituting under a binder has to shift the indices of
+the substituted term; [`shift`], [`substitute`] and [`beta`] implement
+that, and the [`BetaReduce`] and [`Shift`] appliers use them in rewrites.
+
+# Example
+```
+use egg::{rewrite as rw, *};
+
+define_language! {
+    enum Lambda {
+        "lam" = Lam(Id),
+        "app" = App([Id; 2]),
+        Index(usize),
+        Symbol(Symbol),
+    }
+}
+
+impl DeBruijn for Lambda {
+    fn index(&self) -> Option<usize> {
+        match self {
+            Lambda::Index(i) => Some(*i),
+            _ => None,
+        }
+    }
+    fn from_index(index: usize) -> Self {
+        Lambda::Index(index)
+    }
+    fn binders(&self, _child: usize) -> usize {
+        match self {
+            Lambda::Lam(_) => 1,
+            _ => 0,
+        }
+    }
+}
+
+let rules: &[Rewrite<Lambda, ()>] = &[
+    rw!("beta"; "(app (lam ?body) ?arg)" => { BetaReduce::new("?body", "?arg") }),
+];
+// (\x. \y. x y) f
+let runner = Runner::default()
+    .with_expr(&"(app (lam (lam (app 1 0))) f)".parse().unwrap())
+    .run(rules);
+let reduced = "(lam (app f 0))".parse().unwrap();
+assert_eq!(runner.egraph.lookup_expr(&reduced), Some(runner.egraph.find(runner.roots[0])));
+```
+**/
+pub trait DeBruijn: Language {
+    /// Returns the de Bruijn index of this e-node, if it is a variable.
+    fn index(&self) -> Option<usize>;
+
+    /// Returns a variable e-node with the given de Bruijn index.
+    fn from_index(index: usize) -> Self;
+
+    /// Returns how many binders this e-node puts around its `child`th child.
+    ///
+    /// This is 1 for the body of a lambda, and 0 for everything else.
+    fn binders(&self, child: usize) -> usize;
+}
+
+/// Shifts the free variables of `expr` with index at least `cutoff` by
+/// `amount`, returning `None` if that would make an index negative.
+///
+/// # Example
+/// ```
+/// # use egg::{debruijn::shift, *};
+/// # define_language! { enum Lambda { "lam" = Lam(Id), "app" = App([Id; 2]), Index(usize), } }
+/// # impl DeBruijn for Lambda {
+/// #     fn index(&self) -> Option<usize> { match self { Lambda::Index(i) => Some(*i), _ => None } }
+/// #     fn from_index(index: usize) -> Self { Lambda::Index(index) }
+/// #     fn binders(&self, _: usize) -> usize { matches!(self, Lambda::Lam(_)) as usize }
+/// # }
+/// let expr: RecExpr<Lambda> = "(app 0 (lam (app 0 1)))".parse().unwrap();
+/// assert_eq!(shift(&expr, 2, 0).unwrap().to_string(), "(app 2 (lam (app 0 3)))");
+/// assert_eq!(shift(&expr, -1, 0), None);
+/// ```
+pub fn shift<L: DeBruijn>(expr: &RecExpr<L>, amount: isize, cutoff: usize) -> Option<RecExpr<L>> {
+    let mut out = RecExpr::default();
+    let mut memo = HashMap::default();
+    let mut var = |index: usize, depth: usize, out: &mut RecExpr<L>| {
+        if index < cutoff + depth {
+            Some(out.add(L::from_index(index)))
+        } else {
+            let shifted = index as isize + amount;
+            (shifted >= 0).then(|| out.add(L::from_index(shifted as usize)))
+        }
+    };
+    rebuild(expr, root(expr), 0, &mut out, &mut memo, &mut var)?;
+    Some(out)
+}
+
+/// Substitutes `value` for the free variable `index` in `expr`, shifting
+/// `value` as it goes under binders.
+///
+/// This doesn't remove the binder of `index`; see [`beta`] for that.
+pub fn substitute<L: DeBruijn>(expr: &RecExpr<L>, index: usize, value: &RecExpr<L>) -> RecExpr<L> {
+    let mut out = RecExpr::default();
+    let mut memo = HashMap::default();
+    let mut var = |i: usize, depth: usize, out: &mut RecExpr<L>| {
+        if i == index + depth {
+            let value = shift(value, depth as isize, 0).unwrap();
+            Some(append(out, &value))
+        } else {
+            Some(out.add(L::from_index(i)))
+        }
+    };
+    rebuild(expr, root(expr), 0, &mut out, &mut memo, &mut var).unwrap();
+    out
+}
+
+/// Beta-reduces the application of a lambda with body `body` to `arg`.
+///
+/// # Example
+/// ```
+/// # use egg::{debruijn::beta, *};
+/// # define_language! { enum Lambda { "lam" = Lam(Id), "app" = App([Id; 2]), Index(usize), Symbol(Symbol), } }
+/// # impl DeBruijn for Lambda {
+/// #     fn index(&self) -> Option<usize> { match self { Lambda::Index(i) => Some(*i), _ => None } }
+/// #     fn from_index(index: usize) -> Self { Lambda::Index(index) }
+/// #     fn binders(&self, _: usize) -> usize { matches!(self, Lambda::Lam(_)) as usize }
+/// # }
+/// // the body of (lam (lam (app 1 2))), applied to the free variable 0
+/// let body: RecExpr<Lambda> = "(lam (app 1 2))".parse().unwrap();
+/// let arg = "0".parse().unwrap();
+/// assert_eq!(beta(&body, &arg).to_string(), "(lam (app 1 1))");
+/// ```
+pub fn beta<L: DeBruijn>(body: &RecExpr<L>, arg: &RecExpr<L>) -> RecExpr<L> {
+    let arg = shift(arg, 1, 0).unwrap();
+    let substituted = substitute(body, 0, &arg);
+    shift(&substituted, -1, 0).expect("the bound variable was substituted away")
+}
+
+fn root<L: Language>(expr: &RecExpr<L>) -> Id {
+    Id::from(expr.as_ref().len() - 1)
+}
+
+/// Adds all of `expr` to `out`, returning the id of its root.
+fn append<L: Language>(out: &mut RecExpr<L>, expr: &RecExpr<L>) -> Id {
+    let mut ids: Vec<Id> = Vec::with_capacity(expr.as_ref().len());
+    for node in expr.as_ref() {
+        let node = node.clone().map_children(|c| ids[usize::from(c)]);
+        ids.push(out.add(node));
+    }
+    *ids.last().unwrap()
+}
+
+/// Copies the term rooted at `id` into `out`, replacing each variable with
+/// what `var` returns given its index and the number of binders above it.
+fn rebuild<L, F>(
+    expr: &RecExpr<L>,
+    id: Id,
+    depth: usize,
+    out: &mut RecExpr<L>,
+    memo: &mut HashMap<(Id, usize), Id>,
+    var: &mut F,
+) -> Option<Id>
+where
+    L: DeBruijn,
+    F: FnMut(usize, usize, &mut RecExpr<L>) -> Option<Id>,
+{
+    if let Some(&new) = memo.get(&(id, depth)) {
+        return Some(new);
+    }
+    let node = &expr[id];
+    let new = match node.index() {
+        Some(index) => var(index, depth, out)?,
+        None => {
+            let mut children = Vec::with_capacity(node.len());
+            for (i, &child) in node.children().iter().enumerate() {
+                let depth = depth + node.binders(i);
+                children.push(rebuild(expr, child, depth, out, memo, var)?);
+            }
+            let mut node = node.clone();
+            node.children_mut().copy_from_slice(&children);
+            out.add(node)
+        }
+    };
+    memo.insert((id, depth), new);
+    Some(new)
+}
+
+/// Extracts the smallest term of each of `ids`.
+fn extract_all<L: Language, N: Analysis<L>>(
+    egraph: &EGraph<L, N>,
+    extractor: &Extractor<'_, AstSize, L, N>,
+    ids: &[Id],
+) -> Option<Vec<RecExpr<L>>> {
+    ids.iter()
+        .map(|&id| {
+            extractor
+                .try_find_best(egraph.find(id))
+                .ok()
+                .map(|(_, e)| e)
+        })
+        .collect()
+}
+
+/// Adds each of `results` to the egraph and unions it with its e-class,
+/// returning the e-classes that changed.
+fn union_results<L: Language, N: Analysis<L>>(
+    egraph: &mut EGraph<L, N>,
+    results: Vec<(Id, RecExpr<L>)>,
+    rule_name: Symbol,
+) -> Vec<Id> {
+    let mut changed = vec![];
+    for (eclass, expr) in results {
+        let id = egraph.add_expr(&expr);
+        if egraph.union_trusted(eclass, id, rule_name) {
+            changed.push(eclass);
+        }
+    }
+    changed
+}
+
+fn single_match<'a, L: Language>(eclass: Id, subst: &Subst) -> SearchMatches<'a, L> {
+    SearchMatches {
+        eclass,
+        substs: vec![subst.clone()],
+        ast: None,
+    }
+}
+
+/** An [`Applier`] that beta-reduces a lambda body bound to one variable
+applied to the argument bound to another.
+
+Since substitution has to look at whole terms, this extracts the smallest
+term of both e-classes with [`AstSize`] and adds the reduced term,
+so it only reduces one representative of each.
+It builds one [`Extractor`] for all the matches of a search, which costs
+about as much as the search itself, so [`apply_one`](Applier::apply_one)
+is best avoided; a [`ConditionalApplier`] around it still applies all the
+matches at once.
+
+See [`DeBruijn`] for an example.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BetaReduce {
+    body: Var,
+    arg: Var,
+}
+
+impl BetaReduce {
+    /// Creates a [`BetaReduce`] applier, parsing the two variables.
+    pub fn new(body: &str, arg: &str) -> Self {
+        Self {
+            body: body.parse().unwrap(),
+            arg: arg.parse().unwrap(),
+        }
+    }
+}
+
+impl<L: DeBruijn, N: Analysis<L>> Applier<L, N> for BetaReduce {
+    fn apply_matches(
+        &self,
+        egraph: &mut EGraph<L, N>,
+        matches: &[SearchMatches<L>],
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        let extractor = Extractor::new(egraph, AstSize);
+        let mut results = vec![];
+        for mat in matches {
+            for subst in &mat.substs {
+                let ids = [subst[self.body], subst[self.arg]];
+                if let Some(exprs) = extract_all(egraph, &extractor, &ids) {
+                    results.push((mat.eclass, beta(&exprs[0], &exprs[1])));
+                }
+            }
+        }
+        union_results(egraph, results, rule_name)
+    }
+
+    fn apply_one(
+        &self,
+        egraph: &mut EGraph<L, N>,
+        eclass: Id,
+        subst: &Subst,
+        _searcher_ast: Option<&PatternAst<L>>,
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        self.apply_matches(egraph, &[single_match(eclass, subst)], rule_name)
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        vec![self.body, self.arg]
+    }
+}
+
+/** An [`Applier`] that shifts the free variables of the term bound to a
+variable.
+
+Like [`BetaReduce`], this works on the smallest term of the e-class.
+A match is skipped if shifting would make an index negative, so a
+negative shift also checks that the shifted variables aren't free.
+
+# Example
+```
+# use egg::{rewrite as rw, *};
+# define_language! { enum Lambda { "lam" = Lam(Id), "app" = App([Id; 2]), Index(usize), Symbol(Symbol), } }
+# impl DeBruijn for Lambda {
+#     fn index(&self) -> Option<usize> { match self { Lambda::Index(i) => Some(*i), _ => None } }
+#     fn from_index(index: usize) -> Self { Lambda::Index(index) }
+#     fn binders(&self, _: usize) -> usize { matches!(self, Lambda::Lam(_)) as usize }
+# }
+// eta-reduction only applies if the function doesn't use the bound variable
+let rules: &[Rewrite<Lambda, ()>] = &[
+    rw!("eta"; "(lam (app ?f 0))" => { Shift::new("?f", -1) }),
+];
+let runner = Runner::default()
+    .with_expr(&"(lam (app f 0))".parse().unwrap())
+    .with_expr(&"(lam (app 0 0))".parse().unwrap())
+    .run(rules);
+let egraph = &runner.egraph;
+assert_eq!(egraph.lookup_expr(&"f".parse().unwrap()), Some(egraph.find(runner.roots[0])));
+assert_eq!(egraph[runner.roots[1]].len(), 1);
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shift {
+    var: Var,
+    amount: isize,
+    cutoff: usize,
+}
+
+impl Shift {
+    /// Creates a [`Shift`] applier, parsing the variable.
+    pub fn new(var: &str, amount: isize) -> Self {
+        Self {
+            var: var.parse().unwrap(),
+            amount,
+            cutoff: 0,
+        }
+    }
+
+    /// Only shift the variables with index at least `cutoff`.
+    pub fn with_cutoff(self, cutoff: usize) -> Self {
+        Self { cutoff, ..self }
+    }
+}
+
+impl<L: DeBruijn, N: Analysis<L>> Applier<L, N> for Shift {
+    fn apply_matches(
+        &self,
+        egraph: &mut EGraph<L, N>,
+        matches: &[SearchMatches<L>],
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        let extractor = Extractor::new(egraph, AstSize);
+        let mut results = vec![];
+        for mat in matches {
+            for subst in &mat.substs {
+                if let Some(exprs) = extract_all(egraph, &extractor, &[subst[self.var]]) {
+                    if let Some(shifted) = shift(&exprs[0], self.amount, self.cutoff) {
+                        results.push((mat.eclass, shifted));
+                    }
+                }
+            }
+        }
+        union_results(egraph, results, rule_name)
+    }
+
+    fn apply_one(
+        &self,
+        egraph: &mut EGraph<L, N>,
+        eclass: Id,
+        subst: &Subst,
+        _searcher_ast: Option<&PatternAst<L>>,
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        self.apply_matches(egraph, &[single_match(eclass, subst)], rule_name)
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        vec![self.var]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    define_language! {
+        enum Lambda {
+            "lam" = Lam(Id),
+            "app" = App([Id; 2]),
+            "let" = Let([Id; 2]),
+            Index(usize),
+            Symbol(Symbol),
+        }
+    }
+
+    impl DeBruijn for Lambda {
+        fn index(&self) -> Option<usize> {
+            match self {
+                Lambda::Index(i) => Some(*i),
+                _ => None,
+            }
+        }
+        fn from_index(index: usize) -> Self {
+            Lambda::Index(index)
+        }
+        fn binders(&self, child: usize) -> usize {
+            match self {
+                Lambda::Lam(_) => 1,
+                // (let value body) only binds in its body
+                Lambda::Let(_) => (child == 1) as usize,
+                _ => 0,
+            }
+        }
+    }
+
+    fn expr(s: &str) -> RecExpr<Lambda> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn shift_respects_cutoff_and_binders() {
+        let e = expr("(app 0 (app 1 (lam (app 0 2))))");
+        let shifted = shift(&e, 1, 1).unwrap();
+        assert_eq!(shifted.to_string(), "(app 0 (app 2 (lam (app 0 3))))");
+        let shifted = shift(&e, -1, 1).unwrap();
+        assert_eq!(shifted.to_string(), "(app 0 (app 0 (lam (app 0 1))))");
+        assert_eq!(shift(&e, -1, 0), None);
+        // the value of a let is outside its binder
+        let e = expr("(let 0 0)");
+        assert_eq!(shift(&e, 1, 0).unwrap().to_string(), "(let 1 0)");
+    }
+
+    #[test]
+    fn substitute_shifts_under_binders() {
+        let e = expr("(app 0 (lam (app 1 0)))");
+        let value = expr("(app f 0)");
+        assert_eq!(
+            substitute(&e, 0, &value).to_string(),
+            "(app (app f 0) (lam (app (app f 1) 0)))"
+        );
+        // other variables are left alone
+        assert_eq!(substitute(&e, 1, &value).to_string(), e.to_string());
+    }
+
+    #[test]
+    fn beta_reduces_under_binders() {
+        // the body of (lam (lam (app 1 0))), applied to the free variable 0
+        let body = expr("(lam (app 1 0))");
+        assert_eq!(beta(&body, &expr("0")).to_string(), "(lam (app 1 0))");
+        // the free variable 1 in the body is 0 outside the lambda
+        let body = expr("(app 0 1)");
+        assert_eq!(beta(&body, &expr("f")).to_string(), "(app f 0)");
+    }
+
+    #[test]
+    fn conditional_beta_reduce() {
+        let rules: &[Rewrite<Lambda, ()>] = &[rewrite!("beta";
+            "(app (lam ?body) ?arg)" => { BetaReduce::new("?body", "?arg") }
+            if ConditionEqual::parse("?arg", "f"))];
+        let runner = Runner::default()
+            .with_expr(&expr("(app (lam (app 0 0)) f)"))
+            .with_expr(&expr("(app (lam (app 0 0)) g)"))
+            .run(rules);
+        let egraph = &runner.egraph;
+        assert_eq!(
+            egraph.lookup_expr(&expr("(app f f)")),
+            Some(egraph.find(runner.roots[0]))
+        );
+        assert_eq!(egraph.lookup_expr(&expr("(app g g)")), None);
+    }
+}
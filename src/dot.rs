@@ -11,7 +11,7 @@ use std::fmt::{self, Debug, Display, Formatter};
 use std::io::{Error, ErrorKind, Result, Write};
 use std::path::Path;
 
-use crate::{egraph::EGraph, Analysis, Language};
+use crate::{egraph::EGraph, Analysis, EClass, Language};
 
 /**
 A wrapper for an [`EGraph`] that can output [GraphViz] for
@@ -36,6 +36,17 @@ let egraph = Runner::default().with_egraph(egraph).run(rules).egraph;
 // Dot implements std::fmt::Display
 println!("My egraph dot file: {}", egraph.dot());
 
+// it can also be written to anything that implements std::io::Write,
+// with some options to customize the output
+let mut buf: Vec<u8> = vec![];
+egraph
+    .dot()
+    .with_rankdir("LR")
+    .with_class_ids(true)
+    .with_class_metadata(|class| format!("{} nodes", class.len()))
+    .write_to(&mut buf)
+    .unwrap();
+
 // create a Dot and then compile it assuming `dot` is on the system
 egraph.dot().to_svg("target/foo.svg").unwrap();
 egraph.dot().to_png("target/foo.png").unwrap();
@@ -57,6 +68,11 @@ pub struct Dot<'a, L: Language, N: Analysis<L>> {
     /// Whether or not to anchor the edges in the output.
     /// True by default.
     pub use_anchors: bool,
+    /// Whether or not to label each eclass with its id.
+    /// False by default.
+    pub show_class_ids: bool,
+    #[allow(clippy::type_complexity)]
+    class_metadata: Option<Box<dyn Fn(&EClass<L, N::Data>) -> String + 'a>>,
 }
 
 impl<'a, L, N> Dot<'a, L, N>
@@ -67,8 +83,14 @@ where
     /// Writes the `Dot` to a .dot file with the given filename.
     /// Does _not_ require a `dot` binary.
     pub fn to_dot(&self, filename: impl AsRef<Path>) -> Result<()> {
-        let mut file = std::fs::File::create(filename)?;
-        write!(file, "{}", self)
+        let file = std::fs::File::create(filename)?;
+        self.write_to(file)
+    }
+
+    /// Writes the `Dot` to the given writer.
+    /// Does _not_ require a `dot` binary.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        write!(writer, "{}", self)
     }
 
     /// Adds a line to the dot output.
@@ -84,6 +106,28 @@ where
         self
     }
 
+    /// Sets the direction of the graph layout, e.g. `"LR"` for left to right.
+    /// This is a shorthand for adding a `rankdir` config line.
+    pub fn with_rankdir(self, rankdir: impl Display) -> Self {
+        self.with_config_line(format!("rankdir={}", rankdir))
+    }
+
+    /// Set whether or not to label each eclass with its id.
+    pub fn with_class_ids(mut self, show_class_ids: bool) -> Self {
+        self.show_class_ids = show_class_ids;
+        self
+    }
+
+    /// Annotates each eclass with the string returned by the given function,
+    /// for example to show its analysis data.
+    pub fn with_class_metadata<F>(mut self, metadata: F) -> Self
+    where
+        F: Fn(&EClass<L, N::Data>) -> String + 'a,
+    {
+        self.class_metadata = Some(Box::new(metadata));
+        self
+    }
+
     /// Renders the `Dot` to a .png file with the given filename.
     /// Requires a `dot` binary to be on your `$PATH`.
     pub fn to_png(&self, filename: impl AsRef<Path>) -> Result<()> {
@@ -102,6 +146,39 @@ where
         self.run_dot(&["-Tpdf".as_ref(), "-o".as_ref(), filename.as_ref()])
     }
 
+    /// Renders the `Dot` in the given output format (like `"svg"` or `"png"`)
+    /// and returns the rendered bytes, without going through any files.
+    /// Requires a `dot` binary to be on your `$PATH`.
+    ///
+    /// ```no_run
+    /// # use egg::*;
+    /// # let mut egraph: EGraph<SymbolLang, ()> = Default::default();
+    /// let png: Vec<u8> = egraph.dot().render("png").unwrap();
+    /// ```
+    pub fn render(&self, format: &str) -> Result<Vec<u8>> {
+        use std::process::{Command, Stdio};
+        let mut child = Command::new("dot")
+            .arg(format!("-T{}", format))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        // dot reads all of its input before writing anything,
+        // so we can write everything before collecting the output
+        let mut stdin = child.stdin.take().expect("Failed to open stdin");
+        write!(stdin, "{}", self)?;
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        check_exit_status(output.status)?;
+        Ok(output.stdout)
+    }
+
+    /// Renders the `Dot` as SVG and returns it as a string.
+    /// Requires a `dot` binary to be on your `$PATH`.
+    pub fn to_svg_string(&self) -> Result<String> {
+        let bytes = self.render("svg")?;
+        String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
     /// Invokes `dot` with the given arguments, piping this formatted
     /// `Dot` into stdin.
     pub fn run_dot<S, I>(&self, args: I) -> Result<()>
@@ -138,17 +215,7 @@ where
             .spawn()?;
         let stdin = child.stdin.as_mut().expect("Failed to open stdin");
         write!(stdin, "{}", self)?;
-        match child.wait()?.code() {
-            Some(0) => Ok(()),
-            Some(e) => Err(Error::new(
-                ErrorKind::Other,
-                format!("dot program returned error code {}", e),
-            )),
-            None => Err(Error::new(
-                ErrorKind::Other,
-                "dot program was killed by a signal",
-            )),
-        }
+        check_exit_status(child.wait()?)
     }
 
     // gives back the appropriate label and anchor
@@ -170,6 +237,20 @@ where
     }
 }
 
+fn check_exit_status(status: std::process::ExitStatus) -> Result<()> {
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(e) => Err(Error::new(
+            ErrorKind::Other,
+            format!("dot program returned error code {}", e),
+        )),
+        None => Err(Error::new(
+            ErrorKind::Other,
+            "dot program was killed by a signal",
+        )),
+    }
+}
+
 impl<'a, L: Language, N: Analysis<L>> Debug for Dot<'a, L, N> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_tuple("Dot").field(self.egraph).finish()
@@ -196,6 +277,17 @@ where
         for class in self.egraph.classes() {
             writeln!(f, "  subgraph cluster_{} {{", class.id)?;
             writeln!(f, "    style=dotted")?;
+            let mut label = vec![];
+            if self.show_class_ids {
+                label.push(format!("#{}", class.id));
+            }
+            if let Some(metadata) = &self.class_metadata {
+                label.push(metadata(class));
+            }
+            if !label.is_empty() {
+                let label = label.join("\\n").replace('"', "\\\"");
+                writeln!(f, "    label = \"{}\"", label)?;
+            }
             for (i, node) in class.iter().enumerate() {
                 writeln!(f, "    {}.{}[label = \"{}\"]", class.id, i, node)?;
             }
@@ -19,6 +19,12 @@ pub struct EClass<L, D> {
     pub data: D,
     /// The parent enodes and their original Ids.
     pub(crate) parents: Vec<(L, Id)>,
+    /// The [stamp](EGraph::stamp()) of each of the `nodes`.
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    pub(crate) stamps: Vec<usize>,
+    /// The [`EGraph`] version at which this eclass was created or last merged.
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    pub(crate) version: usize,
 }
 
 impl<L, D> EClass<L, D> {
@@ -37,6 +43,23 @@ impl<L, D> EClass<L, D> {
         self.nodes.iter()
     }
 
+    /// Iterates over the enodes in this eclass along with their
+    /// [stamps](EGraph::stamp()), which a [`Runner`] sets to the
+    /// iteration at which they were added.
+    ///
+    /// The stamps are only kept in sync with the enodes by
+    /// [`EGraph::rebuild`](EGraph::rebuild()); if the enodes were
+    /// modified directly, they all get the oldest stamp in this eclass.
+    pub fn iter_stamped(&self) -> impl Iterator<Item = (&L, usize)> {
+        self.nodes.iter().zip(self.stamps.iter().copied())
+    }
+
+    /// Returns the newest [stamp](EGraph::stamp()) of the enodes in
+    /// this eclass.
+    pub fn newest_stamp(&self) -> usize {
+        self.stamps.iter().copied().max().unwrap_or(0)
+    }
+
     /// Iterates over the parent enodes of this eclass.
     pub fn parents(&self) -> impl ExactSizeIterator<Item = (&L, Id)> {
         self.parents.iter().map(|(node, id)| (node, *id))
@@ -2,6 +2,7 @@ use crate::*;
 use std::{
     borrow::BorrowMut,
     fmt::{self, Debug, Display},
+    rc::Rc,
 };
 
 #[cfg(feature = "serde-1")]
@@ -74,9 +75,35 @@ pub struct EGraph<L: Language, N: Analysis<L>> {
         ))
     )]
     pub(crate) classes: HashMap<Id, EClass<L, N::Data>>,
+    /// The e-classes containing an e-node with each operator, keyed by
+    /// [`op_key`]. Updated by [`EGraph::rebuild`].
     #[cfg_attr(feature = "serde-1", serde(skip))]
     #[cfg_attr(feature = "serde-1", serde(default = "default_classes_by_op"))]
-    pub(crate) classes_by_op: HashMap<std::mem::Discriminant<L>, HashSet<Id>>,
+    pub(crate) classes_by_op: HashMap<L, HashSet<Id>>,
+    /// E-classes marked as roots by [`EGraph::mark_roots`].
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    roots: Vec<Id>,
+    /// Unions recorded since the last [`EGraph::take_union_log`], if enabled.
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    union_log: Option<Vec<(Id, Id, Id)>>,
+    /// Set by [`EGraph::with_observer`].
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    observer: ObserverSlot<L>,
+    /// Changes to undo with [`EGraph::rollback`], if enabled.
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    undo_log: Option<UndoLog<L, N::Data>>,
+    /// The scopes opened by [`EGraph::push`], and whether each one enabled
+    /// the undo log.
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    scopes: Vec<(UndoMark, bool)>,
+    /// Incremented whenever an eclass is created or merged.
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    pub(crate) version: usize,
+    /// Given to the enodes added from now on, see [`EGraph::stamp`].
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    stamp: usize,
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    match_backend: MatchBackend,
     /// Whether or not reading operation are allowed on this e-graph.
     /// Mutating operations will set this to `false`, and
     /// [`EGraph::rebuild`] will set it to true.
@@ -86,6 +113,114 @@ pub struct EGraph<L: Language, N: Analysis<L>> {
     pub clean: bool,
 }
 
+/// Callbacks for mirroring changes to an [`EGraph`] somewhere else, set with
+/// [`EGraph::with_observer`].
+///
+/// All methods do nothing by default.
+pub trait EGraphObserver<L>: Send + Sync {
+    /// Called when `enode` is added as the new e-class `id`.
+    /// Adding an e-node that was already in the egraph doesn't call this.
+    fn on_add(&mut self, _id: Id, _enode: &L) {}
+
+    /// Called when the e-classes `id1` and `id2` are merged, `leader` being
+    /// whichever of the two is canonical afterwards.
+    /// This includes the unions found by [`rebuild`](EGraph::rebuild()).
+    fn on_union(&mut self, _id1: Id, _id2: Id, _leader: Id) {}
+
+    /// Called at the end of each [`rebuild`](EGraph::rebuild()), with the
+    /// number of unions it performed.
+    fn on_rebuild(&mut self, _n_unions: usize) {}
+}
+
+/// The observer of an egraph isn't cloned, serialized or [forked](EGraph::fork()).
+struct ObserverSlot<L>(Option<Box<dyn EGraphObserver<L>>>);
+
+impl<L> Default for ObserverSlot<L> {
+    fn default() -> Self {
+        ObserverSlot(None)
+    }
+}
+
+impl<L> Clone for ObserverSlot<L> {
+    fn clone(&self) -> Self {
+        ObserverSlot(None)
+    }
+}
+
+/// A point to go back to with [`EGraph::rollback`], returned by
+/// [`EGraph::mark`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoMark {
+    entries: usize,
+    ids: usize,
+    union_find: usize,
+    roots: usize,
+}
+
+/// The inverse of a change to an egraph.
+enum Undo<L, D> {
+    /// An e-class was created (`None`) or is about to change.
+    Class(Id, Option<EClass<L, D>>),
+    /// An e-node was pushed onto the parents of an e-class.
+    Parent(Id),
+    /// The analysis data of an e-class is about to change.
+    Data(Id, D),
+    /// An entry of the hashcons is about to change.
+    Memo(L, Option<Id>),
+}
+
+struct UndoLog<L, D> {
+    entries: Vec<Undo<L, D>>,
+    // `N::Data` isn't always `Clone`, so this is only known when the log is
+    // enabled
+    clone_data: fn(&D) -> D,
+}
+
+impl<L: Clone, D> UndoLog<L, D> {
+    fn clone_class(&self, class: &EClass<L, D>) -> EClass<L, D> {
+        EClass {
+            id: class.id,
+            nodes: class.nodes.clone(),
+            data: (self.clone_data)(&class.data),
+            parents: class.parents.clone(),
+            stamps: class.stamps.clone(),
+            version: class.version,
+        }
+    }
+
+    fn push_class(&mut self, class: &EClass<L, D>) {
+        let before = self.clone_class(class);
+        self.entries.push(Undo::Class(class.id, Some(before)));
+    }
+}
+
+impl<L: Clone, D> Clone for UndoLog<L, D> {
+    fn clone(&self) -> Self {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                Undo::Class(id, class) => {
+                    Undo::Class(*id, class.as_ref().map(|c| self.clone_class(c)))
+                }
+                Undo::Parent(id) => Undo::Parent(*id),
+                Undo::Data(id, data) => Undo::Data(*id, (self.clone_data)(data)),
+                Undo::Memo(node, id) => Undo::Memo(node.clone(), *id),
+            })
+            .collect();
+        UndoLog {
+            entries,
+            clone_data: self.clone_data,
+        }
+    }
+}
+
+/// An e-node with all of its children set to 0, which is equal for two
+/// e-nodes exactly when they [match](Language::matches).
+pub(crate) fn op_key<L: Language>(node: &L) -> L {
+    node.clone().map_children(|_| Id::from(0))
+}
+
 #[cfg(feature = "serde-1")]
 fn default_classes_by_op<K>() -> HashMap<K, HashSet<Id>> {
     HashMap::default()
@@ -107,6 +242,31 @@ impl<L: Language, N: Analysis<L>> Debug for EGraph<L, N> {
     }
 }
 
+/// Prints the size of the egraph followed by its [`dump`](EGraph::dump()),
+/// one canonical e-class per line.
+///
+/// ```
+/// use egg::*;
+/// let mut egraph = EGraph::<SymbolLang, ()>::default();
+/// egraph.add_expr(&"(+ x x)".parse().unwrap());
+/// egraph.rebuild();
+/// assert_eq!(
+///     egraph.to_string(),
+///     "EGraph with 2 nodes in 2 classes\nc0: x\nc1: (+ c0 c0)\n"
+/// );
+/// ```
+impl<L: Language + Display, N: Analysis<L>> Display for EGraph<L, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "EGraph with {} nodes in {} classes",
+            self.total_number_of_nodes(),
+            self.number_of_classes()
+        )?;
+        Display::fmt(&self.dump(), f)
+    }
+}
+
 impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     /// Creates a new, empty `EGraph` with the given `Analysis`
     pub fn new(analysis: N) -> Self {
@@ -120,6 +280,88 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
             memo: Default::default(),
             analysis_pending: Default::default(),
             classes_by_op: Default::default(),
+            roots: Default::default(),
+            union_log: None,
+            observer: Default::default(),
+            undo_log: None,
+            scopes: vec![],
+            version: 0,
+            stamp: 0,
+            match_backend: MatchBackend::default(),
+        }
+    }
+
+    /// Creates a new `EGraph` with the given `Analysis`, containing just
+    /// `expr`.
+    ///
+    /// Returns the egraph along with the id of the root of `expr`.
+    /// The returned egraph has already been [rebuilt](EGraph::rebuild()).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let expr: RecExpr<SymbolLang> = "(+ x (* y 2))".parse().unwrap();
+    /// let (egraph, root) = EGraph::<SymbolLang, ()>::from_expr((), &expr);
+    /// assert_eq!(egraph.number_of_classes(), 5);
+    /// assert_eq!(egraph.lookup_expr(&expr), Some(root));
+    /// ```
+    pub fn from_expr(analysis: N, expr: &RecExpr<L>) -> (Self, Id) {
+        let mut egraph = Self::new(analysis);
+        let root = egraph.add_expr(expr);
+        egraph.rebuild();
+        (egraph, root)
+    }
+
+    /// Returns a copy of this egraph for speculative exploration.
+    ///
+    /// This is like [`clone`](Clone::clone), except that the copy has
+    /// explanations and the [union log](EGraph::with_union_log()) disabled,
+    /// so none of that (potentially large) history is copied.
+    /// The [undo log](EGraph::with_undo_log()) is disabled as well, and the
+    /// copy has no [scopes](EGraph::push()).
+    /// Like clones, the copy has no [observer](EGraph::with_observer()).
+    /// Use it to try out some rules on the copy and compare the result with
+    /// the original, which is left untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let (egraph, root) = EGraph::<SymbolLang, ()>::from_expr((), &"(+ x 0)".parse().unwrap());
+    /// let egraph = egraph.with_explanations_disabled();
+    ///
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("add-0"; "(+ ?a 0)" => "?a")];
+    /// let runner = Runner::default().with_egraph(egraph.fork()).run(rules);
+    ///
+    /// let x = "x".parse().unwrap();
+    /// assert_eq!(runner.egraph.lookup_expr(&x), Some(runner.egraph.find(root)));
+    /// assert_ne!(egraph.lookup_expr(&x), Some(root));
+    /// assert_eq!(egraph.number_of_classes(), 3);
+    /// ```
+    pub fn fork(&self) -> Self
+    where
+        N: Clone,
+        N::Data: Clone,
+    {
+        let mut unionfind = self.unionfind.clone();
+        unionfind.disable_log();
+        Self {
+            analysis: self.analysis.clone(),
+            explain: None,
+            unionfind,
+            memo: self.memo.clone(),
+            pending: self.pending.clone(),
+            analysis_pending: self.analysis_pending.clone(),
+            classes: self.classes.clone(),
+            classes_by_op: self.classes_by_op.clone(),
+            roots: self.roots.clone(),
+            union_log: None,
+            observer: Default::default(),
+            undo_log: None,
+            scopes: vec![],
+            version: self.version,
+            stamp: self.stamp,
+            match_backend: self.match_backend,
+            clean: self.clean,
         }
     }
 
@@ -128,6 +370,16 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.classes.values()
     }
 
+    /// Returns a view of this egraph restricted to the eclasses for which
+    /// `filter` returns `true`.
+    /// See [`EGraphView`] for more.
+    pub fn view<F>(&self, filter: F) -> EGraphView<'_, L, N, F>
+    where
+        F: Fn(&EClass<L, N::Data>) -> bool,
+    {
+        EGraphView::new(self, filter)
+    }
+
     /// Returns an mutating iterator over the eclasses in the egraph.
     pub fn classes_mut(&mut self) -> impl ExactSizeIterator<Item = &mut EClass<L, N::Data>> {
         self.classes.values_mut()
@@ -146,9 +398,15 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.memo.is_empty()
     }
 
-    /// Returns the number of enodes in the `EGraph`.
+    /// Returns the size of the hashcons index.
     ///
-    /// Actually returns the size of the hashcons index.
+    /// Right after a [`rebuild`](EGraph::rebuild()) this is the number of
+    /// distinct e-nodes, the same as
+    /// [`total_number_of_nodes`](EGraph::total_number_of_nodes()).
+    /// In between, unions can leave several entries for e-nodes that have
+    /// become the same, so it may be larger.
+    /// It is cheap to call, which is why [`Runner`]'s node limit uses it
+    /// by default.
     /// # Example
     /// ```
     /// use egg::{*, SymbolLang as S};
@@ -167,6 +425,9 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     }
 
     /// Iterates over the classes, returning the total number of nodes.
+    ///
+    /// This takes time linear in the number of e-classes; see
+    /// [`total_size`](EGraph::total_size()) for a constant-time estimate.
     pub fn total_number_of_nodes(&self) -> usize {
         self.classes().map(|c| c.len()).sum()
     }
@@ -176,6 +437,62 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.classes.len()
     }
 
+    /// Returns the number of ids ever handed out, canonical or not.
+    pub(crate) fn number_of_ids(&self) -> usize {
+        self.unionfind.size()
+    }
+
+    /// Returns a summary of the size of this egraph.
+    /// See [`EGraphReport`].
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let (egraph, _) = EGraph::<SymbolLang, ()>::from_expr((), &"(+ x x)".parse().unwrap());
+    /// let report = egraph.report();
+    /// assert_eq!(report.classes, 2);
+    /// assert_eq!(report.nodes, 2);
+    /// assert_eq!(report.memo_size, 2);
+    /// // the second `x` is found in the hashcons, so it gets no new id
+    /// assert_eq!(report.unionfind_size, 2);
+    /// println!("{}", report);
+    /// ```
+    pub fn report(&self) -> EGraphReport {
+        use std::mem::size_of;
+        let class_bytes: usize = self
+            .classes()
+            .map(|class| {
+                size_of::<(Id, EClass<L, N::Data>)>()
+                    + class.nodes.capacity() * size_of::<L>()
+                    + class.parents.capacity() * size_of::<(L, Id)>()
+            })
+            .sum();
+        EGraphReport {
+            classes: self.number_of_classes(),
+            nodes: self.total_number_of_nodes(),
+            memo_size: self.total_size(),
+            unionfind_size: self.unionfind.size(),
+            estimated_bytes: class_bytes
+                + self.memo.capacity() * size_of::<(L, Id)>()
+                + self.unionfind.size() * size_of::<Id>()
+                + self.pending.capacity() * size_of::<(L, Id)>(),
+        }
+    }
+
+    /// Sets the [`MatchBackend`] used by the [`Pattern`]s searching this
+    /// egraph, unless they [choose their own](Pattern::with_backend()).
+    /// Default: [`MatchBackend::TopDown`]
+    pub fn with_match_backend(mut self, backend: MatchBackend) -> Self {
+        self.match_backend = backend;
+        self
+    }
+
+    /// Returns the [`MatchBackend`] used by the [`Pattern`]s searching this
+    /// egraph, see [`EGraph::with_match_backend`].
+    pub fn match_backend(&self) -> MatchBackend {
+        self.match_backend
+    }
+
     /// Enable explanations for this `EGraph`.
     /// This allows the egraph to explain why two expressions are
     /// equivalent with the [`explain_equivalence`](EGraph::explain_equivalence) function.
@@ -186,10 +503,339 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         if self.total_size() > 0 {
             panic!("Need to set explanations enabled before adding any expressions to the egraph.");
         }
+        assert!(
+            self.undo_log.is_none(),
+            "Can't enable explanations with the undo log enabled"
+        );
         self.explain = Some(Explain::new());
         self
     }
 
+    /// Enable recording of unions for [`take_union_log`](EGraph::take_union_log()).
+    pub fn with_union_log(mut self) -> Self {
+        self.union_log.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Returns and clears the unions performed since the last call,
+    /// as `(class1, class2, leader)` triples in the order they happened.
+    ///
+    /// `class1` and `class2` are the canonical ids of the two e-classes at
+    /// the time they were unioned, and `leader` is whichever of the two is
+    /// canonical afterwards. This lets you keep external maps keyed by [`Id`]
+    /// up to date incrementally, typically by calling this after each
+    /// [`rebuild`](EGraph::rebuild()).
+    ///
+    /// Always returns an empty list unless the log was enabled with
+    /// [`with_union_log`](EGraph::with_union_log()).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default().with_union_log();
+    /// let x = egraph.add(S::leaf("x"));
+    /// let y = egraph.add(S::leaf("y"));
+    /// let fx = egraph.add(S::new("f", vec![x]));
+    /// let fy = egraph.add(S::new("f", vec![y]));
+    /// egraph.union(x, y);
+    /// egraph.rebuild();
+    ///
+    /// let log = egraph.take_union_log();
+    /// // the congruence of (f x) and (f y) is logged too
+    /// assert_eq!(log.len(), 2);
+    /// assert_eq!((log[0].0, log[0].1), (x, y));
+    /// assert_eq!(log[1].2, egraph.find(fx));
+    /// assert_eq!(egraph.find(fx), egraph.find(fy));
+    /// assert!(egraph.take_union_log().is_empty());
+    /// ```
+    pub fn take_union_log(&mut self) -> Vec<(Id, Id, Id)> {
+        self.union_log
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Sets the [`EGraphObserver`] notified of every change to this egraph,
+    /// replacing any previous one.
+    ///
+    /// Clones of the egraph don't have an observer.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Default)]
+    /// struct Counts {
+    ///     adds: usize,
+    ///     unions: usize,
+    /// }
+    ///
+    /// struct Counter(Arc<Mutex<Counts>>);
+    ///
+    /// impl EGraphObserver<S> for Counter {
+    ///     fn on_add(&mut self, _id: Id, _enode: &S) {
+    ///         self.0.lock().unwrap().adds += 1;
+    ///     }
+    ///     fn on_union(&mut self, _id1: Id, _id2: Id, _leader: Id) {
+    ///         self.0.lock().unwrap().unions += 1;
+    ///     }
+    /// }
+    ///
+    /// let counts = Arc::new(Mutex::new(Counts::default()));
+    /// let mut egraph = EGraph::<S, ()>::default().with_observer(Counter(counts.clone()));
+    /// let x = egraph.add(S::leaf("x"));
+    /// let y = egraph.add(S::leaf("y"));
+    /// egraph.add(S::new("f", vec![x]));
+    /// egraph.add(S::new("f", vec![y]));
+    /// egraph.add(S::leaf("x"));
+    /// egraph.union(x, y);
+    /// egraph.rebuild();
+    ///
+    /// let counts = counts.lock().unwrap();
+    /// assert_eq!(counts.adds, 4);
+    /// // the congruence of (f x) and (f y) is observed too
+    /// assert_eq!(counts.unions, 2);
+    /// ```
+    pub fn with_observer(mut self, observer: impl EGraphObserver<L> + 'static) -> Self {
+        self.observer = ObserverSlot(Some(Box::new(observer)));
+        self
+    }
+
+    /// Enables the undo log, so changes can be undone with
+    /// [`rollback`](EGraph::rollback()).
+    ///
+    /// Rather than copying the whole egraph, the log records the previous
+    /// state of just the parts that change, like the e-classes that get
+    /// merged.
+    /// That's cheap enough to speculatively apply a risky rule set, and
+    /// roll it back if it blows the budget or breaks an invariant.
+    /// Entries are kept until the log is disabled with
+    /// [`without_undo_log`](EGraph::without_undo_log()).
+    ///
+    /// # Panics
+    /// Panics if explanations are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let expr = "(+ a (+ b (+ c d)))".parse().unwrap();
+    /// let (egraph, _) = EGraph::<SymbolLang, ()>::from_expr((), &expr);
+    /// let mut egraph = egraph.with_undo_log();
+    /// let mark = egraph.mark();
+    ///
+    /// let risky: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rw!("assoc"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    /// ];
+    /// let runner = Runner::default()
+    ///     .with_egraph(egraph)
+    ///     .with_node_limit(50)
+    ///     .run(risky);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::NodeLimit(_))));
+    ///
+    /// let mut egraph = runner.egraph;
+    /// egraph.rollback(mark);
+    /// assert_eq!(egraph.total_number_of_nodes(), 7);
+    /// assert!(egraph.lookup_expr(&"(+ b a)".parse().unwrap()).is_none());
+    /// ```
+    pub fn with_undo_log(mut self) -> Self
+    where
+        N::Data: Clone,
+    {
+        self.enable_undo_log();
+        self
+    }
+
+    /// Enables the undo log, returning whether it was disabled.
+    fn enable_undo_log(&mut self) -> bool
+    where
+        N::Data: Clone,
+    {
+        assert!(
+            self.explain.is_none(),
+            "Can't enable the undo log with explanations enabled"
+        );
+        if self.undo_log.is_some() {
+            return false;
+        }
+        self.unionfind.enable_log();
+        self.undo_log = Some(UndoLog {
+            entries: vec![],
+            clone_data: N::Data::clone,
+        });
+        true
+    }
+
+    /// Disables the [undo log](EGraph::with_undo_log()), dropping its
+    /// entries.
+    ///
+    /// # Panics
+    /// Panics if there are open [scopes](EGraph::push()).
+    pub fn without_undo_log(mut self) -> Self {
+        assert!(
+            self.scopes.is_empty(),
+            "Can't disable the undo log inside a scope"
+        );
+        self.unionfind.disable_log();
+        self.undo_log = None;
+        self
+    }
+
+    /// Opens a new scope, like `push` in an SMT solver.
+    ///
+    /// Everything added to the egraph inside the scope, and every union,
+    /// is retracted by the matching [`pop`](EGraph::pop()).
+    /// Scopes nest, and use the [undo log](EGraph::with_undo_log()), which
+    /// is enabled for as long as there are open scopes if it wasn't already.
+    /// This makes case splits cheap: assume a case by unioning, explore it,
+    /// and pop, without copying the egraph for each case.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean) or explanations
+    /// are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("if-true"; "(if true ?a ?b)" => "?a"),
+    ///     rw!("if-false"; "(if false ?a ?b)" => "?b"),
+    /// ];
+    /// let expr = "(if c (+ x 0) (- x 0))".parse().unwrap();
+    /// let (mut egraph, root) = EGraph::<SymbolLang, ()>::from_expr((), &expr);
+    /// let c = egraph.lookup_expr(&"c".parse().unwrap()).unwrap();
+    ///
+    /// // the if only simplifies once c is known
+    /// let mut results = vec![];
+    /// for case in ["true", "false"] {
+    ///     egraph.push();
+    ///     let value = egraph.add_expr(&case.parse().unwrap());
+    ///     egraph.union(c, value);
+    ///     egraph.rebuild();
+    ///
+    ///     let runner = Runner::default().with_egraph(egraph).run(rules);
+    ///     egraph = runner.egraph;
+    ///     let (_, best) = Extractor::new(&egraph, AstSize).find_best(root);
+    ///     results.push(best.to_string());
+    ///     egraph.pop();
+    /// }
+    /// assert_eq!(results, ["(+ x 0)", "(- x 0)"]);
+    /// assert_eq!(egraph.lookup_expr(&"true".parse().unwrap()), None);
+    /// assert_eq!(egraph.number_of_scopes(), 0);
+    /// ```
+    pub fn push(&mut self)
+    where
+        N::Data: Clone,
+    {
+        let enabled = self.enable_undo_log();
+        let mark = self.mark();
+        self.scopes.push((mark, enabled));
+    }
+
+    /// Closes the innermost scope opened by [`push`](EGraph::push()), rolling
+    /// back everything that happened in it.
+    ///
+    /// # Panics
+    /// Panics if there is no open scope.
+    pub fn pop(&mut self) {
+        let (mark, enabled) = self.scopes.pop().expect("No scope to pop");
+        self.rollback(mark);
+        if enabled {
+            self.unionfind.disable_log();
+            self.undo_log = None;
+        }
+    }
+
+    /// Returns the number of scopes opened by [`push`](EGraph::push()) and
+    /// not popped yet.
+    pub fn number_of_scopes(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Returns the current position in the [undo log](EGraph::with_undo_log()).
+    ///
+    /// # Panics
+    /// Panics if the undo log is disabled or the egraph isn't
+    /// [`clean`](EGraph::clean).
+    pub fn mark(&mut self) -> UndoMark {
+        assert!(self.clean, "Must rebuild the egraph before marking it");
+        let entries = self
+            .undo_log
+            .as_ref()
+            .expect("The undo log is disabled")
+            .entries
+            .len();
+        UndoMark {
+            entries,
+            ids: self.unionfind.size(),
+            union_find: self.unionfind.log_len(),
+            roots: self.roots.len(),
+        }
+    }
+
+    /// Undoes every add and union since `mark` was taken, as well as the
+    /// [rebuilds](EGraph::rebuild()) and analysis updates that followed.
+    ///
+    /// The egraph is left [`clean`](EGraph::clean), and the same `mark` can be
+    /// rolled back to again later.
+    /// Marks taken after `mark` are no longer valid, and neither are
+    /// [scopes](EGraph::push()) opened after it.
+    /// Changes made through [`classes_mut`](EGraph::classes_mut()), and to
+    /// the state of the [`Analysis`] itself, are not undone;
+    /// changes made through `egraph[id]` are.
+    ///
+    /// # Panics
+    /// Panics if the undo log is disabled.
+    pub fn rollback(&mut self, mark: UndoMark) {
+        let undo = self.undo_log.as_mut().expect("The undo log is disabled");
+        assert!(
+            mark.entries <= undo.entries.len(),
+            "This mark is no longer valid"
+        );
+        self.version += 1;
+        let version = self.version;
+        for entry in undo.entries.drain(mark.entries..).rev() {
+            match entry {
+                Undo::Class(id, None) => {
+                    self.classes.remove(&id);
+                }
+                Undo::Class(id, Some(mut class)) => {
+                    class.version = version;
+                    self.classes.insert(id, class);
+                }
+                Undo::Parent(id) => {
+                    self.classes.get_mut(&id).unwrap().parents.pop();
+                }
+                Undo::Data(id, data) => {
+                    let class = self.classes.get_mut(&id).unwrap();
+                    class.data = data;
+                    class.version = version;
+                }
+                Undo::Memo(node, None) => {
+                    self.memo.remove(&node);
+                }
+                Undo::Memo(node, Some(id)) => {
+                    self.memo.insert(node, id);
+                }
+            }
+        }
+        self.unionfind.rollback(mark.ids, mark.union_find);
+        self.roots.truncate(mark.roots);
+        self.pending.clear();
+        self.analysis_pending = Default::default();
+        // the e-nodes are canonical again, this just rebuilds `classes_by_op`
+        self.rebuild_classes();
+        debug_assert!(self.check_memo());
+        self.clean = true;
+    }
+
+    /// Removes the [observer](EGraph::with_observer()) of this egraph
+    /// and returns it.
+    pub fn take_observer(&mut self) -> Option<Box<dyn EGraphObserver<L>>> {
+        self.observer.0.take()
+    }
+
     /// By default, egg runs a greedy algorithm to reduce the size of resulting explanations (without complexity overhead).
     /// Use this function to turn this algorithm off.
     pub fn without_explanation_length_optimization(mut self) -> Self {
@@ -236,6 +882,88 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.rebuild();
     }
 
+    /// Copies all the e-classes of `other` into this egraph, returning a map
+    /// from the ids of `other`'s e-classes to the e-classes they ended up in.
+    ///
+    /// Unlike [`egraph_union`](EGraph::egraph_union()), this doesn't need
+    /// explanations to be enabled on `other`.
+    /// Terms that are in both egraphs end up in the same e-class, so this
+    /// combines egraphs built separately, for example one per function.
+    /// The unions are justified as `"absorb"` when explanations are enabled
+    /// here. Analysis data is recomputed rather than copied from `other`.
+    /// E-classes of `other` that represent no finite term are skipped.
+    /// This egraph is [rebuilt](EGraph::rebuild()) afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut f = EGraph::<SymbolLang, ()>::default();
+    /// let a = f.add_expr(&"(+ x 1)".parse().unwrap());
+    /// let b = f.add_expr(&"(+ 1 x)".parse().unwrap());
+    /// f.union(a, b);
+    /// f.rebuild();
+    ///
+    /// let mut g = EGraph::<SymbolLang, ()>::default();
+    /// let c = g.add_expr(&"(+ 1 x)".parse().unwrap());
+    /// let d = g.add_expr(&"(inc x)".parse().unwrap());
+    /// g.union(c, d);
+    /// g.rebuild();
+    ///
+    /// let map = f.absorb(&g);
+    /// assert_eq!(map[&g.find(d)], f.find(a));
+    /// assert_eq!(f.lookup_expr(&"(inc x)".parse().unwrap()), Some(f.find(a)));
+    /// ```
+    pub fn absorb(&mut self, other: &EGraph<L, N>) -> HashMap<Id, Id> {
+        // each e-node of `other` is copied once all its children are mapped,
+        // so count the children it's still waiting for
+        let mut pending: HashMap<(Id, usize), usize> = HashMap::default();
+        let mut waiting: HashMap<Id, Vec<(Id, usize)>> = HashMap::default();
+        let mut ready: Vec<(Id, usize)> = vec![];
+        for class in other.classes() {
+            for (i, node) in class.nodes.iter().enumerate() {
+                let mut children: Vec<Id> =
+                    node.children().iter().map(|&c| other.find(c)).collect();
+                children.sort_unstable();
+                children.dedup();
+                if children.is_empty() {
+                    ready.push((class.id, i));
+                } else {
+                    pending.insert((class.id, i), children.len());
+                    for child in children {
+                        waiting.entry(child).or_default().push((class.id, i));
+                    }
+                }
+            }
+        }
+
+        let mut map: HashMap<Id, Id> = HashMap::default();
+        while let Some((class, i)) = ready.pop() {
+            let node = other[class].nodes[i].clone();
+            let id = self.add(node.map_children(|c| map[&other.find(c)]));
+            match map.get(&class) {
+                Some(&existing) => {
+                    self.union_trusted(existing, id, "absorb");
+                }
+                None => {
+                    map.insert(class, id);
+                    for &parent in waiting.get(&class).into_iter().flatten() {
+                        let count = pending.get_mut(&parent).unwrap();
+                        *count -= 1;
+                        if *count == 0 {
+                            ready.push(parent);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.rebuild();
+        for id in map.values_mut() {
+            *id = self.find(*id);
+        }
+        map
+    }
+
     fn from_enodes(enodes: Vec<(L, Id)>, analysis: N) -> Self {
         let mut egraph = Self::new(analysis);
         let mut ids: HashMap<Id, Id> = Default::default();
@@ -334,14 +1062,96 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     }
 
     /// Pick a representative term for a given Id.
+    ///
+    /// With explanations enabled, this is the term that was added with that
+    /// exact Id. Otherwise, this is some term of the eclass, which is
+    /// cheaper to find than the best term from an [`Extractor`] and handy
+    /// for logging and error messages.
+    /// Panics if the eclass doesn't represent any finite term.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let a = egraph.add_expr(&"(f (g x))".parse().unwrap());
+    /// let b = egraph.add_expr(&"y".parse().unwrap());
+    /// egraph.union(a, b);
+    /// egraph.rebuild();
+    /// let expr = egraph.id_to_expr(a);
+    /// assert_eq!(egraph.lookup_expr(&expr), Some(egraph.find(a)));
+    /// ```
     pub fn id_to_expr(&self, id: Id) -> RecExpr<L> {
         if let Some(explain) = &self.explain {
             explain.node_to_recexpr(id)
         } else {
-            panic!("Use runner.with_explanations_enabled() or egraph.with_explanations_enabled() before running to get unique expressions per id");
+            self.some_term(id)
         }
     }
 
+    /// Finds some finite term of an eclass by picking, for each reachable
+    /// eclass, an enode whose children have already been picked.
+    fn some_term(&self, id: Id) -> RecExpr<L> {
+        let root = self.find(id);
+        let reachable = self.descendants(&[root], usize::MAX);
+
+        let mut picked: HashMap<Id, &L> = HashMap::default();
+        let mut did_something = true;
+        while did_something && !picked.contains_key(&root) {
+            did_something = false;
+            for &class in &reachable {
+                if picked.contains_key(&class) {
+                    continue;
+                }
+                let ready = |node: &&L| node.all(|c| picked.contains_key(&self.find(c)));
+                if let Some(node) = self[class].nodes.iter().find(ready) {
+                    picked.insert(class, node);
+                    did_something = true;
+                }
+            }
+        }
+
+        let node = picked
+            .get(&root)
+            .unwrap_or_else(|| panic!("Eclass {} doesn't represent a finite term", root));
+        node.build_recexpr(|child| (*picked[&self.find(child)]).clone())
+    }
+
+    /// Lazily enumerates the distinct terms represented by an eclass, up
+    /// to `max_depth` deep.
+    ///
+    /// Leaves have depth 1, like with [`AstDepth`], so the depth bound
+    /// also keeps cycles in the egraph from making the enumeration infinite.
+    /// The terms are produced one at a time, so there's no need to
+    /// collect what may be exponentially many of them.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let x = egraph.add_expr(&"x".parse().unwrap());
+    /// let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// let y = egraph.add_expr(&"y".parse().unwrap());
+    /// egraph.union(x, fx);
+    /// egraph.union(x, y);
+    /// egraph.rebuild();
+    ///
+    /// let mut terms: Vec<String> = egraph.enumerate(x, 2).map(|t| t.to_string()).collect();
+    /// terms.sort();
+    /// assert_eq!(terms, ["(f x)", "(f y)", "x", "y"]);
+    /// assert_eq!(egraph.enumerate(x, 3).count(), 6);
+    /// ```
+    pub fn enumerate(&self, id: Id, max_depth: usize) -> impl Iterator<Item = RecExpr<L>> + '_ {
+        assert!(self.clean, "Must rebuild the egraph before enumerating");
+        enumerate_terms(self, id, max_depth).map(|term| {
+            let mut expr = RecExpr::default();
+            term.add_to(&mut expr);
+            expr
+        })
+    }
+
     /// Like [`id_to_expr`](EGraph::id_to_expr), but creates a pattern instead of a term.
     /// When an eclass listed in the given substitutions is found, it creates a variable.
     /// It also adds this variable and the corresponding Id value to the resulting [`Subst`]
@@ -507,6 +1317,8 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
             egraph: self,
             config: vec![],
             use_anchors: true,
+            show_class_ids: false,
+            class_metadata: None,
         }
     }
 }
@@ -527,6 +1339,9 @@ impl<L: Language, N: Analysis<L>> std::ops::Index<Id> for EGraph<L, N> {
 impl<L: Language, N: Analysis<L>> std::ops::IndexMut<Id> for EGraph<L, N> {
     fn index_mut(&mut self, id: Id) -> &mut Self::Output {
         let id = self.find_mut(id);
+        if let (Some(undo), Some(class)) = (&mut self.undo_log, self.classes.get(&id)) {
+            undo.push_class(class);
+        }
         self.classes
             .get_mut(&id)
             .unwrap_or_else(|| panic!("Invalid id {}", id))
@@ -593,13 +1408,18 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         let mut new_node_q = Vec::with_capacity(nodes.len());
         for node in nodes {
             match node {
+                ENodeOrVar::Var(var) if var.is_segment() => {
+                    // spliced into the parent by `instantiate_node`
+                    new_ids.push(Id::default());
+                    new_node_q.push(false);
+                }
                 ENodeOrVar::Var(var) => {
                     let id = self.find(subst[*var]);
                     new_ids.push(id);
                     new_node_q.push(false);
                 }
                 ENodeOrVar::ENode(node) => {
-                    let new_node = node.clone().map_children(|i| new_ids[usize::from(i)]);
+                    let new_node = pattern::instantiate_node(node, nodes, &new_ids, subst);
                     let size_before = self.unionfind.size();
                     let next_id = self.add_internal(new_node);
                     if self.unionfind.size() > size_before {
@@ -683,6 +1503,43 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         Some(new_ids)
     }
 
+    /// Lookup the eclass of the term you would get by instantiating `pat`
+    /// with `subst`, without adding anything to the egraph.
+    ///
+    /// Returns `None` if some part of that term isn't in the egraph.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let x = egraph.add_expr(&"x".parse().unwrap());
+    /// let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// let f: Pattern<SymbolLang> = "(f ?a)".parse().unwrap();
+    /// let g: Pattern<SymbolLang> = "(g ?a)".parse().unwrap();
+    /// let subst: Subst = vec![("?a".parse().unwrap(), x)].into_iter().collect();
+    /// assert_eq!(egraph.lookup_instantiation(&f.ast, &subst), Some(fx));
+    /// assert_eq!(egraph.lookup_instantiation(&g.ast, &subst), None);
+    /// assert_eq!(egraph.number_of_classes(), 2);
+    /// ```
+    pub fn lookup_instantiation(&self, pat: &PatternAst<L>, subst: &Subst) -> Option<Id> {
+        let nodes = pat.as_ref();
+        let mut ids: Vec<Id> = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            let id = match node {
+                ENodeOrVar::Var(v) if v.is_segment() => {
+                    subst.get_segment(*v)?;
+                    Id::default()
+                }
+                ENodeOrVar::Var(v) => self.find(*subst.get(*v)?),
+                ENodeOrVar::ENode(n) => {
+                    self.lookup(pattern::instantiate_node(n, nodes, &ids, subst))?
+                }
+            };
+            ids.push(id);
+        }
+        ids.last().copied()
+    }
+
     /// Adds an enode to the [`EGraph`].
     ///
     /// When adding an enode, to the egraph, [`add`] it performs
@@ -736,28 +1593,133 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     fn make_new_eclass(&mut self, enode: L) -> Id {
         let id = self.unionfind.make_set();
         log::trace!("  ...adding to {}", id);
+        self.version += 1;
         let class = EClass {
             id,
             nodes: vec![enode.clone()],
             data: N::make(self, &enode),
             parents: Default::default(),
+            stamps: vec![self.stamp],
+            version: self.version,
         };
 
         // add this enode to the parent lists of its children
         enode.for_each(|child| {
             let tup = (enode.clone(), id);
-            self[child].parents.push(tup);
+            let child = self.find_mut(child);
+            self.classes.get_mut(&child).unwrap().parents.push(tup);
+            if let Some(undo) = &mut self.undo_log {
+                undo.entries.push(Undo::Parent(child));
+            }
         });
 
         // TODO is this needed?
         self.pending.push((enode.clone(), id));
 
+        if let Some(observer) = &mut self.observer.0 {
+            observer.on_add(id, &enode);
+        }
+
+        if let Some(undo) = &mut self.undo_log {
+            undo.entries.push(Undo::Class(id, None));
+            undo.entries.push(Undo::Memo(enode.clone(), None));
+        }
+
         self.classes.insert(id, class);
         assert!(self.memo.insert(enode, id).is_none());
 
         id
     }
 
+    /// Returns the stamp given to the enodes added to this egraph, and
+    /// kept by them through unions and rebuilds.
+    /// See [`EClass::iter_stamped`].
+    ///
+    /// A [`Runner`] sets this to the index of the current iteration, so
+    /// the stamp of an enode tells how recently it was added;
+    /// [`RecencyScheduler`] uses that to focus on the frontier of the
+    /// egraph.
+    /// It's 0 for egraphs that aren't run by a [`Runner`].
+    pub fn stamp(&self) -> usize {
+        self.stamp
+    }
+
+    /// Sets the [stamp](EGraph::stamp()) given to the enodes added from now
+    /// on.
+    pub fn set_stamp(&mut self, stamp: usize) {
+        self.stamp = stamp;
+    }
+
+    /// Returns the eclasses created or merged after `version`, along with
+    /// their ancestors up to `levels` parents up, sorted by id.
+    pub(crate) fn classes_modified_since(&self, version: usize, levels: usize) -> Vec<Id> {
+        let ids: Vec<Id> = self
+            .classes()
+            .filter(|class| class.version > version)
+            .map(|class| class.id)
+            .collect();
+        self.ancestors(&ids, levels)
+    }
+
+    /// Returns the canonical ids of the given e-classes and of their
+    /// ancestors up to `levels` [parents](EClass::parents()) above them,
+    /// sorted.
+    ///
+    /// With `levels` set to 0, this just canonicalizes and dedups `ids`.
+    /// See [`Pattern::search_above`] for searching only these e-classes.
+    pub fn ancestors(&self, ids: &[Id], levels: usize) -> Vec<Id> {
+        self.walk(ids, levels, |class, next| {
+            for (_, parent) in &class.parents {
+                next(*parent)
+            }
+        })
+    }
+
+    /// Returns the canonical ids of the given e-classes and of the e-classes
+    /// reachable from them through at most `levels` e-nodes, sorted.
+    ///
+    /// With `levels` set to 0, this just canonicalizes and dedups `ids`, and
+    /// with `levels` set to [`usize::MAX`] it returns every e-class
+    /// reachable from them.
+    /// See [`Pattern::search_in`] for searching only these e-classes.
+    pub fn descendants(&self, ids: &[Id], levels: usize) -> Vec<Id> {
+        self.walk(ids, levels, |class, next| {
+            for node in &class.nodes {
+                node.for_each(&mut *next)
+            }
+        })
+    }
+
+    /// Breadth-first walk from `ids` up to `levels` steps, where `step`
+    /// calls its second argument on the neighbors of an e-class.
+    fn walk(
+        &self,
+        ids: &[Id],
+        levels: usize,
+        step: impl Fn(&EClass<L, N::Data>, &mut dyn FnMut(Id)),
+    ) -> Vec<Id> {
+        let mut frontier: Vec<Id> = ids.iter().map(|&id| self.find(id)).collect();
+        let mut ids: HashSet<Id> = frontier.iter().copied().collect();
+        for _ in 0..levels {
+            if frontier.is_empty() {
+                break;
+            }
+            let mut next = vec![];
+            for id in frontier {
+                step(&self[id], &mut |neighbor| {
+                    let neighbor = self.find(neighbor);
+                    if ids.insert(neighbor) {
+                        next.push(neighbor);
+                    }
+                });
+            }
+            frontier = next;
+        }
+        let mut ids: Vec<Id> = ids.into_iter().collect();
+        ids.sort_unstable();
+        ids
+    }
+
     /// Checks whether two [`RecExpr`]s are equivalent.
     /// Returns a list of id where both expression are represented.
     /// In most cases, there will none or exactly one id.
@@ -825,6 +1787,52 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.perform_union(from, to, Some(Justification::Rule(reason.into())), false)
     }
 
+    /// Adds and unions many pairs of expressions at once, for example facts
+    /// learned by an external decision procedure.
+    ///
+    /// Each union is justified by [`Justification::Axiom`] with `reason`, so
+    /// when explanations are enabled these steps show up as axioms under
+    /// that name rather than as rewrites.
+    /// The egraph is [rebuilt](EGraph::rebuild()) once after all the
+    /// pairs have been added.
+    /// Returns the number of pairs that were not already equivalent.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default().with_explanations_enabled();
+    /// let facts: Vec<(RecExpr<SymbolLang>, RecExpr<SymbolLang>)> = vec![
+    ///     ("(f x)".parse().unwrap(), "y".parse().unwrap()),
+    ///     ("y".parse().unwrap(), "z".parse().unwrap()),
+    /// ];
+    /// assert_eq!(egraph.assert_equalities(&facts, "smt"), 2);
+    ///
+    /// let fx = "(f x)".parse().unwrap();
+    /// let z = "z".parse().unwrap();
+    /// assert_eq!(egraph.lookup_expr(&fx), egraph.lookup_expr(&z));
+    /// let explanation = egraph.explain_equivalence(&fx, &z).get_flat_string();
+    /// assert!(explanation.contains("(Axiom=> smt"));
+    /// ```
+    pub fn assert_equalities(
+        &mut self,
+        pairs: &[(RecExpr<L>, RecExpr<L>)],
+        reason: impl Into<Symbol>,
+    ) -> usize {
+        let reason = reason.into();
+        let mut n_unions = 0;
+        for (from, to) in pairs {
+            let id1 = self.add_expr_internal(from);
+            let size_before = self.unionfind.size();
+            let id2 = self.add_expr_internal(to);
+            let rhs_new = self.unionfind.size() > size_before;
+            if self.perform_union(id1, id2, Some(Justification::Axiom(reason)), rhs_new) {
+                n_unions += 1;
+            }
+        }
+        self.rebuild();
+        n_unions
+    }
+
     /// Unions two eclasses given their ids.
     ///
     /// The given ids need not be canonical.
@@ -860,13 +1868,14 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         let mut id1 = self.find_mut(enode_id1);
         let mut id2 = self.find_mut(enode_id2);
         if id1 == id2 {
-            if let Some(Justification::Rule(_)) = rule {
+            if let Some(Justification::Rule(_) | Justification::Axiom(_)) = rule {
                 if let Some(explain) = &mut self.explain {
                     explain.alternate_rewrite(enode_id1, enode_id2, rule.unwrap());
                 }
             }
             return false;
         }
+        let (root1, root2) = (id1, id2);
         // make sure class2 has fewer parents
         let class1_parents = self.classes[&id1].parents.len();
         let class2_parents = self.classes[&id2].parents.len();
@@ -880,13 +1889,27 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
         // make id1 the new root
         self.unionfind.union(id1, id2);
+        if let Some(log) = &mut self.union_log {
+            log.push((root1, root2, id1));
+        }
+        if let Some(observer) = &mut self.observer.0 {
+            observer.on_union(root1, root2, id1);
+        }
 
         assert_ne!(id1, id2);
+        if let Some(undo) = &mut self.undo_log {
+            undo.push_class(&self.classes[&id1]);
+            undo.push_class(&self.classes[&id2]);
+        }
+        self.version += 1;
         let class2 = self.classes.remove(&id2).unwrap();
         let class1 = self.classes.get_mut(&id1).unwrap();
         assert_eq!(id1, class1.id);
+        class1.version = self.version;
 
         self.pending.extend(class2.parents.iter().cloned());
+        #[cfg(debug_assertions)]
+        self.analysis.debug_check_merge(&class1.data, &class2.data);
         let did_merge = self.analysis.merge(&mut class1.data, class2.data);
         if did_merge.0 {
             self.analysis_pending.extend(class1.parents.iter().cloned());
@@ -896,6 +1919,7 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         }
 
         concat_vecs(&mut class1.nodes, class2.nodes);
+        concat_vecs(&mut class1.stamps, class2.stamps);
         concat_vecs(&mut class1.parents, class2.parents);
 
         N::modify(self, id1);
@@ -910,6 +1934,10 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     pub fn set_analysis_data(&mut self, id: Id, new_data: N::Data) {
         let id = self.find_mut(id);
         let class = self.classes.get_mut(&id).unwrap();
+        if let Some(undo) = &mut self.undo_log {
+            let before = (undo.clone_data)(&class.data);
+            undo.entries.push(Undo::Data(id, before));
+        }
         class.data = new_data;
         self.analysis_pending.extend(class.parents.iter().cloned());
         N::modify(self, id)
@@ -922,8 +1950,25 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     /// This method returns a wrapper that implements [`Debug`] in a
     /// slightly nicer way, just dumping enodes in each eclass.
     ///
+    /// If the language implements [`Display`], the wrapper also
+    /// implements [`Display`], printing one line per eclass like
+    /// `c12: (+ c3 c4), (<< c3 c7)`, sorted so that the same egraph always
+    /// prints the same way. This is handy for snapshot tests and for diffing
+    /// two runs.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let a = egraph.add_expr(&"(+ x 1)".parse().unwrap());
+    /// let b = egraph.add_expr(&"(+ 1 x)".parse().unwrap());
+    /// egraph.union(a, b);
+    /// egraph.rebuild();
+    /// assert_eq!(egraph.dump().to_string(), "c0: x\nc1: 1\nc2: (+ c0 c1), (+ c1 c0)\n");
+    /// ```
+    ///
     /// [`Debug`]: std::fmt::Debug
-    pub fn dump(&self) -> impl Debug + '_ {
+    pub fn dump(&self) -> EGraphDump<'_, L, N> {
         EGraphDump(self)
     }
 }
@@ -964,25 +2009,40 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
         let mut trimmed = 0;
         let uf = &mut self.unionfind;
+        let undo_log = &mut self.undo_log;
 
         for class in self.classes.values_mut() {
+            if let Some(undo) = undo_log.as_mut() {
+                let changes = class.nodes.windows(2).any(|w| w[0] >= w[1])
+                    || class.nodes.iter().any(|n| n.any(|c| uf.find(c) != c));
+                if changes {
+                    undo.push_class(class);
+                }
+            }
             let old_len = class.len();
             class
                 .nodes
                 .iter_mut()
                 .for_each(|n| n.update_children(|id| uf.find_mut(id)));
-            class.nodes.sort_unstable();
-            class.nodes.dedup();
+            if class.stamps.len() != class.nodes.len() {
+                // the nodes were modified directly
+                let oldest = class.stamps.iter().copied().min().unwrap_or(0);
+                class.stamps = vec![oldest; class.nodes.len()];
+            }
+            if class.nodes.windows(2).any(|w| w[0] >= w[1]) {
+                let mut stamped: Vec<(L, usize)> =
+                    class.nodes.drain(..).zip(class.stamps.drain(..)).collect();
+                // duplicates keep the oldest stamp
+                stamped.sort_unstable();
+                stamped.dedup_by(|a, b| a.0 == b.0);
+                let (nodes, stamps) = stamped.into_iter().unzip();
+                class.nodes = nodes;
+                class.stamps = stamps;
+            }
 
             trimmed += old_len - class.nodes.len();
 
-            let mut add = |n: &L| {
-                #[allow(enum_intrinsics_non_enums)]
-                classes_by_op
-                    .entry(std::mem::discriminant(n))
-                    .or_default()
-                    .insert(class.id)
-            };
+            let mut add = |n: &L| classes_by_op.entry(op_key(n)).or_default().insert(class.id);
 
             // we can go through the ops in order to dedup them, becaue we
             // just sorted them
@@ -1049,6 +2109,10 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         while !self.pending.is_empty() || !self.analysis_pending.is_empty() {
             while let Some((mut node, class)) = self.pending.pop() {
                 node.update_children(|id| self.find_mut(id));
+                if let Some(undo) = &mut self.undo_log {
+                    let before = self.memo.get(&node).copied();
+                    undo.entries.push(Undo::Memo(node.clone(), before));
+                }
                 if let Some(memo_class) = self.memo.insert(node, class) {
                     let did_something = self.perform_union(
                         memo_class,
@@ -1064,7 +2128,13 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
                 let class_id = self.find_mut(class_id);
                 let node_data = N::make(self, &node);
                 let class = self.classes.get_mut(&class_id).unwrap();
+                if let Some(undo) = &mut self.undo_log {
+                    let before = (undo.clone_data)(&class.data);
+                    undo.entries.push(Undo::Data(class_id, before));
+                }
 
+                #[cfg(debug_assertions)]
+                self.analysis.debug_check_merge(&class.data, &node_data);
                 let did_merge = self.analysis.merge(&mut class.data, node_data);
                 if did_merge.0 {
                     self.analysis_pending.extend(class.parents.iter().cloned());
@@ -1145,9 +2215,292 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
         debug_assert!(self.check_memo());
         self.clean = true;
+        if let Some(observer) = &mut self.observer.0 {
+            observer.on_rebuild(n_unions);
+        }
         n_unions
     }
 
+    /// Marks the given e-classes as roots of this egraph.
+    ///
+    /// Roots are what [`sweep_unreachable`](EGraph::sweep_unreachable())
+    /// keeps around. Marking is cumulative; the ids need not be canonical.
+    pub fn mark_roots(&mut self, roots: &[Id]) {
+        self.roots.extend_from_slice(roots);
+    }
+
+    /// Returns the e-classes marked by [`mark_roots`](EGraph::mark_roots()),
+    /// in the order they were marked.
+    pub fn roots(&self) -> &[Id] {
+        &self.roots
+    }
+
+    /// Deletes every e-class that is not reachable from the
+    /// [marked roots](EGraph::mark_roots()), returning how many were deleted.
+    ///
+    /// This shrinks the egraph before extraction or serialization without
+    /// changing the terms represented by the roots. The hashcons, parent
+    /// lists, and operator index are all updated; analysis data of the
+    /// remaining e-classes only depends on reachable e-classes, so it is kept
+    /// as is. If no roots have been marked, everything is deleted.
+    ///
+    /// The `Id`s of deleted e-classes must not be used afterwards.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean) or if explanations
+    /// are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let root = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// egraph.add_expr(&"(g y)".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// egraph.mark_roots(&[root]);
+    /// assert_eq!(egraph.sweep_unreachable(), 2);
+    /// assert_eq!(egraph.number_of_classes(), 2);
+    /// assert_eq!(egraph.lookup_expr(&"(f x)".parse().unwrap()), Some(root));
+    /// assert_eq!(egraph.lookup_expr(&"y".parse().unwrap()), None);
+    /// ```
+    pub fn sweep_unreachable(&mut self) -> usize {
+        assert!(self.clean, "Must rebuild the egraph before sweeping it");
+        assert!(
+            self.explain.is_none(),
+            "Can't sweep an egraph with explanations enabled"
+        );
+        assert!(
+            self.undo_log.is_none(),
+            "Can't sweep an egraph with the undo log enabled"
+        );
+
+        let reachable: HashSet<Id> = self
+            .descendants(&self.roots, usize::MAX)
+            .into_iter()
+            .collect();
+
+        let n_classes_before = self.classes.len();
+        self.classes.retain(|id, _| reachable.contains(id));
+
+        let uf = &self.unionfind;
+        let is_reachable = |id: Id| reachable.contains(&uf.find(id));
+        self.memo
+            .retain(|node, id| is_reachable(*id) && node.all(is_reachable));
+        for class in self.classes.values_mut() {
+            class.parents.retain(|(_, parent)| is_reachable(*parent));
+        }
+        for ids in self.classes_by_op.values_mut() {
+            ids.retain(|id| reachable.contains(id));
+        }
+
+        debug_assert!(self.check_memo());
+        n_classes_before - self.classes.len()
+    }
+
+    /// Deletes every e-class that is not reachable from `roots`, and
+    /// renumbers the remaining ones so their ids are `0..n`.
+    ///
+    /// Returns the new id of every remaining e-class, keyed by its old
+    /// canonical id.
+    /// Unlike [`sweep_unreachable`](EGraph::sweep_unreachable()), this also
+    /// shrinks the union-find, so ids handed out before are stale.
+    /// [Marked roots](EGraph::mark_roots()) are renumbered too, and dropped
+    /// if they are unreachable from `roots`.
+    /// Ids inside the analysis data are left alone, and the
+    /// [union log](EGraph::with_union_log()) is cleared.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean), or if
+    /// explanations or the [undo log](EGraph::with_undo_log()) are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// egraph.add_expr(&"(g y)".parse().unwrap());
+    /// let root = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let ids = egraph.retain_reachable(&[root]);
+    /// assert_eq!(egraph.number_of_classes(), 2);
+    /// assert_eq!(ids[&root], Id::from(1));
+    /// assert_eq!(egraph.lookup_expr(&"(f x)".parse().unwrap()), Some(ids[&root]));
+    /// assert_eq!(egraph.lookup_expr(&"y".parse().unwrap()), None);
+    /// ```
+    pub fn retain_reachable(&mut self, roots: &[Id]) -> HashMap<Id, Id> {
+        assert!(self.clean, "Must rebuild the egraph before compacting it");
+        assert!(
+            self.explain.is_none(),
+            "Can't compact an egraph with explanations enabled"
+        );
+        assert!(
+            self.undo_log.is_none(),
+            "Can't compact an egraph with the undo log enabled"
+        );
+
+        let old_ids = self.descendants(roots, usize::MAX);
+        let ids: HashMap<Id, Id> = old_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, Id::from(i)))
+            .collect();
+
+        self.version += 1;
+        let uf = &self.unionfind;
+        let new_id = |id: Id| ids[&uf.find(id)];
+        let mut classes = HashMap::default();
+        let mut memo = HashMap::default();
+        for old in old_ids {
+            let mut class = self.classes.remove(&old).unwrap();
+            class.id = ids[&old];
+            class.version = self.version;
+            for node in &mut class.nodes {
+                node.update_children(new_id);
+                memo.insert(node.clone(), class.id);
+            }
+            class.parents = class
+                .parents
+                .into_iter()
+                .filter(|(_, parent)| ids.contains_key(&uf.find(*parent)))
+                .map(|(node, parent)| (node.map_children(new_id), new_id(parent)))
+                .collect();
+            classes.insert(class.id, class);
+        }
+        self.roots = self
+            .roots
+            .iter()
+            .filter_map(|&root| ids.get(&uf.find(root)).copied())
+            .collect();
+
+        self.classes = classes;
+        self.memo = memo;
+        self.unionfind = UnionFind::default();
+        for _ in 0..self.classes.len() {
+            self.unionfind.make_set();
+        }
+        if let Some(log) = &mut self.union_log {
+            log.clear();
+        }
+        // rebuilds `classes_by_op`
+        self.rebuild_classes();
+        debug_assert!(self.check_memo());
+        ids
+    }
+
+    /// Removes the e-nodes for which `remove` returns `true`, returning how
+    /// many were removed.
+    ///
+    /// `remove` is called with each e-node and the e-class it's in.
+    /// An e-class never loses all of its e-nodes: if `remove` returns `true`
+    /// for every e-node in an e-class, that e-class is left alone.
+    /// The hashcons, parent lists, and operator index are updated, but
+    /// analysis data is kept as is, since it still describes the terms in
+    /// each e-class.
+    /// Removed e-nodes are gone for good, so rules may add them back.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean) or if explanations
+    /// are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let a = egraph.add_expr(&"(+ x 0)".parse().unwrap());
+    /// let b = egraph.add_expr(&"(* x 1)".parse().unwrap());
+    /// egraph.union(a, b);
+    /// egraph.rebuild();
+    ///
+    /// let mul = Symbol::from("*");
+    /// assert_eq!(egraph.prune(|node, _class| node.op == mul), 1);
+    /// assert_eq!(egraph[a].len(), 1);
+    /// assert_eq!(egraph.lookup_expr(&"(* x 1)".parse().unwrap()), None);
+    /// ```
+    pub fn prune<F>(&mut self, mut remove: F) -> usize
+    where
+        F: FnMut(&L, &EClass<L, N::Data>) -> bool,
+    {
+        assert!(self.clean, "Must rebuild the egraph before pruning it");
+        assert!(
+            self.explain.is_none(),
+            "Can't prune an egraph with explanations enabled"
+        );
+        assert!(
+            self.undo_log.is_none(),
+            "Can't prune an egraph with the undo log enabled"
+        );
+
+        let mut removed: HashSet<L> = HashSet::default();
+        let mut touched = vec![];
+        for class in self.classes.values_mut() {
+            let keep: Vec<bool> = {
+                let class: &EClass<L, N::Data> = class;
+                class.nodes.iter().map(|n| !remove(n, class)).collect()
+            };
+            if keep.iter().all(|&k| k) || !keep.iter().any(|&k| k) {
+                continue;
+            }
+            let mut keep_stamps = keep.clone().into_iter();
+            class.stamps.retain(|_| keep_stamps.next().unwrap_or(true));
+            let mut keep = keep.into_iter();
+            class.nodes.retain(|n| {
+                let k = keep.next().unwrap();
+                if !k {
+                    removed.insert(n.clone());
+                }
+                k
+            });
+            touched.push(class.id);
+        }
+        if removed.is_empty() {
+            return 0;
+        }
+
+        let uf = &self.unionfind;
+        let is_removed = |node: &L| removed.contains(&node.clone().map_children(|c| uf.find(c)));
+        self.memo.retain(|node, _| !is_removed(node));
+        for class in self.classes.values_mut() {
+            class.parents.retain(|(node, _)| !is_removed(node));
+        }
+        for id in touched {
+            let class = &self.classes[&id];
+            for (op, ids) in self.classes_by_op.iter_mut() {
+                let has_op = class.nodes.iter().any(|n| n.matches(op));
+                if !has_op {
+                    ids.remove(&id);
+                }
+            }
+        }
+
+        debug_assert!(self.check_memo());
+        removed.len()
+    }
+
+    /// Removes all e-nodes with children from the e-classes that contain a
+    /// leaf, returning how many were removed.
+    ///
+    /// After constant folding, e-classes that are known to be a constant
+    /// often still hold many operator e-nodes that only slow down matching
+    /// and extraction; this gets rid of them.
+    /// See [`prune`](EGraph::prune()) for details.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let sum = egraph.add_expr(&"(+ 1 1)".parse().unwrap());
+    /// let two = egraph.add_expr(&"2".parse().unwrap());
+    /// egraph.union(sum, two);
+    /// egraph.rebuild();
+    ///
+    /// assert_eq!(egraph.prune_to_leaves(), 1);
+    /// assert_eq!(egraph[two].nodes, vec![SymbolLang::leaf("2")]);
+    /// ```
+    pub fn prune_to_leaves(&mut self) -> usize {
+        self.prune(|node, class| !node.is_leaf() && class.leaves().next().is_some())
+    }
+
     pub(crate) fn check_each_explain(&self, rules: &[&Rewrite<L, N>]) -> bool {
         if let Some(explain) = &self.explain {
             explain.check_each_explain(rules)
@@ -1157,7 +2510,92 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     }
 }
 
-struct EGraphDump<'a, L: Language, N: Analysis<L>>(&'a EGraph<L, N>);
+/// A term produced by [`EGraph::enumerate`]: an enode along with the terms
+/// of its children.
+struct Term<L>(L, Vec<Rc<Term<L>>>);
+
+impl<L: Language> Term<L> {
+    fn add_to(&self, expr: &mut RecExpr<L>) -> Id {
+        let children: Vec<Id> = self.1.iter().map(|child| child.add_to(expr)).collect();
+        let mut children = children.into_iter();
+        let node = self.0.clone().map_children(|_| children.next().unwrap());
+        expr.add(node)
+    }
+}
+
+fn enumerate_terms<'a, L: Language, N: Analysis<L>>(
+    egraph: &'a EGraph<L, N>,
+    id: Id,
+    depth: usize,
+) -> Box<dyn Iterator<Item = Rc<Term<L>>> + 'a> {
+    if depth == 0 {
+        return Box::new(std::iter::empty());
+    }
+    Box::new(egraph[id].nodes.iter().flat_map(move |node| {
+        let children: Rc<[Id]> = node.children().into();
+        enumerate_children(egraph, children, depth - 1)
+            .map(move |terms| Rc::new(Term(node.clone(), terms)))
+    }))
+}
+
+/// Enumerates every combination of terms for `children`, each at most
+/// `depth` deep.
+fn enumerate_children<'a, L: Language, N: Analysis<L>>(
+    egraph: &'a EGraph<L, N>,
+    children: Rc<[Id]>,
+    depth: usize,
+) -> Box<dyn Iterator<Item = Vec<Rc<Term<L>>>> + 'a> {
+    let first = match children.first() {
+        Some(&first) => first,
+        None => return Box::new(std::iter::once(vec![])),
+    };
+    Box::new(enumerate_terms(egraph, first, depth).flat_map(move |term| {
+        let rest: Rc<[Id]> = children[1..].into();
+        enumerate_children(egraph, rest, depth).map(move |mut terms| {
+            terms.insert(0, term.clone());
+            terms
+        })
+    }))
+}
+
+/// Size statistics of an [`EGraph`], returned by [`EGraph::report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct EGraphReport {
+    /// The number of e-classes.
+    pub classes: usize,
+    /// The total number of e-nodes in all e-classes.
+    pub nodes: usize,
+    /// The number of e-nodes in the hashcons.
+    /// This includes e-nodes that became equal to others after a union.
+    pub memo_size: usize,
+    /// The number of ids ever handed out by the egraph.
+    pub unionfind_size: usize,
+    /// A rough estimate of the memory used by the egraph, in bytes.
+    ///
+    /// This only counts the egraph's own tables, not memory owned by the
+    /// e-nodes themselves (like the children of a [`SymbolLang`]),
+    /// the analysis data, or the explanations.
+    pub estimated_bytes: usize,
+}
+
+impl Display for EGraphReport {
+    #[rustfmt::skip]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "EGraph report")?;
+        writeln!(f, "=============")?;
+        writeln!(f, "  Classes: {}", self.classes)?;
+        writeln!(f, "  Nodes: {}", self.nodes)?;
+        writeln!(f, "  Memo: {}", self.memo_size)?;
+        writeln!(f, "  Union-find: {}", self.unionfind_size)?;
+        writeln!(f, "  Estimated size: {:.1} KiB", self.estimated_bytes as f64 / 1024.0)?;
+        Ok(())
+    }
+}
+
+/// A printable view of an [`EGraph`], returned by [`EGraph::dump`].
+pub struct EGraphDump<'a, L: Language, N: Analysis<L>>(&'a EGraph<L, N>);
 
 impl<'a, L: Language, N: Analysis<L>> Debug for EGraphDump<'a, L, N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -1172,6 +2610,31 @@ impl<'a, L: Language, N: Analysis<L>> Debug for EGraphDump<'a, L, N> {
     }
 }
 
+impl<'a, L: Language + Display, N: Analysis<L>> Display for EGraphDump<'a, L, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let egraph = self.0;
+        let mut ids: Vec<Id> = egraph.classes().map(|c| c.id).collect();
+        ids.sort();
+        for id in ids {
+            let mut nodes: Vec<String> = egraph[id]
+                .iter()
+                .map(|node| {
+                    if node.is_leaf() {
+                        node.to_string()
+                    } else {
+                        let mut s = format!("({}", node);
+                        node.for_each(|c| s += &format!(" c{}", egraph.find(c)));
+                        s + ")"
+                    }
+                })
+                .collect();
+            nodes.sort();
+            writeln!(f, "c{}: {}", id, nodes.join(", "))?
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -1197,6 +2660,176 @@ mod tests {
         egraph.rebuild();
     }
 
+    #[test]
+    fn sweep_keeps_root_terms() {
+        crate::init_logger();
+        let rules: &[Rewrite<SymbolLang, ()>] = &[
+            rewrite!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+            rewrite!("mul-1"; "(* ?a 1)" => "?a"),
+            rewrite!("double"; "(+ ?a ?a)" => "(* 2 ?a)"),
+        ];
+        let runner = Runner::default()
+            .with_expr(&"(+ (* x 1) (+ x 0))".parse().unwrap())
+            .with_expr(&"(+ y (* z 1))".parse().unwrap())
+            .with_expr(&"(* w 1)".parse().unwrap())
+            .run(rules);
+        let root = runner.roots[0];
+        let mut egraph = runner.egraph;
+
+        let size_before = Extractor::new(&egraph, AstSize).find_best(root);
+        let depth_before = Extractor::new(&egraph, AstDepth).find_best(root);
+        let n_classes = egraph.number_of_classes();
+
+        egraph.mark_roots(&[root]);
+        let swept = egraph.sweep_unreachable();
+        assert!(swept > 0);
+        assert_eq!(egraph.number_of_classes(), n_classes - swept);
+        assert_eq!(egraph.lookup_expr(&"y".parse().unwrap()), None);
+
+        let size_after = Extractor::new(&egraph, AstSize).find_best(root);
+        let depth_after = Extractor::new(&egraph, AstDepth).find_best(root);
+        assert_eq!(size_before, size_after);
+        assert_eq!(depth_before.0, depth_after.0);
+        assert_eq!(egraph.lookup_expr(&size_after.1), Some(root));
+
+        // the swept egraph can keep running
+        let runner = Runner::default().with_egraph(egraph).run(rules);
+        assert_eq!(
+            runner.egraph.lookup_expr(&"(* 2 x)".parse().unwrap()),
+            Some(runner.egraph.find(root))
+        );
+    }
+
+    #[test]
+    fn classes_indexed_by_op() {
+        let mut egraph = EGraph::<SymbolLang, ()>::default();
+        let a = egraph.add_expr(&"(f x (g y))".parse().unwrap());
+        let b = egraph.add_expr(&"(f x)".parse().unwrap());
+        egraph.union(a, b);
+        egraph.rebuild();
+
+        let classes = |node: SymbolLang| egraph.classes_by_op[&op_key(&node)].len();
+        // f with 1 and 2 children are different operators
+        assert_eq!(egraph.classes_by_op.len(), 5);
+        assert_eq!(classes(SymbolLang::new("g", vec![a])), 1);
+        assert_eq!(classes(SymbolLang::new("f", vec![a])), 1);
+        assert_eq!(classes(SymbolLang::leaf("x")), 1);
+
+        let pattern: Pattern<SymbolLang> = "(g ?a)".parse().unwrap();
+        assert_eq!(pattern.search(&egraph).len(), 1);
+    }
+
+    #[test]
+    fn absorb_cycles() {
+        let mut other = EGraph::<SymbolLang, ()>::default();
+        let x = other.add_expr(&"x".parse().unwrap());
+        let fx = other.add_expr(&"(f x)".parse().unwrap());
+        let g = other.add_expr(&"(g (f x) x)".parse().unwrap());
+        other.union(x, fx);
+        other.rebuild();
+
+        let mut egraph = EGraph::<SymbolLang, ()>::default();
+        let map = egraph.absorb(&other);
+        assert_eq!(map.len(), other.number_of_classes());
+        assert_eq!(
+            egraph.total_number_of_nodes(),
+            other.total_number_of_nodes()
+        );
+        let x = map[&other.find(x)];
+        assert_eq!(egraph.lookup_expr(&"(f (f x))".parse().unwrap()), Some(x));
+        assert_eq!(
+            egraph.lookup_expr(&"(g x (f x))".parse().unwrap()),
+            Some(map[&other.find(g)])
+        );
+    }
+
+    #[test]
+    fn descendants_through_cycles() {
+        let mut egraph = EGraph::<SymbolLang, ()>::default();
+        let x = egraph.add_expr(&"x".parse().unwrap());
+        let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+        let g = egraph.add_expr(&"(g (f x) y)".parse().unwrap());
+        egraph.union(x, fx);
+        egraph.rebuild();
+
+        let x = egraph.find(x);
+        assert_eq!(egraph.descendants(&[fx], usize::MAX), vec![x]);
+        assert_eq!(egraph.descendants(&[g], 1).len(), 3);
+        assert_eq!(egraph.descendants(&[g], usize::MAX).len(), 3);
+    }
+
+    #[test]
+    fn retain_reachable_compacts_ids() {
+        crate::init_logger();
+        let rules: &[Rewrite<SymbolLang, ()>] = &[
+            rewrite!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+            rewrite!("double"; "(+ ?a ?a)" => "(* 2 ?a)"),
+        ];
+        let runner = Runner::default()
+            .with_expr(&"(+ y (+ z 0))".parse().unwrap())
+            .with_expr(&"(+ (+ x 0) (+ 0 x))".parse().unwrap())
+            .run(rules);
+        let mut egraph = runner.egraph;
+        let root = egraph.find(runner.roots[1]);
+        let cost_before = Extractor::new(&egraph, AstSize).find_best_cost(root);
+
+        let ids = egraph.retain_reachable(&[root]);
+        let root = ids[&root];
+        assert_eq!(egraph.number_of_ids(), egraph.number_of_classes());
+        assert_eq!(egraph.lookup_expr(&"y".parse().unwrap()), None);
+        assert_eq!(
+            Extractor::new(&egraph, AstSize).find_best_cost(root),
+            cost_before
+        );
+
+        // the compacted egraph can keep running
+        let runner = Runner::default().with_egraph(egraph).run(rules);
+        assert_eq!(
+            runner.egraph.lookup_expr(&"(+ x x)".parse().unwrap()),
+            Some(runner.egraph.find(root))
+        );
+    }
+
+    #[test]
+    fn rollback_restores_egraph() {
+        crate::init_logger();
+        let rules: &[Rewrite<SymbolLang, MinDepth>] = &[
+            rewrite!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+            rewrite!("double"; "(+ ?a ?a)" => "(* 2 ?a)"),
+            rewrite!("wrap"; "(* 2 ?a)" => "(f (* 2 ?a))"),
+        ];
+        let expr = "(+ (+ x 0) (+ 0 x))".parse().unwrap();
+        let (egraph, root) = EGraph::<SymbolLang, MinDepth>::from_expr(MinDepth, &expr);
+        let mut egraph = egraph.with_undo_log();
+        let snapshot = |egraph: &EGraph<SymbolLang, MinDepth>| {
+            let mut memo: Vec<_> = egraph.memo.iter().map(|(n, &id)| (n.clone(), id)).collect();
+            memo.sort();
+            let mut classes: Vec<_> = egraph
+                .classes()
+                .map(|c| (c.id, c.data, c.nodes.clone(), c.parents.len()))
+                .collect();
+            classes.sort();
+            (memo, classes, egraph.number_of_ids())
+        };
+        let before = snapshot(&egraph);
+        let mark = egraph.mark();
+
+        for _ in 0..2 {
+            let runner = Runner::default()
+                .with_egraph(egraph)
+                .with_iter_limit(4)
+                .run(rules);
+            egraph = runner.egraph;
+            assert_eq!(egraph[root].data, 2);
+            egraph.rollback(mark);
+            assert_eq!(snapshot(&egraph), before);
+            assert_eq!(egraph[root].data, 3);
+        }
+    }
+
     #[cfg(all(feature = "serde-1", feature = "serde_json"))]
     #[test]
     fn test_serde() {
@@ -1211,4 +2844,49 @@ mod tests {
         let json_rep = serde_json::to_string_pretty(&egraph).unwrap();
         println!("{}", json_rep);
     }
+
+    #[cfg(all(feature = "serde-1", feature = "serde_json"))]
+    #[test]
+    fn test_serde_id_range() {
+        assert_eq!(serde_json::from_str::<Id>("3").unwrap(), Id::from(3));
+        let max = serde_json::to_string(&u32::MAX).unwrap();
+        assert!(serde_json::from_str::<Id>(&max).is_err());
+    }
+
+    #[cfg(all(feature = "serde-1", feature = "serde_json"))]
+    #[test]
+    fn test_serde_round_trip() {
+        let rules = &[
+            rewrite!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("assoc"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+        ];
+        let start: RecExpr<SymbolLang> = "(+ a (+ b c))".parse().unwrap();
+        let goal: RecExpr<SymbolLang> = "(+ c (+ b a))".parse().unwrap();
+
+        // checkpoint after one iteration
+        let runner = Runner::<SymbolLang, ()>::default()
+            .with_expr(&start)
+            .with_iter_limit(1)
+            .run(rules);
+        let json_rep = serde_json::to_string(&runner.egraph).unwrap();
+
+        let mut egraph: EGraph<SymbolLang, ()> = serde_json::from_str(&json_rep).unwrap();
+        egraph.rebuild();
+        assert_eq!(
+            egraph.number_of_classes(),
+            runner.egraph.number_of_classes()
+        );
+        assert_eq!(egraph.total_size(), runner.egraph.total_size());
+        assert_eq!(
+            egraph.lookup_expr(&start),
+            runner.egraph.lookup_expr(&start)
+        );
+
+        // resume from the checkpoint
+        let runner = Runner::default().with_egraph(egraph).run(rules);
+        assert_eq!(
+            runner.egraph.lookup_expr(&start),
+            runner.egraph.lookup_expr(&goal)
+        );
+    }
 }
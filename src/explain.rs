@@ -13,13 +13,17 @@ use symbolic_expressions::Sexp;
 const CONGRUENCE_LIMIT: usize = 2;
 const GREEDY_NUM_ITERS: usize = 2;
 
-/// A justification for a union, either via a rule or congruence.
+/// A justification for a union, either via a rule, an axiom, or congruence.
 /// A direct union with a justification is also stored as a rule.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub enum Justification {
     /// Justification by a rule with this name.
     Rule(Symbol),
+    /// Justification by an external fact, asserted with
+    /// [`assert_equalities`](super::EGraph::assert_equalities) for this
+    /// reason.
+    Axiom(Symbol),
     /// Justification by congruence.
     Congruence,
 }
@@ -128,6 +132,8 @@ impl<L: Language + Display + FromOp> Explanation<L> {
     /// or "(Rewrite<= rule-name expression)".
     /// "Rewrite=>" indicates that the previous term is rewritten to the current term
     /// and "Rewrite<=" indicates that the current term is rewritten to the previous term.
+    /// Steps justified by [`assert_equalities`](super::EGraph::assert_equalities)
+    /// are written "Axiom=>" and "Axiom<=" instead.
     /// The name of the rule or the reason provided to [`union_instantiations`](super::EGraph::union_instantiations).
     ///
     /// Example explanation:
@@ -152,6 +158,8 @@ impl<L: Language + Display + FromOp> Explanation<L> {
     /// or "(Rewrite<= rule-name expression)".
     /// "Rewrite=>" indicates that the previous term is rewritten to the current term
     /// and "Rewrite<=" indicates that the current term is rewritten to the previous term.
+    /// Steps justified by [`assert_equalities`](super::EGraph::assert_equalities)
+    /// are written "Axiom=>" and "Axiom<=" instead.
     /// The name of the rule or the reason provided to [`union_instantiations`](super::EGraph::union_instantiations).
     ///
     /// The following example shows that `(+ 1 (- a (* (- 2 1) a))) = 1`
@@ -456,6 +464,10 @@ pub struct TreeTerm<L: Language> {
     pub backward_rule: Option<Symbol>,
     /// A rule rewriting the last TreeTerm's final term to this TreeTerm's initial term.
     pub forward_rule: Option<Symbol>,
+    /// Whether the rule is an axiom from
+    /// [`assert_equalities`](super::EGraph::assert_equalities) rather than a
+    /// rewrite.
+    pub axiom: bool,
     /// A list of child proofs, each transforming the initial term to the final term for that child.
     pub child_proofs: Vec<TreeExplanation<L>>,
 
@@ -470,6 +482,7 @@ impl<L: Language> TreeTerm<L> {
             node,
             backward_rule: None,
             forward_rule: None,
+            axiom: false,
             child_proofs,
             current: Id::from(0),
             last: Id::from(0),
@@ -501,6 +514,7 @@ impl<L: Language> TreeTerm<L> {
             node: self.node.clone(),
             backward_rule: self.backward_rule,
             forward_rule: self.forward_rule,
+            axiom: self.axiom,
             children: self
                 .child_proofs
                 .iter()
@@ -515,6 +529,7 @@ impl<L: Language> TreeTerm<L> {
             node: self.node.clone(),
             backward_rule: self.backward_rule,
             forward_rule: self.forward_rule,
+            axiom: self.axiom,
             children: self
                 .child_proofs
                 .iter()
@@ -560,6 +575,7 @@ impl<L: Language> TreeTerm<L> {
 
         proof[0].backward_rule = self.backward_rule;
         proof[0].forward_rule = self.forward_rule;
+        proof[0].axiom = self.axiom;
 
         proof
     }
@@ -587,6 +603,10 @@ pub struct FlatTerm<L: Language> {
     pub backward_rule: Option<Symbol>,
     /// A rule rewriting the last FlatTerm to this FlatTerm.
     pub forward_rule: Option<Symbol>,
+    /// Whether the rule is an axiom from
+    /// [`assert_equalities`](super::EGraph::assert_equalities) rather than a
+    /// rewrite.
+    pub axiom: bool,
     /// The children of this FlatTerm.
     pub children: FlatExplanation<L>,
 }
@@ -626,6 +646,9 @@ impl<L: Language> FlatTerm<L> {
     }
 
     fn combine_rewrites(&mut self, other: &FlatTerm<L>) {
+        if other.forward_rule.is_some() || other.backward_rule.is_some() {
+            self.axiom = other.axiom;
+        }
         if other.forward_rule.is_some() {
             assert!(self.forward_rule.is_none());
             self.forward_rule = other.forward_rule;
@@ -667,9 +690,10 @@ impl<L: Language + Display + FromOp> FlatTerm<L> {
             Sexp::List(vec)
         };
 
+        let kind = if self.axiom { "Axiom" } else { "Rewrite" };
         if let Some(rule_name) = &self.backward_rule {
             expr = Sexp::List(vec![
-                Sexp::String("Rewrite<=".to_string()),
+                Sexp::String(format!("{}<=", kind)),
                 Sexp::String((*rule_name).to_string()),
                 expr,
             ]);
@@ -677,7 +701,7 @@ impl<L: Language + Display + FromOp> FlatTerm<L> {
 
         if let Some(rule_name) = &self.forward_rule {
             expr = Sexp::List(vec![
-                Sexp::String("Rewrite=>".to_string()),
+                Sexp::String(format!("{}=>", kind)),
                 Sexp::String((*rule_name).to_string()),
                 expr,
             ]);
@@ -739,9 +763,10 @@ impl<L: Language + Display + FromOp> TreeTerm<L> {
             Sexp::List(vec)
         };
 
+        let kind = if self.axiom { "Axiom" } else { "Rewrite" };
         if let Some(rule_name) = &self.backward_rule {
             expr = Sexp::List(vec![
-                Sexp::String("Rewrite<=".to_string()),
+                Sexp::String(format!("{}<=", kind)),
                 Sexp::String((*rule_name).to_string()),
                 expr,
             ]);
@@ -749,7 +774,7 @@ impl<L: Language + Display + FromOp> TreeTerm<L> {
 
         if let Some(rule_name) = &self.forward_rule {
             expr = Sexp::List(vec![
-                Sexp::String("Rewrite=>".to_string()),
+                Sexp::String(format!("{}=>", kind)),
                 Sexp::String((*rule_name).to_string()),
                 expr,
             ]);
@@ -766,6 +791,7 @@ impl<L: Language> FlatTerm<L> {
             node,
             backward_rule: None,
             forward_rule: None,
+            axiom: false,
             children,
         }
     }
@@ -1130,7 +1156,7 @@ impl<L: Language> Explain<L> {
         self.make_leader(node1);
         self.explainfind[usize::from(node1)].parent_connection.next = node2;
 
-        if let Justification::Rule(_) = justification {
+        if let Justification::Rule(_) | Justification::Axiom(_) = justification {
             self.shortest_explanation_memo
                 .insert((node1, node2), (1, node2));
             self.shortest_explanation_memo
@@ -1163,7 +1189,8 @@ impl<L: Language> Explain<L> {
         for node in &self.explainfind {
             for neighbor in &node.neighbors {
                 if neighbor.is_rewrite_forward {
-                    if let Justification::Rule(r) = neighbor.justification {
+                    if let Justification::Rule(r) | Justification::Axiom(r) = neighbor.justification
+                    {
                         equalities.push((neighbor.current, neighbor.next, r));
                     }
                 }
@@ -1261,7 +1288,7 @@ impl<L: Language> Explain<L> {
     fn get_neighbor(&self, current: Id, next: Id) -> Connection {
         for neighbor in &self.explainfind[usize::from(current)].neighbors {
             if neighbor.next == next {
-                if let Justification::Rule(_) = neighbor.justification {
+                if let Justification::Rule(_) | Justification::Axiom(_) = neighbor.justification {
                     return neighbor.clone();
                 }
             }
@@ -1397,9 +1424,10 @@ impl<L: Language> Explain<L> {
         }
 
         let term = match connection.justification {
-            Justification::Rule(name) => {
+            Justification::Rule(name) | Justification::Axiom(name) => {
                 let mut rewritten =
                     (*self.node_to_explanation(connection.next, node_explanation_cache)).clone();
+                rewritten.axiom = matches!(connection.justification, Justification::Axiom(_));
                 if connection.is_rewrite_forward {
                     rewritten.forward_rule = Some(name);
                 } else {
@@ -1577,7 +1605,7 @@ impl<L: Language> Explain<L> {
             Justification::Congruence => {
                 self.congruence_distance(connection.current, connection.next, distance_memo)
             }
-            Justification::Rule(_) => 1,
+            Justification::Rule(_) | Justification::Axiom(_) => 1,
         }
     }
 
@@ -1617,7 +1645,7 @@ impl<L: Language> Explain<L> {
                     Justification::Congruence => {
                         self.congruence_distance(current, next, distance_memo)
                     }
-                    Justification::Rule(_) => 1,
+                    Justification::Rule(_) | Justification::Axiom(_) => 1,
                 };
                 distance_memo.parent_distance[usize::from(parent)] = (self.parent(parent), cost);
             }
@@ -1736,7 +1764,7 @@ impl<L: Language> Explain<L> {
             }
 
             for neighbor in &self.explainfind[usize::from(current)].neighbors {
-                if let Justification::Rule(_) = neighbor.justification {
+                if let Justification::Rule(_) | Justification::Axiom(_) = neighbor.justification {
                     let neighbor_cost = cost_so_far.saturating_add(1);
                     todo.push(HeapState {
                         item: neighbor.clone(),
@@ -1,8 +1,11 @@
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::fmt::Debug;
 
-use crate::util::HashMap;
-use crate::{Analysis, EClass, EGraph, Id, Language, RecExpr};
+use thiserror::Error;
+
+use crate::util::{HashMap, HashSet};
+use crate::{Analysis, EClass, EGraph, ENodeOrVar, Id, Language, PatternAst, RecExpr, Subst};
 
 /** Extracting a single [`RecExpr`] from an [`EGraph`].
 
@@ -42,6 +45,7 @@ pub struct Extractor<'a, CF: CostFunction<L>, L: Language, N: Analysis<L>> {
     cost_function: CF,
     costs: HashMap<Id, (CF::Cost, L)>,
     egraph: &'a EGraph<L, N>,
+    constraints: ExtractionConstraints<L>,
 }
 
 /** A cost function that can be used by an [`Extractor`].
@@ -78,34 +82,65 @@ assert_eq!(AstDepth.cost_rec(&e), 2);
 ```
 
 If you'd like to access the [`Analysis`] data or anything else in the e-graph,
-you can put a reference to the e-graph in your [`CostFunction`]:
+you can put a reference to the e-graph in your [`CostFunction`].
+During [`Extractor::new`], the children of `enode` are e-class ids, so the
+cost can depend on properties of the operands, such as bit-widths or
+tensor shapes computed by an analysis:
 
 ```
 # use egg::*;
-# type MyAnalysis = ();
+// the analysis data is the bit-width of each e-class
+#[derive(Default)]
+struct Width;
+impl Analysis<SymbolLang> for Width {
+    type Data = u32;
+    fn make(egraph: &EGraph<SymbolLang, Self>, enode: &SymbolLang) -> u32 {
+        match enode.op.as_str() {
+            "wide" => 64,
+            "narrow" => 8,
+            _ => enode.children.iter().map(|&c| egraph[c].data).max().unwrap_or(0),
+        }
+    }
+    fn merge(&mut self, a: &mut u32, b: u32) -> DidMerge {
+        merge_max(a, b)
+    }
+}
+
 struct EGraphCostFn<'a> {
-    egraph: &'a EGraph<SymbolLang, MyAnalysis>,
+    egraph: &'a EGraph<SymbolLang, Width>,
 }
 
 impl<'a> CostFunction<SymbolLang> for EGraphCostFn<'a> {
-    type Cost = usize;
+    type Cost = u32;
     fn cost<C>(&mut self, enode: &SymbolLang, mut costs: C) -> Self::Cost
     where
         C: FnMut(Id) -> Self::Cost
     {
-        // use self.egraph however you want here
-        println!("the egraph has {} classes", self.egraph.number_of_classes());
-        return 1
+        // a multiplication costs as much as its widest operand
+        let op_cost = match enode.op.as_str() {
+            "*" => enode.children.iter().map(|&c| self.egraph[c].data).max().unwrap(),
+            _ => 1,
+        };
+        enode.fold(op_cost, |sum, id| sum + costs(id))
     }
 }
 
-let mut egraph = EGraph::<SymbolLang, MyAnalysis>::default();
-let id = egraph.add_expr(&"(foo bar)".parse().unwrap());
+let mut egraph = EGraph::<SymbolLang, Width>::default();
+let id = egraph.add_expr(&"(* wide narrow)".parse().unwrap());
+egraph.rebuild();
 let cost_func = EGraphCostFn { egraph: &egraph };
-let mut extractor = Extractor::new(&egraph, cost_func);
-let _ = extractor.find_best(id);
+let extractor = Extractor::new(&egraph, cost_func);
+assert_eq!(extractor.find_best_cost(id), 66);
 ```
 
+Other users of a cost function may pass ids that are not e-classes:
+[`cost_rec`](CostFunction::cost_rec()) passes indices into the
+[`RecExpr`], and [`find_best_k`](Extractor::find_best_k()) and
+[`find_pareto`](Extractor::find_pareto()) pass the positions of the
+children.
+So a cost function that looks up e-classes is only meant for
+[`Extractor::new`].
+
 Note that a particular e-class might occur in an expression multiple times.
 This means that pathological, but nevertheless realistic cases
 might overflow `usize` if you implement a cost function like [`AstSize`],
@@ -153,7 +188,7 @@ assert_eq!(AstSize.cost_rec(&e), 4);
 ```
 
 **/
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct AstSize;
 impl<L: Language> CostFunction<L> for AstSize {
     type Cost = usize;
@@ -174,7 +209,7 @@ assert_eq!(AstDepth.cost_rec(&e), 2);
 ```
 
 **/
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct AstDepth;
 impl<L: Language> CostFunction<L> for AstDepth {
     type Cost = usize;
@@ -186,6 +221,219 @@ impl<L: Language> CostFunction<L> for AstDepth {
     }
 }
 
+/** A pair of [`CostFunction`]s computes both costs at once.
+
+Tuples of cost functions compare lexicographically as a plain
+[`CostFunction`], and componentwise (see [`ParetoCost`]) in
+[`find_pareto`](Extractor::find_pareto()).
+
+```
+# use egg::*;
+let e: RecExpr<SymbolLang> = "(do_it foo bar baz)".parse().unwrap();
+assert_eq!((AstSize, AstDepth).cost_rec(&e), (4, 2));
+```
+**/
+impl<L: Language, A: CostFunction<L>, B: CostFunction<L>> CostFunction<L> for (A, B) {
+    type Cost = (A::Cost, B::Cost);
+    fn cost<C>(&mut self, enode: &L, mut costs: C) -> Self::Cost
+    where
+        C: FnMut(Id) -> Self::Cost,
+    {
+        let children: HashMap<Id, Self::Cost> =
+            enode.children().iter().map(|&id| (id, costs(id))).collect();
+        (
+            self.0.cost(enode, |id| children[&id].0.clone()),
+            self.1.cost(enode, |id| children[&id].1.clone()),
+        )
+    }
+}
+
+/// Like the pair, but for three cost functions.
+impl<L, A, B, C> CostFunction<L> for (A, B, C)
+where
+    L: Language,
+    A: CostFunction<L>,
+    B: CostFunction<L>,
+    C: CostFunction<L>,
+{
+    type Cost = (A::Cost, B::Cost, C::Cost);
+    fn cost<F>(&mut self, enode: &L, mut costs: F) -> Self::Cost
+    where
+        F: FnMut(Id) -> Self::Cost,
+    {
+        let children: HashMap<Id, Self::Cost> =
+            enode.children().iter().map(|&id| (id, costs(id))).collect();
+        (
+            self.0.cost(enode, |id| children[&id].0.clone()),
+            self.1.cost(enode, |id| children[&id].1.clone()),
+            self.2.cost(enode, |id| children[&id].2.clone()),
+        )
+    }
+}
+
+/// A cost made of several objectives, compared componentwise for
+/// [`find_pareto`](Extractor::find_pareto()).
+///
+/// This is implemented for pairs and triples, which are the costs of pairs
+/// and triples of [`CostFunction`]s.
+pub trait ParetoCost {
+    /// Returns `true` if `self` is no worse than `other` in every objective
+    /// and strictly better in at least one.
+    fn dominates(&self, other: &Self) -> bool;
+}
+
+impl<A: PartialOrd, B: PartialOrd> ParetoCost for (A, B) {
+    fn dominates(&self, other: &Self) -> bool {
+        self.0 <= other.0 && self.1 <= other.1 && (self.0 < other.0 || self.1 < other.1)
+    }
+}
+
+impl<A: PartialOrd, B: PartialOrd, C: PartialOrd> ParetoCost for (A, B, C) {
+    fn dominates(&self, other: &Self) -> bool {
+        self.0 <= other.0
+            && self.1 <= other.1
+            && self.2 <= other.2
+            && (self.0 < other.0 || self.1 < other.1 || self.2 < other.2)
+    }
+}
+
+impl<'a, L: Language, CF: CostFunction<L>> CostFunction<L> for &'a mut CF {
+    type Cost = CF::Cost;
+    fn cost<C>(&mut self, enode: &L, costs: C) -> Self::Cost
+    where
+        C: FnMut(Id) -> Self::Cost,
+    {
+        (**self).cost(enode, costs)
+    }
+}
+
+/// An error from [`Extractor::try_find_best`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExtractionError {
+    /// No finite term could be extracted because every e-node of these
+    /// e-classes refers back into a cycle or into another of these e-classes.
+    #[error("every e-node of e-classes {0:?} refers back into a cycle")]
+    Cyclic(Vec<Id>),
+    /// No term of these e-classes satisfies the
+    /// [`ExtractionConstraints`], either because their allowed e-nodes
+    /// only lead into a cycle or because none of their e-nodes is allowed.
+    #[error("no term of e-classes {0:?} satisfies the extraction constraints")]
+    Constrained(Vec<Id>),
+}
+
+/** Restrictions on which e-nodes an [`Extractor`] may pick.
+
+Pinning an e-class makes the extractor use the given e-node there, and
+forbidden e-nodes are never used, as if their cost were infinite.
+This is useful, for example, when lowering to a backend that lacks some
+operators.
+If the constraints leave no legal term,
+[`try_find_best`](Extractor::try_find_best()) returns
+[`ExtractionError::Constrained`].
+
+```
+use egg::*;
+let mut egraph = EGraph::<SymbolLang, ()>::default();
+let shl = egraph.add_expr(&"(<< x 1)".parse().unwrap());
+let mul = egraph.add_expr(&"(* x 2)".parse().unwrap());
+let add = egraph.add_expr(&"(+ x x)".parse().unwrap());
+egraph.union(shl, mul);
+egraph.union(shl, add);
+egraph.rebuild();
+
+// this backend has no shifts or additions
+let constraints = ExtractionConstraints::new().forbid_op("<<").forbid_op("+");
+let extractor = Extractor::new_constrained(&egraph, AstSize, constraints);
+assert_eq!(extractor.find_best(shl).1.to_string(), "(* x 2)");
+
+// force the addition
+let add_node = SymbolLang::new("+", vec![egraph.lookup(SymbolLang::leaf("x")).unwrap(); 2]);
+let constraints = ExtractionConstraints::new().pin(shl, add_node);
+let extractor = Extractor::new_constrained(&egraph, AstSize, constraints);
+assert_eq!(extractor.find_best(shl).1.to_string(), "(+ x x)");
+
+// nothing is left without x
+let constraints = ExtractionConstraints::new().forbid_op("x");
+let extractor = Extractor::new_constrained(&egraph, AstSize, constraints);
+assert!(matches!(extractor.try_find_best(shl), Err(ExtractionError::Constrained(_))));
+```
+**/
+pub struct ExtractionConstraints<L> {
+    pins: HashMap<Id, L>,
+    #[allow(clippy::type_complexity)]
+    forbidden: Vec<Box<dyn Fn(&L) -> bool + Send + Sync>>,
+}
+
+impl<L> Default for ExtractionConstraints<L> {
+    fn default() -> Self {
+        Self {
+            pins: Default::default(),
+            forbidden: vec![],
+        }
+    }
+}
+
+impl<L: Debug> Debug for ExtractionConstraints<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtractionConstraints")
+            .field("pins", &self.pins)
+            .field("forbidden", &self.forbidden.len())
+            .finish()
+    }
+}
+
+impl<L: Language> ExtractionConstraints<L> {
+    /// Creates constraints that allow every e-node.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allows `node` in `eclass`.
+    /// `node` must be one of the e-nodes of `eclass`; its children are
+    /// canonicalized when the [`Extractor`] is created.
+    pub fn pin(mut self, eclass: Id, node: L) -> Self {
+        self.pins.insert(eclass, node);
+        self
+    }
+
+    /// Never picks e-nodes for which `forbidden` returns `true`.
+    pub fn forbid(mut self, forbidden: impl Fn(&L) -> bool + Send + Sync + 'static) -> Self {
+        self.forbidden.push(Box::new(forbidden));
+        self
+    }
+
+    /// Never picks e-nodes whose operator, as printed by [`Display`], is
+    /// `op`.
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub fn forbid_op(self, op: &str) -> Self
+    where
+        L: std::fmt::Display,
+    {
+        let op = op.to_owned();
+        self.forbid(move |node| node.to_string() == op)
+    }
+
+    /// Returns `true` if nothing is pinned or forbidden.
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty() && self.forbidden.is_empty()
+    }
+
+    fn canonicalize<N: Analysis<L>>(&mut self, egraph: &EGraph<L, N>) {
+        self.pins = std::mem::take(&mut self.pins)
+            .into_iter()
+            .map(|(id, node)| (egraph.find(id), node.map_children(|c| egraph.find(c))))
+            .collect();
+    }
+
+    fn allows(&self, eclass: Id, node: &L) -> bool {
+        match self.pins.get(&eclass) {
+            Some(pinned) if pinned != node => false,
+            _ => !self.forbidden.iter().any(|f| f(node)),
+        }
+    }
+}
+
 fn cmp<T: PartialOrd>(a: &Option<T>, b: &Option<T>) -> Ordering {
     // None is high
     match (a, b) {
@@ -196,6 +444,56 @@ fn cmp<T: PartialOrd>(a: &Option<T>, b: &Option<T>) -> Ordering {
     }
 }
 
+/// A combination of the children's terms in [`Extractor::node_candidates`],
+/// ordered so that [`BinaryHeap`] pops the cheapest first.
+struct Candidate<C> {
+    cost: C,
+    choice: Vec<usize>,
+}
+
+impl<C: PartialOrd> Ord for Candidate<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.choice.cmp(&self.choice))
+    }
+}
+
+impl<C: PartialOrd> PartialOrd for Candidate<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: PartialOrd> PartialEq for Candidate<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<C: PartialOrd> Eq for Candidate<C> {}
+
+/// The combinations that pick the next term for one of the children.
+fn successors<T>(choice: &[usize], lists: &[&Vec<T>]) -> Vec<Vec<usize>> {
+    (0..choice.len())
+        .filter(|&i| choice[i] + 1 < lists[i].len())
+        .map(|i| {
+            let mut next = choice.to_vec();
+            next[i] += 1;
+            next
+        })
+        .collect()
+}
+
+/// Returns the position of the `n`th occurrence of `id` in `children`,
+/// wrapping around if the cost function asks for it more often.
+fn nth_position(children: &[Id], id: Id, n: usize) -> usize {
+    let positions: Vec<usize> = (0..children.len()).filter(|&i| children[i] == id).collect();
+    positions[n % positions.len()]
+}
+
 impl<'a, CF, L, N> Extractor<'a, CF, L, N>
 where
     CF: CostFunction<L>,
@@ -209,11 +507,24 @@ where
     /// performs the greedy search for cheapest representative of each
     /// eclass.
     pub fn new(egraph: &'a EGraph<L, N>, cost_function: CF) -> Self {
+        Self::new_constrained(egraph, cost_function, ExtractionConstraints::default())
+    }
+
+    /// Like [`new`](Extractor::new()), but only picks e-nodes allowed by
+    /// `constraints`.
+    /// See [`ExtractionConstraints`].
+    pub fn new_constrained(
+        egraph: &'a EGraph<L, N>,
+        cost_function: CF,
+        mut constraints: ExtractionConstraints<L>,
+    ) -> Self {
+        constraints.canonicalize(egraph);
         let costs = HashMap::default();
         let mut extractor = Extractor {
             costs,
             egraph,
             cost_function,
+            constraints,
         };
         extractor.find_costs();
 
@@ -222,10 +533,187 @@ where
 
     /// Find the cheapest (lowest cost) represented `RecExpr` in the
     /// given eclass.
+    ///
+    /// # Panics
+    /// Panics if no finite term could be extracted from `eclass`;
+    /// see [`try_find_best`](Extractor::try_find_best()).
     pub fn find_best(&self, eclass: Id) -> (CF::Cost, RecExpr<L>) {
-        let (cost, root) = self.costs[&self.egraph.find(eclass)].clone();
-        let expr = root.build_recexpr(|id| self.find_best_node(id).clone());
-        (cost, expr)
+        self.try_find_best(eclass)
+            .unwrap_or_else(|e| panic!("Can't extract: {}", e))
+    }
+
+    /// Like [`find_best`](Extractor::find_best()), but returns an error
+    /// instead of panicking if `eclass` has no finite term.
+    ///
+    /// Extraction only ever picks e-nodes whose children already have a
+    /// term, so it never follows a cycle: e-classes with some way out of a
+    /// cycle still get their best acyclic term.
+    /// An e-class fails only if every one of its e-nodes leads back into a
+    /// cycle (or into another failing e-class), which can happen after
+    /// removing e-nodes from the egraph.
+    /// In that case the error lists all such e-classes reachable from
+    /// `eclass`.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let x = egraph.add(S::leaf("x"));
+    /// let a = egraph.add(S::leaf("a"));
+    /// let fa = egraph.add(S::new("f", vec![a]));
+    /// let g = egraph.add(S::new("g", vec![fa, x]));
+    /// egraph.union(a, fa);
+    /// egraph.rebuild();
+    ///
+    /// let extractor = Extractor::new(&egraph, AstSize);
+    /// assert_eq!(extractor.try_find_best(g).unwrap().0, 3);
+    ///
+    /// // leave only (f a) in its e-class, which refers back to itself
+    /// let a = egraph.find(a);
+    /// for class in egraph.classes_mut() {
+    ///     if class.id == a {
+    ///         class.nodes.retain(|n| !n.is_leaf());
+    ///     }
+    /// }
+    ///
+    /// let extractor = Extractor::new(&egraph, AstSize);
+    /// assert!(extractor.try_find_best(x).is_ok());
+    /// match extractor.try_find_best(g) {
+    ///     Err(ExtractionError::Cyclic(ids)) => assert_eq!(ids.len(), 2),
+    ///     _ => panic!("expected a cycle"),
+    /// }
+    /// ```
+    pub fn try_find_best(&self, eclass: Id) -> Result<(CF::Cost, RecExpr<L>), ExtractionError> {
+        let eclass = self.egraph.find(eclass);
+        match self.costs.get(&eclass) {
+            Some((cost, root)) => {
+                let expr = root.build_recexpr(|id| self.find_best_node(id).clone());
+                Ok((cost.clone(), expr))
+            }
+            None if self.constraints.is_empty() => {
+                Err(ExtractionError::Cyclic(self.unextractable_from(eclass)))
+            }
+            None => Err(ExtractionError::Constrained(
+                self.unextractable_from(eclass),
+            )),
+        }
+    }
+
+    /// Find the cheapest represented terms for several e-classes at once,
+    /// returning a single [`RecExpr`] that holds all of them along with
+    /// the index of each root in it, in the order of `eclasses`.
+    ///
+    /// Costs are computed only once when the extractor is created, and
+    /// subterms shared between the roots appear only once in the result,
+    /// so this is the way to extract, for example, every output of a
+    /// program from the same egraph.
+    ///
+    /// # Panics
+    /// Panics if no finite term could be extracted from one of `eclasses`;
+    /// see [`try_find_best`](Extractor::try_find_best()).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let a = egraph.add_expr(&"(+ (* x y) 1)".parse().unwrap());
+    /// let b = egraph.add_expr(&"(- (* x y) 2)".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let extractor = Extractor::new(&egraph, AstSize);
+    /// let (expr, roots) = extractor.find_best_many(&[a, b]);
+    /// // x, y, and (* x y) are only in there once
+    /// assert_eq!(expr.as_ref().len(), 7);
+    /// assert_eq!(expr[roots[0]].op, Symbol::from("+"));
+    /// // the last root is also the root of the whole RecExpr
+    /// assert_eq!(expr.to_string(), "(- (* x y) 2)");
+    /// ```
+    pub fn find_best_many(&self, eclasses: &[Id]) -> (RecExpr<L>, Vec<Id>) {
+        let mut expr = RecExpr::default();
+        let added = self.add_best_many(eclasses, |node| expr.add(node));
+        let roots = eclasses
+            .iter()
+            .map(|&root| added[&self.egraph.find(root)])
+            .collect();
+        (expr, roots)
+    }
+
+    /// Builds a fresh egraph containing just the best terms for `eclasses`,
+    /// to keep running on.
+    ///
+    /// Running equality saturation in epochs, compacting the egraph like
+    /// this in between, keeps long optimizations tractable: everything but
+    /// the best terms found so far is forgotten.
+    /// Returns the new egraph, with `eclasses` [marked as
+    /// roots](EGraph::mark_roots()), and the new id of every e-class the best
+    /// terms went through, keyed by its old canonical id.
+    ///
+    /// # Panics
+    /// Panics if no finite term could be extracted from one of `eclasses`.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rw!("add-0"; "(+ ?a 0)" => "?a"),
+    ///     rw!("mul-1"; "(* ?a 1)" => "?a"),
+    /// ];
+    /// let expr = "(+ 0 (* (+ x 0) 1))".parse().unwrap();
+    /// let (mut egraph, mut root) = EGraph::<SymbolLang, ()>::from_expr((), &expr);
+    /// for _epoch in 0..3 {
+    ///     let runner = Runner::default()
+    ///         .with_egraph(egraph)
+    ///         .with_iter_limit(2)
+    ///         .run(rules);
+    ///     let extractor = Extractor::new(&runner.egraph, AstSize);
+    ///     let (next, ids) = extractor.reseed(&[root], ());
+    ///     root = ids[&runner.egraph.find(root)];
+    ///     egraph = next;
+    /// }
+    /// assert_eq!(egraph.number_of_classes(), 1);
+    /// assert_eq!(egraph.lookup_expr(&"x".parse().unwrap()), Some(root));
+    /// ```
+    pub fn reseed(&self, eclasses: &[Id], analysis: N) -> (EGraph<L, N>, HashMap<Id, Id>) {
+        let mut egraph = EGraph::new(analysis);
+        let added = self.add_best_many(eclasses, |node| egraph.add(node));
+        let roots: Vec<Id> = eclasses
+            .iter()
+            .map(|&root| added[&self.egraph.find(root)])
+            .collect();
+        egraph.mark_roots(&roots);
+        egraph.rebuild();
+        (egraph, added)
+    }
+
+    /// Adds the best term of each of `eclasses` with `add`, sharing common
+    /// subterms, and returns the ids `add` gave each e-class along the way.
+    fn add_best_many(&self, eclasses: &[Id], mut add: impl FnMut(L) -> Id) -> HashMap<Id, Id> {
+        let mut added: HashMap<Id, Id> = HashMap::default();
+        for &root in eclasses {
+            let root = self.egraph.find(root);
+            let mut stack = vec![root];
+            while let Some(&id) = stack.last() {
+                if added.contains_key(&id) {
+                    stack.pop();
+                    continue;
+                }
+                let node = self.find_best_node(id);
+                let len = stack.len();
+                for &child in node.children() {
+                    let child = self.egraph.find(child);
+                    if !added.contains_key(&child) {
+                        stack.push(child);
+                    }
+                }
+                if stack.len() == len {
+                    let node = node.clone().map_children(|c| added[&self.egraph.find(c)]);
+                    added.insert(id, add(node));
+                    stack.pop();
+                }
+            }
+        }
+        added
     }
 
     /// Find the cheapest e-node in the given e-class.
@@ -239,39 +727,306 @@ where
         cost.clone()
     }
 
-    fn node_total_cost(&mut self, node: &L) -> Option<CF::Cost> {
-        let eg = &self.egraph;
-        let has_cost = |id| self.costs.contains_key(&eg.find(id));
-        if node.all(has_cost) {
-            let costs = &self.costs;
-            let cost_f = |id| costs[&eg.find(id)].0.clone();
-            Some(self.cost_function.cost(node, cost_f))
-        } else {
-            None
+    /// Find the `k` cheapest distinct `RecExpr`s represented in the given
+    /// eclass, sorted by increasing cost.
+    ///
+    /// Returns fewer than `k` expressions if the eclass doesn't represent
+    /// that many.
+    /// The cost function should be monotonic, as the terms of each e-node are
+    /// built from the cheapest terms of its children first.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let a = egraph.add_expr(&"(+ x (* y 1))".parse().unwrap());
+    /// let b = egraph.add_expr(&"(+ x y)".parse().unwrap());
+    /// let y = egraph.add_expr(&"y".parse().unwrap());
+    /// let y1 = egraph.add_expr(&"(* y 1)".parse().unwrap());
+    /// egraph.union(y, y1);
+    /// egraph.rebuild();
+    /// assert_eq!(egraph.find(a), egraph.find(b));
+    ///
+    /// let mut extractor = Extractor::new(&egraph, AstSize);
+    /// let best: Vec<String> = extractor
+    ///     .find_best_k(a, 3)
+    ///     .into_iter()
+    ///     .map(|(_, expr)| expr.to_string())
+    ///     .collect();
+    /// assert_eq!(best, ["(+ x y)", "(+ x (* y 1))", "(+ x (* (* y 1) 1))"]);
+    /// ```
+    pub fn find_best_k(&mut self, eclass: Id, k: usize) -> Vec<(CF::Cost, RecExpr<L>)> {
+        let egraph = self.egraph;
+        let root = egraph.find(eclass);
+        if k == 0 {
+            return vec![];
         }
+
+        let classes = egraph.descendants(&[root], usize::MAX);
+
+        let mut best: HashMap<Id, Vec<(CF::Cost, RecExpr<L>)>> =
+            classes.iter().map(|&id| (id, vec![])).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &id in &classes {
+                for node in &egraph[id].nodes {
+                    if !self.constraints.allows(id, node) {
+                        continue;
+                    }
+                    for (cost, expr) in self.node_candidates(node, &best, k) {
+                        let list = best.get_mut(&id).unwrap();
+                        if list.iter().any(|(_, e)| *e == expr) {
+                            continue;
+                        }
+                        if list.len() == k {
+                            if !(cost < list[k - 1].0) {
+                                continue;
+                            }
+                            list.pop();
+                        }
+                        let pos = list
+                            .iter()
+                            .position(|(c, _)| cost < *c)
+                            .unwrap_or(list.len());
+                        list.insert(pos, (cost, expr));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        best.remove(&root).unwrap()
     }
 
-    fn find_costs(&mut self) {
-        let mut did_something = true;
-        while did_something {
-            did_something = false;
-
-            for class in self.egraph.classes() {
-                let pass = self.make_pass(class);
-                match (self.costs.get(&class.id), pass) {
-                    (None, Some(new)) => {
-                        self.costs.insert(class.id, new);
-                        did_something = true;
+    /// Finds the Pareto frontier of terms in `eclass`: the terms whose cost
+    /// no other term [dominates](ParetoCost::dominates()).
+    ///
+    /// Use a tuple of cost functions, such as latency and code size, to
+    /// trade objectives off against each other.
+    /// Only one term is kept per cost, and the frontier of each e-class is
+    /// capped at `max_size` terms, keeping the lexicographically cheapest.
+    /// The result is sorted lexicographically by cost.
+    /// Each e-node only builds its `max_size` lexicographically cheapest
+    /// terms from the frontiers of its children, so a term that comes after
+    /// as many dominated ones can be missed.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let wide = egraph.add_expr(&"(g a b c d e)".parse().unwrap());
+    /// let deep = egraph.add_expr(&"(h (k a))".parse().unwrap());
+    /// let deeper = egraph.add_expr(&"(h (k (k a)))".parse().unwrap());
+    /// egraph.union(wide, deep);
+    /// egraph.union(wide, deeper);
+    /// egraph.rebuild();
+    ///
+    /// let mut extractor = Extractor::new(&egraph, (AstSize, AstDepth));
+    /// let frontier: Vec<_> = extractor
+    ///     .find_pareto(wide, 4)
+    ///     .into_iter()
+    ///     .map(|(cost, expr)| (cost, expr.to_string()))
+    ///     .collect();
+    /// assert_eq!(
+    ///     frontier,
+    ///     [((3, 3), "(h (k a))".to_string()), ((6, 2), "(g a b c d e)".to_string())]
+    /// );
+    /// ```
+    pub fn find_pareto(&mut self, eclass: Id, max_size: usize) -> Vec<(CF::Cost, RecExpr<L>)>
+    where
+        CF::Cost: ParetoCost,
+    {
+        let egraph = self.egraph;
+        let root = egraph.find(eclass);
+        if max_size == 0 {
+            return vec![];
+        }
+
+        let classes = egraph.descendants(&[root], usize::MAX);
+
+        let mut frontiers: HashMap<Id, Vec<(CF::Cost, RecExpr<L>)>> =
+            classes.iter().map(|&id| (id, vec![])).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &id in &classes {
+                for node in &egraph[id].nodes {
+                    if !self.constraints.allows(id, node) {
+                        continue;
                     }
-                    (Some(old), Some(new)) if new.0 < old.0 => {
-                        self.costs.insert(class.id, new);
-                        did_something = true;
+                    for (cost, expr) in self.node_candidates(node, &frontiers, max_size) {
+                        let list = frontiers.get_mut(&id).unwrap();
+                        if list.iter().any(|(c, _)| *c == cost || c.dominates(&cost)) {
+                            continue;
+                        }
+                        list.retain(|(c, _)| !cost.dominates(c));
+                        let pos = list
+                            .iter()
+                            .position(|(c, _)| cost < *c)
+                            .unwrap_or(list.len());
+                        if pos < max_size {
+                            list.insert(pos, (cost, expr));
+                            list.truncate(max_size);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        frontiers.remove(&root).unwrap()
+    }
+
+    /// Computes the cost of instantiating `pat` with `subst`,
+    /// using the best costs of the e-classes that `subst` refers to.
+    ///
+    /// Returns `None` if any of those e-classes has no cost.
+    pub(crate) fn instantiation_cost(
+        &mut self,
+        pat: &PatternAst<L>,
+        subst: &Subst,
+    ) -> Option<CF::Cost> {
+        let mut costs: Vec<CF::Cost> = Vec::with_capacity(pat.as_ref().len());
+        for node in pat.as_ref() {
+            let cost = match node {
+                ENodeOrVar::Var(v) => {
+                    let id = self.egraph.find(*subst.get(*v)?);
+                    self.costs.get(&id)?.0.clone()
+                }
+                ENodeOrVar::ENode(n) => self
+                    .cost_function
+                    .cost(n, |i| costs[usize::from(i)].clone()),
+            };
+            costs.push(cost);
+        }
+        costs.pop()
+    }
+
+    /// Like [`find_best_cost`](Extractor::find_best_cost), but returns `None`
+    /// if no cost could be computed for the e-class.
+    pub(crate) fn try_find_best_cost(&self, eclass: Id) -> Option<&CF::Cost> {
+        self.costs.get(&self.egraph.find(eclass)).map(|(c, _)| c)
+    }
+
+    /// Builds terms for `node` from the known terms of its children,
+    /// keeping the `k` cheapest, sorted by cost.
+    ///
+    /// A term is chosen for each child position, so the occurrences of a
+    /// repeated child can get different terms; the cost function only sees
+    /// e-class ids, so the `n`th time it asks for a repeated child, it gets
+    /// the cost of that child's `n`th occurrence.
+    /// The children's lists are sorted, so they are merged lazily, cheapest
+    /// combination first: as long as the cost function is monotonic, only
+    /// about `k` times as many combinations as there are children are costed.
+    /// Combinations whose cost isn't comparable to itself, like a NaN, are
+    /// skipped.
+    fn node_candidates(
+        &mut self,
+        node: &L,
+        best: &HashMap<Id, Vec<(CF::Cost, RecExpr<L>)>>,
+        k: usize,
+    ) -> Vec<(CF::Cost, RecExpr<L>)> {
+        let egraph = self.egraph;
+        let node = node.clone().map_children(|c| egraph.find(c));
+        let children: Vec<Id> = node.children().to_vec();
+        let lists: Vec<&Vec<(CF::Cost, RecExpr<L>)>> = children.iter().map(|c| &best[c]).collect();
+        if k == 0 || lists.iter().any(|list| list.is_empty()) {
+            return vec![];
+        }
+
+        let mut heap = BinaryHeap::new();
+        let mut seen: HashSet<Vec<usize>> = HashSet::default();
+        let mut todo = vec![vec![0; children.len()]];
+        let mut candidates = Vec::with_capacity(k.min(16));
+        loop {
+            // cost the new combinations, skipping over the incomparable ones
+            while let Some(choice) = todo.pop() {
+                if !seen.insert(choice.clone()) {
+                    continue;
+                }
+                let mut asked: HashMap<Id, usize> = HashMap::default();
+                let cost = self.cost_function.cost(&node, |id| {
+                    let n = asked.entry(id).or_insert(0);
+                    let i = nth_position(&children, id, *n);
+                    *n += 1;
+                    lists[i][choice[i]].0.clone()
+                });
+                if cost.partial_cmp(&cost).is_some() {
+                    heap.push(Candidate { cost, choice });
+                } else {
+                    todo.extend(successors(&choice, &lists));
+                }
+            }
+            let Candidate { cost, choice } = match heap.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            todo.extend(successors(&choice, &lists));
+
+            let mut expr = RecExpr::default();
+            let mut roots = Vec::with_capacity(children.len());
+            for (i, list) in lists.iter().enumerate() {
+                let offset = expr.as_ref().len();
+                for n in list[choice[i]].1.as_ref() {
+                    expr.add(
+                        n.clone()
+                            .map_children(|c| Id::from(usize::from(c) + offset)),
+                    );
+                }
+                roots.push(Id::from(expr.as_ref().len() - 1));
+            }
+            let mut roots = roots.into_iter();
+            expr.add(node.clone().map_children(|_| roots.next().unwrap()));
+            candidates.push((cost, expr));
+            if candidates.len() == k {
+                break;
+            }
+        }
+        candidates
+    }
+
+    /// Returns the e-classes reachable from `eclass` that have no cost,
+    /// sorted by id.
+    fn unextractable_from(&self, eclass: Id) -> Vec<Id> {
+        let mut stack = vec![eclass];
+        let mut seen = HashSet::default();
+        seen.insert(eclass);
+        let mut failed = vec![];
+        while let Some(id) = stack.pop() {
+            if self.costs.contains_key(&id) {
+                continue;
+            }
+            failed.push(id);
+            for node in &self.egraph[id].nodes {
+                for &child in node.children() {
+                    let child = self.egraph.find(child);
+                    if seen.insert(child) {
+                        stack.push(child);
                     }
-                    _ => (),
                 }
             }
         }
+        failed.sort_unstable();
+        failed
+    }
 
+    fn node_total_cost(&mut self, node: &L) -> Option<CF::Cost> {
+        node_total_cost(self.egraph, &self.costs, &mut self.cost_function, node)
+    }
+
+    fn find_costs(&mut self) {
+        let classes: Vec<Id> = self.egraph.classes().map(|class| class.id).collect();
+        self.costs = find_costs(
+            self.egraph,
+            &classes,
+            &mut self.cost_function,
+            &self.constraints,
+        );
+        self.warn_missing_costs();
+    }
+
+    fn warn_missing_costs(&self) {
         for class in self.egraph.classes() {
             if !self.costs.contains_key(&class.id) {
                 log::warn!(
@@ -282,15 +1037,286 @@ where
             }
         }
     }
+}
 
-    fn make_pass(&mut self, eclass: &EClass<L, N::Data>) -> Option<(CF::Cost, L)> {
-        let (cost, node) = eclass
-            .iter()
-            .map(|n| (self.node_total_cost(n), n))
-            .min_by(|a, b| cmp(&a.0, &b.0))
-            .unwrap_or_else(|| panic!("Can't extract, eclass is empty: {:#?}", eclass));
-        cost.map(|c| (c, node.clone()))
+/// Finds the cheapest term of each of the canonical e-classes `classes`,
+/// which must include all the e-classes reachable from them, using only
+/// the e-nodes allowed by `constraints`.
+///
+/// E-classes without a finite term are left out.
+pub(crate) fn find_costs<L, N, CF>(
+    egraph: &EGraph<L, N>,
+    classes: &[Id],
+    cost_function: &mut CF,
+    constraints: &ExtractionConstraints<L>,
+) -> HashMap<Id, (CF::Cost, L)>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: CostFunction<L>,
+{
+    let mut costs: HashMap<Id, (CF::Cost, L)> = HashMap::default();
+    let mut did_something = true;
+    while did_something {
+        did_something = false;
+
+        for &id in classes {
+            let pass = make_pass(egraph, &costs, cost_function, constraints, &egraph[id]);
+            match (costs.get(&id), pass) {
+                (None, Some(new)) => {
+                    costs.insert(id, new);
+                    did_something = true;
+                }
+                (Some(old), Some(new)) if new.0 < old.0 => {
+                    costs.insert(id, new);
+                    did_something = true;
+                }
+                _ => (),
+            }
+        }
+    }
+    costs
+}
+
+/** An extractor that is kept up to date as its [`EGraph`] grows.
+
+An [`Extractor`] borrows its egraph and computes every cost when it is
+created, which is wasteful when extracting the best term after every
+iteration of a run.
+An `IncrementalExtractor` instead keeps its costs between calls to
+[`update`](IncrementalExtractor::update()), which only revisits the
+e-classes that were created or merged since the last update and the
+e-classes whose cost depends on them.
+
+Since e-graphs only grow, costs only go down, so the result is the same as
+creating a new [`Extractor`] with the same cost function, up to ties.
+
+```
+use egg::{rewrite as rw, *};
+let rules: &[Rewrite<SymbolLang, ()>] = &[
+    rw!("expand"; "(f ?a)" => "(g ?a ?a)"),
+    rw!("collapse"; "(g ?a ?a)" => "?a"),
+];
+let mut runner = Runner::default()
+    .with_expr(&"(f x)".parse().unwrap())
+    .with_iter_limit(1);
+let mut extractor = IncrementalExtractor::new(AstSize);
+let mut best = vec![];
+for _ in 0..3 {
+    runner = runner.run(rules);
+    extractor.update(&runner.egraph);
+    best.push(extractor.find_best(&runner.egraph, runner.roots[0]).1.to_string());
+    runner.stop_reason = None;
+    runner.iterations.clear();
+}
+assert_eq!(best, ["(f x)", "x", "x"]);
+```
+**/
+#[derive(Debug)]
+pub struct IncrementalExtractor<CF: CostFunction<L>, L: Language> {
+    cost_function: CF,
+    costs: HashMap<Id, (CF::Cost, L)>,
+    constraints: ExtractionConstraints<L>,
+    n_ids: usize,
+}
+
+impl<CF: CostFunction<L>, L: Language> IncrementalExtractor<CF, L> {
+    /// Creates an `IncrementalExtractor` that hasn't seen any e-class yet;
+    /// call [`update`](IncrementalExtractor::update()) before extracting.
+    pub fn new(cost_function: CF) -> Self {
+        Self {
+            cost_function,
+            costs: Default::default(),
+            constraints: Default::default(),
+            n_ids: 0,
+        }
+    }
+
+    /// Brings the costs up to date with `egraph`, which must be the egraph
+    /// passed to previous updates, grown since then and
+    /// [rebuilt](EGraph::rebuild()).
+    ///
+    /// The classes created since the last update are found from their ids.
+    /// Merged classes are found by checking which of the known classes are
+    /// no longer canonical, which takes time linear in the number of
+    /// classes but is much cheaper than recomputing their costs.
+    pub fn update<N: Analysis<L>>(&mut self, egraph: &EGraph<L, N>) {
+        let mut todo: Vec<Id> = (self.n_ids..egraph.number_of_ids())
+            .map(|i| egraph.find(Id::from(i)))
+            .collect();
+        self.n_ids = egraph.number_of_ids();
+
+        let merged: Vec<Id> = self
+            .costs
+            .keys()
+            .copied()
+            .filter(|&id| egraph.find(id) != id)
+            .collect();
+        for id in merged {
+            let (cost, node) = self.costs.remove(&id).unwrap();
+            let root = egraph.find(id);
+            let better = match self.costs.get(&root) {
+                Some(old) => cost < old.0,
+                None => true,
+            };
+            if better {
+                self.costs.insert(root, (cost, node));
+            }
+            todo.push(root);
+            todo.extend(egraph[root].parents().map(|(_, p)| egraph.find(p)));
+        }
+
+        while let Some(id) = todo.pop() {
+            let class = &egraph[id];
+            let pass = make_pass(
+                egraph,
+                &self.costs,
+                &mut self.cost_function,
+                &self.constraints,
+                class,
+            );
+            if let Some(new) = pass {
+                let better = match self.costs.get(&class.id) {
+                    Some(old) => new.0 < old.0,
+                    None => true,
+                };
+                if better {
+                    self.costs.insert(class.id, new);
+                    todo.extend(class.parents().map(|(_, p)| egraph.find(p)));
+                }
+            }
+        }
+    }
+
+    /// Finds the cheapest term in `eclass` as of the last
+    /// [`update`](IncrementalExtractor::update()).
+    ///
+    /// # Panics
+    /// Panics if `eclass` has no finite term.
+    pub fn find_best<N: Analysis<L>>(
+        &self,
+        egraph: &EGraph<L, N>,
+        eclass: Id,
+    ) -> (CF::Cost, RecExpr<L>) {
+        let cost = self
+            .find_best_cost(egraph, eclass)
+            .unwrap_or_else(|| panic!("Can't extract e-class {}", eclass));
+        let root = &self.costs[&egraph.find(eclass)].1;
+        let expr = root.build_recexpr(|id| self.costs[&egraph.find(id)].1.clone());
+        (cost, expr)
+    }
+
+    /// Returns the cost of the cheapest term in `eclass`, if it has one.
+    pub fn find_best_cost<N: Analysis<L>>(
+        &self,
+        egraph: &EGraph<L, N>,
+        eclass: Id,
+    ) -> Option<CF::Cost> {
+        self.costs
+            .get(&egraph.find(eclass))
+            .map(|(cost, _)| cost.clone())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, CF, L, N> Extractor<'a, CF, L, N>
+where
+    CF: CostFunction<L> + Clone + Send + Sync,
+    CF::Cost: Send + Sync,
+    L: Language + Send + Sync,
+    N: Analysis<L> + Sync,
+    N::Data: Sync,
+{
+    /// Like [`new`](Extractor::new()), but computes the costs on all cores
+    /// using [`rayon`](https://docs.rs/rayon).
+    ///
+    /// Each round computes the cost of every e-class in parallel from the
+    /// costs of the previous round, cloning `cost_function` once per
+    /// worker, until no cost improves.
+    /// The best costs are the same as with [`new`](Extractor::new()), but
+    /// among e-nodes of equal cost a different one may be picked.
+    ///
+    /// Requires the `parallel` feature.
+    pub fn new_parallel(egraph: &'a EGraph<L, N>, cost_function: CF) -> Self {
+        use rayon::prelude::*;
+
+        let constraints = ExtractionConstraints::default();
+        let classes: Vec<&EClass<L, N::Data>> = egraph.classes().collect();
+        let mut costs: HashMap<Id, (CF::Cost, L)> = HashMap::default();
+        loop {
+            let updates: Vec<(Id, (CF::Cost, L))> = classes
+                .par_iter()
+                .map_init(
+                    || cost_function.clone(),
+                    |cf, class| {
+                        let new = make_pass(egraph, &costs, cf, &constraints, class)?;
+                        match costs.get(&class.id) {
+                            Some(old) if new.0 < old.0 => Some((class.id, new)),
+                            Some(_) => None,
+                            None => Some((class.id, new)),
+                        }
+                    },
+                )
+                .flatten()
+                .collect();
+            if updates.is_empty() {
+                break;
+            }
+            costs.extend(updates);
+        }
+
+        let extractor = Extractor {
+            costs,
+            egraph,
+            cost_function,
+            constraints,
+        };
+        extractor.warn_missing_costs();
+        extractor
+    }
+}
+
+fn node_total_cost<L, N, CF>(
+    egraph: &EGraph<L, N>,
+    costs: &HashMap<Id, (CF::Cost, L)>,
+    cost_function: &mut CF,
+    node: &L,
+) -> Option<CF::Cost>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: CostFunction<L>,
+{
+    let has_cost = |id| costs.contains_key(&egraph.find(id));
+    if node.all(has_cost) {
+        let cost_f = |id| costs[&egraph.find(id)].0.clone();
+        Some(cost_function.cost(node, cost_f))
+    } else {
+        None
+    }
+}
+
+fn make_pass<L, N, CF>(
+    egraph: &EGraph<L, N>,
+    costs: &HashMap<Id, (CF::Cost, L)>,
+    cost_function: &mut CF,
+    constraints: &ExtractionConstraints<L>,
+    eclass: &EClass<L, N::Data>,
+) -> Option<(CF::Cost, L)>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: CostFunction<L>,
+{
+    if eclass.is_empty() {
+        panic!("Can't extract, eclass is empty: {:#?}", eclass);
     }
+    let (cost, node) = eclass
+        .iter()
+        .filter(|n| constraints.allows(eclass.id, n))
+        .map(|n| (node_total_cost(egraph, costs, cost_function, n), n))
+        .min_by(|a, b| cmp(&a.0, &b.0))?;
+    cost.map(|c| (c, node.clone()))
 }
 
 #[cfg(test)]
@@ -312,4 +1338,68 @@ mod tests {
         let (_, best_expr) = extractor.find_best(runner.roots[0]);
         assert_eq!(best_expr, start);
     }
+
+    struct CheckChildren(HashSet<Id>);
+
+    impl CostFunction<SymbolLang> for CheckChildren {
+        type Cost = usize;
+        fn cost<C>(&mut self, enode: &SymbolLang, costs: C) -> usize
+        where
+            C: FnMut(Id) -> usize,
+        {
+            assert!(enode.all(|id| self.0.contains(&id)), "{:?}", enode);
+            AstSize.cost(enode, costs)
+        }
+    }
+
+    #[test]
+    fn best_k_costs_real_children() {
+        let mut egraph = EGraph::<SymbolLang, ()>::default();
+        let root = egraph.add_expr(&"(+ (* 7 1) (+ 7 7))".parse().unwrap());
+        let seven = egraph.add_expr(&"7".parse().unwrap());
+        let times = egraph.add_expr(&"(* 7 1)".parse().unwrap());
+        egraph.union(seven, times);
+        egraph.rebuild();
+
+        let ids = egraph.classes().map(|c| c.id).collect();
+        let mut extractor = Extractor::new(&egraph, CheckChildren(ids));
+        let mut best: Vec<(usize, String)> = extractor
+            .find_best_k(root, 4)
+            .into_iter()
+            .map(|(cost, expr)| (cost, expr.to_string()))
+            .collect();
+        best.sort();
+        assert_eq!(
+            best,
+            [
+                (5, "(+ 7 (+ 7 7))".to_string()),
+                (7, "(+ (* 7 1) (+ 7 7))".to_string()),
+                // both 7s in (+ 7 7) are the same e-class, but get their own terms
+                (7, "(+ 7 (+ (* 7 1) 7))".to_string()),
+                (7, "(+ 7 (+ 7 (* 7 1)))".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn parallel_extraction_agrees() {
+        let rules: &[Rewrite<SymbolLang, ()>] = &[
+            rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+            rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+            rewrite!("double"; "(+ ?a ?a)" => "(* 2 ?a)"),
+        ];
+        let start = "(+ x (+ 0 (+ y (+ x (+ 0 y)))))".parse().unwrap();
+        let runner = Runner::default().with_expr(&start).run(rules);
+
+        let serial = Extractor::new(&runner.egraph, AstSize);
+        let parallel = Extractor::new_parallel(&runner.egraph, AstSize);
+        for class in runner.egraph.classes() {
+            assert_eq!(
+                serial.find_best_cost(class.id),
+                parallel.find_best_cost(class.id)
+            );
+        }
+    }
 }
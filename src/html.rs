@@ -0,0 +1,207 @@
+/*!
+Self-contained, interactive HTML export of an [`EGraph`].
+
+See [`EGraph::to_html`].
+!*/
+
+use std::fmt::Display;
+
+use crate::{Analysis, EGraph, Language};
+
+impl<L: Language + Display, N: Analysis<L>> EGraph<L, N> {
+    /// Exports this egraph as a self-contained HTML page with an
+    /// interactive rendering of it.
+    ///
+    /// The page doesn't load anything from the network. E-classes are laid
+    /// out left to right, from leaves to the terms that use them.
+    /// You can pan by dragging and zoom with the mouse wheel,
+    /// collapse an e-class by clicking on its header,
+    /// and hover over e-classes and e-nodes to see more about them.
+    /// This stays usable for egraphs that are too big for [`Dot`](crate::Dot)
+    /// output to be readable.
+    ///
+    /// The egraph is embedded in the format of [`to_json`](EGraph::to_json()),
+    /// so it should be [rebuilt](EGraph::rebuild()) first.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// egraph.add_expr(&"(+ x (* y 2))".parse().unwrap());
+    /// egraph.rebuild();
+    /// std::fs::write("target/egraph.html", egraph.to_html()).unwrap();
+    /// ```
+    pub fn to_html(&self) -> String {
+        html_page(&self.to_json())
+    }
+
+    /// Like [`to_html`](EGraph::to_html()), but shows the string returned by
+    /// `data` for each e-class when hovering over it,
+    /// as in [`to_json_with_data`](EGraph::to_json_with_data()).
+    pub fn to_html_with_data<F>(&self, data: F) -> String
+    where
+        F: FnMut(&N::Data) -> String,
+    {
+        html_page(&self.to_json_with_data(data))
+    }
+}
+
+fn html_page(json: &str) -> String {
+    // a "</" inside the JSON would end the script element early
+    let json = json.replace("</", "<\\/");
+    HTML_TEMPLATE.replace("/*EGRAPH_JSON*/", &json)
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>egg e-graph</title>
+<style>
+  html, body { margin: 0; height: 100%; overflow: hidden; font-family: monospace; }
+  svg { width: 100%; height: 100%; cursor: grab; }
+  .class rect.box { fill: #f8f8f8; stroke: #888; stroke-dasharray: 4 2; }
+  .class rect.header { fill: #dde6f0; cursor: pointer; }
+  .node rect { fill: white; stroke: #444; }
+  .node:hover rect { fill: #fff3c4; }
+  .edge { fill: none; stroke: #6a7f99; stroke-opacity: 0.6; }
+  #help { position: fixed; top: 4px; left: 8px; color: #666; font-size: 12px; }
+</style>
+</head>
+<body>
+<div id="help">drag to pan, scroll to zoom, click an e-class header to collapse it</div>
+<svg id="egraph" xmlns="http://www.w3.org/2000/svg"><g id="scene"></g></svg>
+<script>
+const data = /*EGRAPH_JSON*/;
+const NS = "http://www.w3.org/2000/svg";
+const COL_WIDTH = 220, CLASS_WIDTH = 160, HEADER = 18, NODE = 20, GAP = 24;
+const svg = document.getElementById("egraph");
+const scene = document.getElementById("scene");
+const collapsed = new Set();
+
+// rank each class by the height of its shallowest term, so that children
+// are always to the left of the nodes that use them (except for cycles)
+const rank = new Map(data.classes.map(c => [c.id, Infinity]));
+for (let changed = true; changed;) {
+  changed = false;
+  for (const c of data.classes) {
+    for (const n of c.nodes) {
+      const r = n.children.reduce((m, ch) => Math.max(m, rank.get(ch) + 1), 0);
+      if (r < rank.get(c.id)) { rank.set(c.id, r); changed = true; }
+    }
+  }
+}
+
+function el(tag, attrs, parent) {
+  const e = document.createElementNS(NS, tag);
+  for (const k in attrs) e.setAttribute(k, attrs[k]);
+  parent.appendChild(e);
+  return e;
+}
+
+function tooltip(parent, text) {
+  el("title", {}, parent).textContent = text;
+}
+
+function render() {
+  scene.textContent = "";
+  const columns = new Map();
+  const pos = new Map();
+  for (const c of data.classes) {
+    const r = rank.get(c.id);
+    const col = r === Infinity ? -1 : r;
+    const y = columns.get(col) || 0;
+    const height = HEADER + (collapsed.has(c.id) ? 0 : c.nodes.length * NODE);
+    pos.set(c.id, { x: (col + 1) * COL_WIDTH, y: y, h: height });
+    columns.set(col, y + height + GAP);
+  }
+
+  const edges = el("g", {}, scene);
+  for (const c of data.classes) {
+    const p = pos.get(c.id);
+    const g = el("g", { class: "class" }, scene);
+    el("rect", { class: "box", x: p.x, y: p.y, width: CLASS_WIDTH, height: p.h }, g);
+    const header = el("rect", { class: "header", x: p.x, y: p.y, width: CLASS_WIDTH, height: HEADER }, g);
+    const label = el("text", { x: p.x + 4, y: p.y + 13 }, g);
+    label.textContent = "#" + c.id + (collapsed.has(c.id) ? " (" + c.nodes.length + " nodes)" : "");
+    let info = "e-class " + c.id + "\n" + c.nodes.length + " nodes";
+    if (c.data !== undefined) info += "\ndata: " + c.data;
+    tooltip(header, info);
+    tooltip(label, info);
+    header.onclick = label.onclick = () => {
+      collapsed.has(c.id) ? collapsed.delete(c.id) : collapsed.add(c.id);
+      render();
+    };
+    if (collapsed.has(c.id)) continue;
+
+    c.nodes.forEach((n, i) => {
+      const y = p.y + HEADER + i * NODE;
+      const ng = el("g", { class: "node" }, g);
+      el("rect", { x: p.x + 4, y: y + 2, width: CLASS_WIDTH - 8, height: NODE - 4 }, ng);
+      const t = el("text", { x: p.x + 8, y: y + 14 }, ng);
+      t.textContent = n.op.length > 18 ? n.op.slice(0, 17) + "…" : n.op;
+      tooltip(ng, n.op + "\nchildren: " + (n.children.map(ch => "#" + ch).join(" ") || "none"));
+      for (const ch of n.children) {
+        const q = pos.get(ch);
+        const x1 = p.x + 4, y1 = y + NODE / 2;
+        const x2 = q.x + CLASS_WIDTH, y2 = q.y + HEADER / 2;
+        const dx = Math.max(40, Math.abs(x1 - x2) / 2);
+        el("path", { class: "edge", d: `M${x1},${y1} C${x1 - dx},${y1} ${x2 + dx},${y2} ${x2},${y2}` }, edges);
+      }
+    });
+  }
+}
+
+// panning and zooming by moving the viewBox around
+const view = { x: -20, y: -40, w: svg.clientWidth || 1000, h: svg.clientHeight || 800 };
+function updateView() {
+  svg.setAttribute("viewBox", `${view.x} ${view.y} ${view.w} ${view.h}`);
+}
+svg.addEventListener("wheel", e => {
+  e.preventDefault();
+  const scale = e.deltaY > 0 ? 1.1 : 1 / 1.1;
+  const r = svg.getBoundingClientRect();
+  const mx = view.x + (e.clientX - r.left) / r.width * view.w;
+  const my = view.y + (e.clientY - r.top) / r.height * view.h;
+  view.x = mx - (mx - view.x) * scale;
+  view.y = my - (my - view.y) * scale;
+  view.w *= scale;
+  view.h *= scale;
+  updateView();
+}, { passive: false });
+let drag = null;
+svg.addEventListener("mousedown", e => { drag = { x: e.clientX, y: e.clientY }; });
+window.addEventListener("mouseup", () => { drag = null; });
+window.addEventListener("mousemove", e => {
+  if (!drag) return;
+  const r = svg.getBoundingClientRect();
+  view.x -= (e.clientX - drag.x) * view.w / r.width;
+  view.y -= (e.clientY - drag.y) * view.h / r.height;
+  drag = { x: e.clientX, y: e.clientY };
+  updateView();
+});
+
+render();
+updateView();
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn html_embeds_escaped_json() {
+        let mut egraph = EGraph::<S, ()>::default();
+        egraph.add_expr(&"(f </script>)".parse().unwrap());
+        egraph.rebuild();
+
+        let html = egraph.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("/*EGRAPH_JSON*/"));
+        assert!(html.contains(r#""op":"<\/script>""#));
+        assert_eq!(html.matches("</script>").count(), 1);
+    }
+}
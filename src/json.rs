@@ -0,0 +1,137 @@
+/*!
+Language-agnostic JSON export of an [`EGraph`].
+
+See [`EGraph::to_json`] for a description of the format.
+!*/
+
+use std::fmt::{Display, Write};
+
+use crate::{Analysis, EGraph, Id, Language};
+
+/// The version of the format written by [`EGraph::to_json`].
+pub const JSON_FORMAT_VERSION: usize = 1;
+
+impl<L: Language + Display, N: Analysis<L>> EGraph<L, N> {
+    /// Exports the structure of this egraph as JSON.
+    ///
+    /// The output doesn't depend on `egg`, so external visualizers and
+    /// extraction tools can consume it without linking against this crate.
+    /// It is a single object with the following fields:
+    ///
+    /// - `"version"`: the format version, currently
+    ///   [`JSON_FORMAT_VERSION`].
+    /// - `"classes"`: a list of e-classes sorted by id. Each e-class is an
+    ///   object with an `"id"` (a number) and a list of `"nodes"`.
+    ///   Each e-node is an object with an `"op"` string (its [`Display`]
+    ///   form) and a list of `"children"` e-class ids.
+    ///
+    /// All ids are canonical, so the egraph should be
+    /// [rebuilt](EGraph::rebuild()) first.
+    /// Use [`to_json_with_data`](EGraph::to_json_with_data()) to also
+    /// export analysis data.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// egraph.add_expr(&"(f x)".parse().unwrap());
+    /// egraph.rebuild();
+    /// assert_eq!(
+    ///     egraph.to_json(),
+    ///     r#"{"version":1,"classes":[{"id":0,"nodes":[{"op":"x","children":[]}]},{"id":1,"nodes":[{"op":"f","children":[0]}]}]}"#,
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        self.write_json(None::<fn(&N::Data) -> String>)
+    }
+
+    /// Like [`to_json`](EGraph::to_json()), but each e-class object
+    /// additionally has a `"data"` string, produced by calling `data` on the
+    /// e-class's analysis data.
+    pub fn to_json_with_data<F>(&self, data: F) -> String
+    where
+        F: FnMut(&N::Data) -> String,
+    {
+        self.write_json(Some(data))
+    }
+
+    fn write_json<F>(&self, mut data: Option<F>) -> String
+    where
+        F: FnMut(&N::Data) -> String,
+    {
+        let mut classes: Vec<_> = self.classes().collect();
+        classes.sort_unstable_by_key(|c| c.id);
+
+        let mut out = String::new();
+        write!(out, r#"{{"version":{},"classes":["#, JSON_FORMAT_VERSION).unwrap();
+        for (i, class) in classes.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, r#"{{"id":{},"nodes":["#, class.id).unwrap();
+            for (j, node) in class.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                out.push_str(r#"{"op":"#);
+                write_json_str(&mut out, &node.to_string());
+                out.push_str(r#","children":["#);
+                for (k, &child) in node.children().iter().enumerate() {
+                    if k > 0 {
+                        out.push(',');
+                    }
+                    write!(out, "{}", self.find(child)).unwrap();
+                }
+                out.push_str("]}");
+            }
+            out.push(']');
+            if let Some(data) = data.as_mut() {
+                out.push_str(r#","data":"#);
+                write_json_str(&mut out, &data(&class.data));
+            }
+            out.push('}');
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn json_escapes_and_data() {
+        let mut egraph = EGraph::<S, MinDepth>::default();
+        let quote = egraph.add(S::leaf("a\"b\\c\n"));
+        egraph.add(S::new("g", vec![quote, quote]));
+        egraph.rebuild();
+
+        let json = egraph.to_json_with_data(|depth| depth.to_string());
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"version":1,"classes":["#,
+                r#"{"id":0,"nodes":[{"op":"a\"b\\c\n","children":[]}],"data":"1"},"#,
+                r#"{"id":1,"nodes":[{"op":"g","children":[0,0]}],"data":"2"}"#,
+                r#"]}"#
+            )
+        );
+    }
+}
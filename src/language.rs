@@ -33,6 +33,57 @@ pub trait Language: Debug + Clone + Eq + Ord + Hash {
     /// This should only consider the operator, not the children `Id`s.
     fn matches(&self, other: &Self) -> bool;
 
+    /// Returns true if this enode's operator is variadic and `other` has
+    /// the same operator with at least as many children.
+    ///
+    /// When this returns true, a [`Pattern`] containing this enode also
+    /// matches `other`, binding only its first `self.len()` children,
+    /// unless the pattern was made [exact](Pattern::with_exact_arity()).
+    /// Like [`matches`](Language::matches()), this should only consider the
+    /// operator and the number of children.
+    ///
+    /// The default implementation returns false, so no operator is variadic.
+    fn matches_prefix(&self, other: &Self) -> bool {
+        let _ = other;
+        false
+    }
+
+    /// Returns a copy of this enode with the given children, or `None` if
+    /// this operator can't have that many children.
+    ///
+    /// This is used to splice the children bound by a
+    /// [segment variable](Var::is_segment()) back into an enode.
+    /// The default implementation only supports the enode's current number
+    /// of children; languages with variadic operators should override it.
+    fn with_children(&self, children: &[Id]) -> Option<Self> {
+        if children.len() == self.len() {
+            let mut node = self.clone();
+            node.children_mut().copy_from_slice(children);
+            Some(node)
+        } else {
+            None
+        }
+    }
+
+    /// Formats this enode given the already formatted strings of its
+    /// children, in order.
+    ///
+    /// This is used by [`RecExpr::fmt_nodes`] to print expressions in a
+    /// language's own syntax, like infix arithmetic, rather than as
+    /// s-expressions.
+    /// The default implementation writes an s-expression using this enode's
+    /// [`Display`] implementation for the operator.
+    fn fmt_node(&self, children: &[String]) -> String
+    where
+        Self: Display,
+    {
+        if children.is_empty() {
+            self.to_string()
+        } else {
+            format!("({} {})", self, children.join(" "))
+        }
+    }
+
     /// Returns the children of this e-node.
     fn children(&self) -> &[Id];
 
@@ -345,6 +396,19 @@ impl LanguageChildren for Vec<Id> {
     fn as_mut_slice(&mut self) -> &mut [Id]  { self }
 }
 
+/// Children stored inline up to `N` and spilled to the heap beyond that.
+///
+/// Pick `N` to cover the usual number of children of your operators, so
+/// variadic e-nodes don't need an allocation each.
+#[rustfmt::skip]
+impl<const N: usize> LanguageChildren for SmallVec<[Id; N]> {
+    fn len(&self) -> usize                   { <[Id]>::len(self) }
+    fn can_be_length(_: usize) -> bool       { true }
+    fn from_vec(v: Vec<Id>) -> Self          { v.into() }
+    fn as_slice(&self) -> &[Id]              { self }
+    fn as_mut_slice(&mut self) -> &mut [Id]  { self }
+}
+
 #[rustfmt::skip]
 impl LanguageChildren for Id {
     fn len(&self) -> usize                   { 1 }
@@ -363,7 +427,9 @@ impl LanguageChildren for Id {
 /// elements that come before it in the list.
 ///
 /// If the `serde-1` feature is enabled, this implements
-/// [`serde::Serialize`](https://docs.rs/serde/latest/serde/trait.Serialize.html).
+/// [`serde::Serialize`](https://docs.rs/serde/latest/serde/trait.Serialize.html)
+/// and [`serde::Deserialize`](https://docs.rs/serde/latest/serde/trait.Deserialize.html)
+/// using the s-expression form.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RecExpr<L> {
     nodes: Vec<L>,
@@ -380,6 +446,18 @@ impl<L: Language + Display> serde::Serialize for RecExpr<L> {
     }
 }
 
+#[cfg(feature = "serde-1")]
+impl<'de, L: FromOp> serde::Deserialize<'de> for RecExpr<L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+    }
+}
+
 impl<L> Default for RecExpr<L> {
     fn default() -> Self {
         Self::from(vec![])
@@ -428,6 +506,43 @@ impl<L: Language> RecExpr<L> {
         self[new_root].build_recexpr(|id| self[id].clone())
     }
 
+    /// Prints this `RecExpr` using a custom formatter for each node.
+    ///
+    /// `fmt_node` is given each node along with the already formatted
+    /// strings of its children, in order, and should return the formatted
+    /// string for that node. This lets you print expressions infix or in the
+    /// syntax of some target language rather than as s-expressions.
+    /// To always print a language in its own syntax, override
+    /// [`Language::fmt_node`] and use [`fmt_nodes`](RecExpr::fmt_nodes())
+    /// instead.
+    ///
+    /// # Example
+    /// ```
+    /// # use egg::*;
+    /// let e: RecExpr<SymbolLang> = "(* a (+ b c))".parse().unwrap();
+    /// let infix = e.display_with(|node, children| match children {
+    ///     [a, b] => format!("({} {} {})", a, node.op, b),
+    ///     _ => node.op.to_string(),
+    /// });
+    /// assert_eq!(infix, "(a * (b + c))");
+    /// ```
+    pub fn display_with<F>(&self, mut fmt_node: F) -> String
+    where
+        F: FnMut(&L, &[String]) -> String,
+    {
+        let mut strings: Vec<String> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let children: Vec<String> = node
+                .children()
+                .iter()
+                .map(|&id| strings[usize::from(id)].clone())
+                .collect();
+            let s = fmt_node(node, &children);
+            strings.push(s);
+        }
+        strings.pop().unwrap_or_default()
+    }
+
     /// Checks if this expr is a DAG, i.e. doesn't have any back edges
     pub fn is_dag(&self) -> bool {
         for (i, n) in self.nodes.iter().enumerate() {
@@ -466,6 +581,52 @@ impl<L: Language + Display> Display for RecExpr<L> {
 }
 
 impl<L: Language + Display> RecExpr<L> {
+    /// Prints this `RecExpr` with [`Language::fmt_node`], so in the syntax
+    /// the language chooses.
+    ///
+    /// Unlike [`Display`], which always writes s-expressions that can be
+    /// parsed back, this is meant for showing results to people.
+    ///
+    /// # Example
+    /// ```
+    /// # use egg::*;
+    /// #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    /// struct Infix(SymbolLang);
+    ///
+    /// impl Language for Infix {
+    ///     fn matches(&self, other: &Self) -> bool {
+    ///         self.0.matches(&other.0)
+    ///     }
+    ///     fn children(&self) -> &[Id] {
+    ///         self.0.children()
+    ///     }
+    ///     fn children_mut(&mut self) -> &mut [Id] {
+    ///         self.0.children_mut()
+    ///     }
+    ///     fn fmt_node(&self, children: &[String]) -> String {
+    ///         match children {
+    ///             [a, b] => format!("({} {} {})", a, self.0.op, b),
+    ///             _ => self.0.op.to_string(),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl std::fmt::Display for Infix {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    ///         std::fmt::Display::fmt(&self.0, f)
+    ///     }
+    /// }
+    ///
+    /// let e: RecExpr<SymbolLang> = "(* a (+ b c))".parse().unwrap();
+    /// assert_eq!(e.fmt_nodes(), "(* a (+ b c))");
+    /// let nodes: Vec<Infix> = e.as_ref().iter().cloned().map(Infix).collect();
+    /// let infix = RecExpr::from(nodes);
+    /// assert_eq!(infix.fmt_nodes(), "(a * (b + c))");
+    /// ```
+    pub fn fmt_nodes(&self) -> String {
+        self.display_with(|node, children| node.fmt_node(children))
+    }
+
     /// Convert this RecExpr into an Sexp
     pub(crate) fn to_sexp(&self) -> Sexp {
         let last = self.nodes.len() - 1;
@@ -732,6 +893,37 @@ pub trait Analysis<L: Language>: Sized {
     /// `modify` has access to the e-graph.
     fn merge(&mut self, a: &mut Self::Data, b: Self::Data) -> DidMerge;
 
+    /// A hook to sanity check [`Analysis::merge`] in debug builds.
+    ///
+    /// When `debug_assertions` are enabled, this is called right before the
+    /// e-graph merges two pieces of analysis data, with the same arguments
+    /// that will be passed to `merge`.
+    ///
+    /// By default it does nothing.
+    /// If your `Data` implements [`Clone`] and [`PartialEq`], you can call
+    /// [`assert_lattice_merge`] here to check that `merge` is commutative and
+    /// idempotent on the data the e-graph actually encounters.
+    /// A `merge` that isn't will make results depend on the order of unions.
+    ///
+    /// ```
+    /// # use egg::*;
+    /// struct MaxNum;
+    /// impl Analysis<SymbolLang> for MaxNum {
+    ///     type Data = Option<i32>;
+    ///     fn make(_: &EGraph<SymbolLang, Self>, enode: &SymbolLang) -> Self::Data {
+    ///         enode.op.as_str().parse().ok()
+    ///     }
+    ///     fn merge(&mut self, a: &mut Self::Data, b: Self::Data) -> DidMerge {
+    ///         merge_max(a, b)
+    ///     }
+    ///     fn debug_check_merge(&mut self, a: &Self::Data, b: &Self::Data) {
+    ///         assert_lattice_merge(|a, b| self.merge(a, b), a, b)
+    ///     }
+    /// }
+    /// ```
+    #[allow(unused_variables)]
+    fn debug_check_merge(&mut self, a: &Self::Data, b: &Self::Data) {}
+
     /// A hook that allows the modification of the
     /// [`EGraph`].
     ///
@@ -741,6 +933,54 @@ pub trait Analysis<L: Language>: Sized {
     /// `Analysis::merge` when unions are performed.
     #[allow(unused_variables)]
     fn modify(egraph: &mut EGraph<L, Self>, id: Id) {}
+
+    /// Checks whether the eclass `eclass` satisfies `constraint`, the
+    /// annotation of a [`Pattern`] variable written `?x:constraint`.
+    ///
+    /// The analysis decides what constraints mean; they can look at the
+    /// eclass's data or at its enodes.
+    /// By default no constraints are defined, so this returns `false` and
+    /// constrained variables never match.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    ///
+    /// #[derive(Default)]
+    /// struct Constants;
+    /// impl Analysis<SymbolLang> for Constants {
+    ///     type Data = Option<i32>;
+    ///     fn make(_: &EGraph<SymbolLang, Self>, enode: &SymbolLang) -> Self::Data {
+    ///         enode.op.as_str().parse().ok()
+    ///     }
+    ///     fn merge(&mut self, a: &mut Self::Data, b: Self::Data) -> DidMerge {
+    ///         merge_option(a, b, |_, _| DidMerge(false, false))
+    ///     }
+    ///     fn check_constraint(egraph: &EGraph<SymbolLang, Self>, eclass: Id, constraint: Symbol) -> bool {
+    ///         match constraint.as_str() {
+    ///             "constant" => egraph[eclass].data.is_some(),
+    ///             // any other constraint names an operator
+    ///             _ => egraph[eclass].iter().any(|n| n.op == constraint),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let rules: &[Rewrite<SymbolLang, Constants>] = &[
+    ///     rw!("move-const"; "(* ?c:constant ?x)" => "(* ?x ?c)"),
+    ///     rw!("unwrap"; "(* ?x:var 1)" => "?x"),
+    /// ];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(* 1 (var y))".parse().unwrap())
+    ///     .with_expr(&"(* z 2)".parse().unwrap())
+    ///     .run(rules);
+    /// let egraph = &runner.egraph;
+    /// assert_eq!(egraph.find(runner.roots[0]), egraph.lookup_expr(&"(var y)".parse().unwrap()).unwrap());
+    /// assert_eq!(egraph.lookup_expr(&"(* 2 z)".parse().unwrap()), None);
+    /// ```
+    #[allow(unused_variables)]
+    fn check_constraint(egraph: &EGraph<L, Self>, eclass: Id, constraint: Symbol) -> bool {
+        false
+    }
 }
 
 impl<L: Language> Analysis<L> for () {
@@ -751,6 +991,102 @@ impl<L: Language> Analysis<L> for () {
     }
 }
 
+/** An [`Analysis`] tracking the depth of the shallowest term in each e-class.
+
+Leaves have depth 1, and every other e-node is one deeper than its
+deepest child. The data of an e-class is the minimum over its e-nodes,
+so it stays well-defined even when the e-graph has cycles.
+This uses the same measure as [`AstDepth`].
+
+See [`depth_le`] for a [`Condition`] using this analysis, and
+[`Runner::with_depth_limit`] to bound the depth of every rule's results.
+
+# Example
+```
+use egg::*;
+let mut egraph = EGraph::<SymbolLang, MinDepth>::default();
+let deep = egraph.add_expr(&"(f (g (h x)))".parse().unwrap());
+let shallow = egraph.add_expr(&"y".parse().unwrap());
+assert_eq!(egraph[deep].data, 4);
+egraph.union(deep, shallow);
+egraph.rebuild();
+assert_eq!(egraph[deep].data, 1);
+```
+*/
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinDepth;
+
+impl<L: Language> Analysis<L> for MinDepth {
+    type Data = usize;
+
+    fn make(egraph: &EGraph<L, Self>, enode: &L) -> Self::Data {
+        let deepest = enode.fold(0, |max: usize, id| max.max(egraph[id].data));
+        deepest.saturating_add(1)
+    }
+
+    fn merge(&mut self, to: &mut Self::Data, from: Self::Data) -> DidMerge {
+        merge_min(to, from)
+    }
+}
+
+/** An [`Analysis`] tracking the size of the smallest term in each e-class.
+
+Every e-node counts 1 plus the sizes of its children, so this is the
+size of the term written out as a tree, like [`AstSize`].
+The data of an e-class is the minimum over its e-nodes, so it stays
+well-defined even when the e-graph has cycles.
+Sizes saturate at [`usize::MAX`] rather than overflowing.
+
+See [`size_le`] for a [`Condition`] using this analysis.
+*/
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinSize;
+
+impl<L: Language> Analysis<L> for MinSize {
+    type Data = usize;
+
+    fn make(egraph: &EGraph<L, Self>, enode: &L) -> Self::Data {
+        enode.fold(1, |sum: usize, id| sum.saturating_add(egraph[id].data))
+    }
+
+    fn merge(&mut self, to: &mut Self::Data, from: Self::Data) -> DidMerge {
+        merge_min(to, from)
+    }
+}
+
+/// A [`Condition`] that holds if the e-class bound to `var` contains a
+/// term of depth at most `depth`, according to [`MinDepth`].
+///
+/// # Example
+/// ```
+/// use egg::*;
+/// let rules: &[Rewrite<SymbolLang, MinDepth>] = &[rewrite!("wrap";
+///     "?a" => "(f ?a)"
+///     if depth_le("?a", 2))];
+///
+/// let runner = Runner::default()
+///     .with_expr(&"x".parse().unwrap())
+///     .run(rules);
+/// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+/// ```
+pub fn depth_le<L: Language>(
+    var: &str,
+    depth: usize,
+) -> impl Fn(&mut EGraph<L, MinDepth>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| egraph[subst[var]].data <= depth
+}
+
+/// A [`Condition`] that holds if the e-class bound to `var` contains a
+/// term of size at most `size`, according to [`MinSize`].
+pub fn size_le<L: Language>(
+    var: &str,
+    size: usize,
+) -> impl Fn(&mut EGraph<L, MinSize>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| egraph[subst[var]].data <= size
+}
+
 /// A utility for implementing [`Analysis::merge`]
 /// when the `Data` type has a total ordering.
 /// This will take the maximum of the two values.
@@ -781,6 +1117,44 @@ pub fn merge_min<T: Ord>(to: &mut T, from: T) -> DidMerge {
     }
 }
 
+/// Asserts that a `merge` function behaves like a semilattice join on `a` and `b`.
+///
+/// This checks that `merge(a, b) == merge(b, a)` and that merging `a` with
+/// itself yields `a`.
+/// It's meant to be called from [`Analysis::debug_check_merge`].
+pub fn assert_lattice_merge<D, F>(mut merge: F, a: &D, b: &D)
+where
+    D: Clone + PartialEq + Debug,
+    F: FnMut(&mut D, D) -> DidMerge,
+{
+    let mut ab = a.clone();
+    merge(&mut ab, b.clone());
+    let mut ba = b.clone();
+    merge(&mut ba, a.clone());
+    assert!(
+        ab == ba,
+        "Analysis::merge is not commutative:\n  merge({:?}, {:?}) = {:?}\n  merge({:?}, {:?}) = {:?}",
+        a,
+        b,
+        ab,
+        b,
+        a,
+        ba
+    );
+
+    for x in [a, b] {
+        let mut xx = x.clone();
+        merge(&mut xx, x.clone());
+        assert!(
+            &xx == x,
+            "Analysis::merge is not idempotent: merge({:?}, {:?}) = {:?}",
+            x,
+            x,
+            xx
+        );
+    }
+}
+
 /// A utility for implementing [`Analysis::merge`]
 /// when the `Data` type is an [`Option`].
 ///
@@ -837,6 +1211,10 @@ impl Language for SymbolLang {
     fn children_mut(&mut self) -> &mut [Id] {
         &mut self.children
     }
+
+    fn with_children(&self, children: &[Id]) -> Option<Self> {
+        Some(Self::new(self.op, children.to_vec()))
+    }
 }
 
 impl Display for SymbolLang {
@@ -855,3 +1233,22 @@ impl FromOp for SymbolLang {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn recexpr_round_trip() {
+        let inputs = ["x", "(>> x (log2 2))", "(* (+ 2 2) (+ x y))"];
+        for input in inputs.iter() {
+            let expr: RecExpr<S> = input.parse().unwrap();
+            assert_eq!(&expr.to_string(), input);
+            assert_eq!(expr.to_string().parse::<RecExpr<S>>().unwrap(), expr);
+        }
+
+        assert!("()".parse::<RecExpr<S>>().is_err());
+        assert!("((>> x) 2)".parse::<RecExpr<S>>().is_err());
+    }
+}
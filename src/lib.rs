@@ -35,79 +35,162 @@ mod macros;
 #[doc(hidden)]
 pub mod test;
 
+pub mod debruijn;
 pub mod tutorials;
 
+#[cfg(feature = "synthesis")]
+pub mod synthesis;
+#[cfg(feature = "proptest")]
+pub mod test_util;
+
+#[cfg(feature = "checkpoint")]
+mod checkpoint;
+#[cfg(feature = "corpus")]
+mod corpus;
+#[cfg(feature = "term-count")]
+mod count;
 mod dot;
 mod eclass;
 mod egraph;
 mod explain;
 mod extract;
+mod html;
+mod json;
 mod language;
 #[cfg(feature = "lp")]
 mod lp_extract;
 mod machine;
 mod multipattern;
 mod pattern;
+mod relational;
 mod rewrite;
 mod run;
+#[cfg(feature = "rand")]
+mod sample;
+mod sort;
 mod subst;
 mod unionfind;
 mod util;
+mod validate;
+mod view;
 
 /// A key to identify [`EClass`]es within an
 /// [`EGraph`].
-#[derive(Clone, Copy, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
-#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
-#[cfg_attr(feature = "serde-1", serde(transparent))]
-pub struct Id(u32);
+///
+/// An `Id` is an index, but it's stored off by one in a [`NonZeroU32`], so
+/// `Option<Id>` is the same size as `Id`.
+/// Convert to and from `usize` with [`From`].
+///
+/// ```
+/// use egg::Id;
+/// assert_eq!(std::mem::size_of::<Option<Id>>(), std::mem::size_of::<Id>());
+/// assert_eq!(usize::from(Id::from(3)), 3);
+/// ```
+///
+/// [`NonZeroU32`]: std::num::NonZeroU32
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct Id(std::num::NonZeroU32);
+
+impl Default for Id {
+    fn default() -> Self {
+        Id::from(0)
+    }
+}
 
 impl From<usize> for Id {
     fn from(n: usize) -> Id {
-        Id(n as u32)
+        let n = std::convert::TryFrom::try_from(n)
+            .ok()
+            .and_then(|n: u32| n.checked_add(1));
+        Id(n.and_then(std::num::NonZeroU32::new).expect("Id overflow"))
     }
 }
 
 impl From<Id> for usize {
     fn from(id: Id) -> usize {
-        id.0 as usize
+        id.0.get() as usize - 1
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0.get() - 1)
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let n = <u32 as serde::Deserialize>::deserialize(deserializer)?;
+        n.checked_add(1)
+            .and_then(std::num::NonZeroU32::new)
+            .map(Id)
+            .ok_or_else(|| serde::de::Error::custom(format!("Id {} is out of range", n)))
     }
 }
 
 impl std::fmt::Debug for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", usize::from(*self))
     }
 }
 
 impl std::fmt::Display for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", usize::from(*self))
     }
 }
 
 pub(crate) use {explain::Explain, unionfind::UnionFind};
 
 pub use {
+    debruijn::{BetaReduce, DeBruijn, Shift},
     dot::Dot,
     eclass::EClass,
-    egraph::EGraph,
+    egraph::{EGraph, EGraphDump, EGraphObserver, EGraphReport, UndoMark},
     explain::{
         Explanation, FlatExplanation, FlatTerm, Justification, TreeExplanation, TreeTerm,
         UnionEqualities,
     },
     extract::*,
+    json::JSON_FORMAT_VERSION,
     language::*,
     multipattern::*,
-    pattern::{ENodeOrVar, Pattern, PatternAst, SearchMatches},
-    rewrite::{Applier, Condition, ConditionEqual, ConditionalApplier, Rewrite, Searcher},
+    pattern::{ENodeOrVar, MatchBackend, Pattern, PatternAst, SearchMatches},
+    rewrite::{
+        load_rules, parse_rules, Applier, Condition, ConditionEqual, ConditionalApplier,
+        LoadRulesError, Rewrite, RewriteBuilder, RewriteError, Searcher,
+    },
     run::*,
+    sort::{has_sort, well_sorted, SortAnalysis, Sorted},
     subst::{Subst, Var},
     util::*,
+    validate::{validate_rules, RuleWarning},
+    view::EGraphView,
+};
+
+/// Re-exported so [`define_language!`] variants can use
+/// `SmallVec<[Id; N]>` children without depending on `smallvec` directly.
+pub use smallvec::SmallVec;
+
+#[cfg(feature = "checkpoint")]
+pub use checkpoint::CheckpointError;
+
+#[cfg(feature = "corpus")]
+pub use corpus::{
+    Benchmark, BenchmarkResult, Comparison, Corpus, CorpusError, CorpusLimits, CorpusReport,
+    CORPUS_MANIFEST,
 };
 
 #[cfg(feature = "lp")]
 pub use lp_extract::*;
 
+/// Re-exported for the counts returned by [`EGraph::count_terms`].
+#[cfg(feature = "term-count")]
+pub use num_bigint::BigUint;
+
 #[cfg(test)]
 fn init_logger() {
     let _ = env_logger::builder().is_test(true).try_init();
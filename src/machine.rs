@@ -8,6 +8,8 @@ struct Machine {
     reg: Vec<Id>,
     // a buffer to re-use for lookups
     lookup: Vec<Id>,
+    // the children bound by segment variables, by their register
+    segments: HashMap<Reg, Vec<Id>>,
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -21,10 +23,28 @@ pub struct Program<L> {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Instruction<L> {
-    Bind { node: L, i: Reg, out: Reg },
-    Compare { i: Reg, j: Reg },
-    Lookup { term: Vec<ENodeOrReg<L>>, i: Reg },
-    Scan { out: Reg },
+    Bind {
+        node: L,
+        i: Reg,
+        out: Reg,
+        prefix: bool,
+        segment: bool,
+    },
+    Compare {
+        i: Reg,
+        j: Reg,
+    },
+    Lookup {
+        term: Vec<ENodeOrReg<L>>,
+        i: Reg,
+    },
+    Scan {
+        out: Reg,
+    },
+    Check {
+        i: Reg,
+        constraint: Symbol,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -37,18 +57,16 @@ enum ENodeOrReg<L> {
 fn for_each_matching_node<L, D>(
     eclass: &EClass<L, D>,
     node: &L,
+    prefix: bool,
     mut f: impl FnMut(&L) -> Result,
 ) -> Result
 where
     L: Language,
 {
+    let is_match = |n: &&L| node.matches(n) || (prefix && node.matches_prefix(n));
     #[allow(enum_intrinsics_non_enums)]
     if eclass.nodes.len() < 50 {
-        eclass
-            .nodes
-            .iter()
-            .filter(|n| node.matches(n))
-            .try_for_each(f)
+        eclass.nodes.iter().filter(is_match).try_for_each(f)
     } else {
         debug_assert!(node.all(|id| id == Id::from(0)));
         debug_assert!(eclass.nodes.windows(2).all(|w| w[0] < w[1]));
@@ -64,19 +82,15 @@ where
         let mut matching = eclass.nodes[start..]
             .iter()
             .take_while(|&n| std::mem::discriminant(n) == discrim)
-            .filter(|n| node.matches(n));
+            .filter(is_match);
         debug_assert_eq!(
             matching.clone().count(),
-            eclass.nodes.iter().filter(|n| node.matches(n)).count(),
+            eclass.nodes.iter().filter(is_match).count(),
             "matching node {:?}\nstart={}\n{:?} != {:?}\nnodes: {:?}",
             node,
             start,
             matching.clone().collect::<HashSet<_>>(),
-            eclass
-                .nodes
-                .iter()
-                .filter(|n| node.matches(n))
-                .collect::<HashSet<_>>(),
+            eclass.nodes.iter().filter(is_match).collect::<HashSet<_>>(),
             eclass.nodes
         );
         matching.try_for_each(&mut f)
@@ -103,11 +117,25 @@ impl Machine {
         let mut instructions = instructions.iter();
         while let Some(instruction) = instructions.next() {
             match instruction {
-                Instruction::Bind { i, out, node } => {
+                Instruction::Bind {
+                    i,
+                    out,
+                    node,
+                    prefix,
+                    segment,
+                } => {
                     let remaining_instructions = instructions.as_slice();
-                    return for_each_matching_node(&egraph[self.reg(*i)], node, |matched| {
+                    let eclass = &egraph[self.reg(*i)];
+                    return for_each_matching_node(eclass, node, *prefix, |matched| {
                         self.reg.truncate(out.0 as usize);
                         matched.for_each(|id| self.reg.push(id));
+                        // a variadic match only binds the children in the pattern
+                        self.reg.truncate(out.0 as usize + node.len());
+                        if *segment {
+                            let last = node.len() - 1;
+                            let rest = matched.children()[last..].to_vec();
+                            self.segments.insert(Reg(out.0 + last as u32), rest);
+                        }
                         self.run(egraph, remaining_instructions, subst, yield_fn)
                     });
                 }
@@ -120,10 +148,21 @@ impl Machine {
                     }
                     return Ok(());
                 }
+                Instruction::Check { i, constraint } => {
+                    if !N::check_constraint(egraph, egraph.find(self.reg(*i)), *constraint) {
+                        return Ok(());
+                    }
+                }
                 Instruction::Compare { i, j } => {
                     if egraph.find(self.reg(*i)) != egraph.find(self.reg(*j)) {
                         return Ok(());
                     }
+                    if let (Some(a), Some(b)) = (self.segments.get(i), self.segments.get(j)) {
+                        let same = |(x, y): (&Id, &Id)| egraph.find(*x) == egraph.find(*y);
+                        if a.len() != b.len() || !a.iter().zip(b).all(same) {
+                            return Ok(());
+                        }
+                    }
                 }
                 Instruction::Lookup { term, i } => {
                     self.lookup.clear();
@@ -161,10 +200,14 @@ struct Compiler<L> {
     todo_nodes: HashMap<(Id, Reg), L>,
     instructions: Vec<Instruction<L>>,
     next_reg: Reg,
+    exact_arity: bool,
+    constraints: Vec<(Var, Symbol)>,
+    /// how many e-classes contain each operator, see `Pattern::optimize_for`
+    op_counts: HashMap<L, usize>,
 }
 
 impl<L: Language> Compiler<L> {
-    fn new() -> Self {
+    fn new(exact_arity: bool, constraints: Vec<(Var, Symbol)>) -> Self {
         Self {
             free_vars: Default::default(),
             subtree_size: Default::default(),
@@ -172,9 +215,17 @@ impl<L: Language> Compiler<L> {
             todo_nodes: Default::default(),
             instructions: Default::default(),
             next_reg: Reg(0),
+            exact_arity,
+            constraints,
+            op_counts: Default::default(),
         }
     }
 
+    /// whether a pattern e-node may match e-nodes with more children
+    fn is_prefix(&self, node: &L) -> bool {
+        !self.exact_arity && node.matches_prefix(node)
+    }
+
     fn add_todo(&mut self, pattern: &PatternAst<L>, id: Id, reg: Reg) {
         match &pattern[id] {
             ENodeOrVar::Var(v) => {
@@ -182,6 +233,14 @@ impl<L: Language> Compiler<L> {
                     self.instructions.push(Instruction::Compare { i: reg, j })
                 } else {
                     self.v2r.insert(*v, reg);
+                    for (w, constraint) in &self.constraints {
+                        if w == v {
+                            self.instructions.push(Instruction::Check {
+                                i: reg,
+                                constraint: *constraint,
+                            });
+                        }
+                    }
                 }
             }
             ENodeOrVar::ENode(pat) => {
@@ -190,6 +249,10 @@ impl<L: Language> Compiler<L> {
         }
     }
 
+    fn is_segment(pattern: &PatternAst<L>, id: Id) -> bool {
+        matches!(&pattern[id], ENodeOrVar::Var(v) if v.is_segment())
+    }
+
     fn load_pattern(&mut self, pattern: &PatternAst<L>) {
         let len = pattern.as_ref().len();
         self.free_vars = Vec::with_capacity(len);
@@ -218,9 +281,12 @@ impl<L: Language> Compiler<L> {
     fn next(&mut self) -> Option<((Id, Reg), L)> {
         // we take the max todo according to this key
         // - prefer grounded
+        // - prefer terms with an already bound variable, which will be
+        //   compared as soon as it's reached
+        // - prefer rarer operators, if we know how rare they are
         // - prefer more free variables
         // - prefer smaller term
-        let key = |(id, _): &&(Id, Reg)| {
+        let key = |((id, _), node): (&(Id, Reg), &L)| {
             let i = usize::from(*id);
             let n_bound = self.free_vars[i]
                 .iter()
@@ -228,13 +294,21 @@ impl<L: Language> Compiler<L> {
                 .count();
             let n_free = self.free_vars[i].len() - n_bound;
             let size = self.subtree_size[i] as isize;
-            (n_free == 0, n_free, -size)
+            let op = node.clone().map_children(|_| Id::from(0));
+            let count = self.op_counts.get(&op).copied().unwrap_or(0);
+            (
+                n_free == 0,
+                n_bound > 0,
+                std::cmp::Reverse(count),
+                n_free,
+                -size,
+            )
         };
 
         self.todo_nodes
-            .keys()
-            .max_by_key(key)
-            .copied()
+            .iter()
+            .max_by_key(|&(k, node)| key((k, node)))
+            .map(|(k, _)| *k)
             .map(|k| (k, self.todo_nodes.remove(&k).unwrap()))
     }
 
@@ -246,6 +320,18 @@ impl<L: Language> Compiler<L> {
             .all(|v| self.v2r.contains_key(v))
     }
 
+    /// check to see if the term rooted at this e-node contains an e-node that
+    /// may match a prefix or a segment variable, in which case it can't be
+    /// looked up directly
+    fn has_prefix(&self, pattern: &PatternAst<L>, id: Id) -> bool {
+        match &pattern[id] {
+            ENodeOrVar::ENode(n) => {
+                self.is_prefix(n) || n.any(|child| self.has_prefix(pattern, child))
+            }
+            ENodeOrVar::Var(v) => v.is_segment(),
+        }
+    }
+
     fn compile(&mut self, patternbinder: Option<Var>, pattern: &PatternAst<L>) {
         self.load_pattern(pattern);
         let last_i = pattern.as_ref().len() - 1;
@@ -254,6 +340,10 @@ impl<L: Language> Compiler<L> {
 
         // Check if patternbinder already bound in v2r
         // Behavior common to creating a new pattern
+        assert!(
+            !Self::is_segment(pattern, Id::from(last_i)),
+            "A segment variable can't be the root of a pattern"
+        );
         let add_new_pattern = |comp: &mut Compiler<L>| {
             if !comp.instructions.is_empty() {
                 // After first pattern needs scan
@@ -280,7 +370,7 @@ impl<L: Language> Compiler<L> {
         }
 
         while let Some(((id, reg), node)) = self.next() {
-            if self.is_ground_now(id) && !node.is_leaf() {
+            if self.is_ground_now(id) && !node.is_leaf() && !self.has_prefix(pattern, id) {
                 let extracted = pattern.extract(id);
                 self.instructions.push(Instruction::Lookup {
                     i: reg,
@@ -299,10 +389,22 @@ impl<L: Language> Compiler<L> {
 
                 // zero out the children so Bind can use it to sort
                 let op = node.clone().map_children(|_| Id::from(0));
+                let prefix = self.is_prefix(&op);
+                let children = node.children();
+                if let Some(pos) = children.iter().position(|&c| Self::is_segment(pattern, c)) {
+                    assert_eq!(
+                        pos,
+                        children.len() - 1,
+                        "A segment variable must be the last child of an e-node"
+                    );
+                }
+                let segment = node.any(|c| Self::is_segment(pattern, c));
                 self.instructions.push(Instruction::Bind {
                     i: reg,
                     node: op,
                     out,
+                    prefix,
+                    segment,
                 });
 
                 for (i, &child) in node.children().iter().enumerate() {
@@ -326,16 +428,35 @@ impl<L: Language> Compiler<L> {
 }
 
 impl<L: Language> Program<L> {
-    pub(crate) fn compile_from_pat(pattern: &PatternAst<L>) -> Self {
-        let mut compiler = Compiler::new();
+    pub(crate) fn compile_from_pat(
+        pattern: &PatternAst<L>,
+        constraints: &[(Var, Symbol)],
+        exact_arity: bool,
+    ) -> Self {
+        Self::compile_from_pat_with_counts(pattern, constraints, exact_arity, Default::default())
+    }
+
+    /// Like `compile_from_pat`, but matches the e-nodes whose operator is
+    /// in the fewest e-classes according to `op_counts` first.
+    pub(crate) fn compile_from_pat_with_counts(
+        pattern: &PatternAst<L>,
+        constraints: &[(Var, Symbol)],
+        exact_arity: bool,
+        op_counts: HashMap<L, usize>,
+    ) -> Self {
+        let mut compiler = Compiler::new(exact_arity, constraints.to_vec());
+        compiler.op_counts = op_counts;
         compiler.compile(None, pattern);
         let program = compiler.extract();
         log::debug!("Compiled {:?} to {:?}", pattern.as_ref(), program);
         program
     }
 
-    pub(crate) fn compile_from_multi_pat(patterns: &[(Var, PatternAst<L>)]) -> Self {
-        let mut compiler = Compiler::new();
+    pub(crate) fn compile_from_multi_pat(
+        patterns: &[(Var, PatternAst<L>)],
+        constraints: &[(Var, Symbol)],
+    ) -> Self {
+        let mut compiler = Compiler::new(false, constraints.to_vec());
         for (var, pattern) in patterns {
             compiler.compile(Some(*var), pattern);
         }
@@ -368,13 +489,26 @@ impl<L: Language> Program<L> {
                 &self.instructions,
                 &self.subst,
                 &mut |machine, subst| {
+                    let mut segments = vec![];
                     let subst_vec = subst
                         .vec
                         .iter()
                         // HACK we are reusing Ids here, this is bad
-                        .map(|(v, reg_id)| (*v, machine.reg(Reg(usize::from(*reg_id) as u32))))
+                        .filter_map(|(v, reg_id)| {
+                            let reg = Reg(usize::from(*reg_id) as u32);
+                            if v.is_segment() {
+                                let ids = machine.segments[&reg].iter();
+                                segments.push((*v, ids.map(|&id| egraph.find(id)).collect()));
+                                None
+                            } else {
+                                Some((*v, egraph.find(machine.reg(reg))))
+                            }
+                        })
                         .collect();
-                    matches.push(Subst { vec: subst_vec });
+                    matches.push(Subst {
+                        vec: subst_vec,
+                        segments,
+                    });
                     limit -= 1;
                     if limit != 0 {
                         Ok(())
@@ -385,6 +519,13 @@ impl<L: Language> Program<L> {
             )
             .unwrap_or_default();
 
+        // different e-nodes can lead to the same substitution,
+        // which would only cause redundant applications
+        if matches.len() > 1 {
+            let mut seen = HashSet::default();
+            matches.retain(|subst| seen.insert(subst.clone()));
+        }
+
         log::trace!("Ran program, found {:?}", matches);
         matches
     }
@@ -39,6 +39,10 @@ define_language! {
         // this will only match if the lengths are the same
         "list" = List(Box<[Id]>),
 
+        // or in a SmallVec, which keeps up to the given number of children
+        // inline and only allocates for more
+        "call" = Call(SmallVec<[Id; 4]>),
+
         // string variants with a single child `Id`
         // note that this is distinct from `Sub`, even though it has the same
         // string, because it has a different number of children
@@ -51,8 +51,24 @@ impl<L: Language> MultiPattern<L> {
     ///
     /// assert_eq!(multipattern.n_matches(&egraph), 2);
     /// ```
+    ///
+    /// Variables in the patterns can be constrained like `?x:constraint`,
+    /// as in [`Pattern::new`].
     pub fn new(asts: Vec<(Var, PatternAst<L>)>) -> Self {
-        let program = machine::Program::compile_from_multi_pat(&asts);
+        let mut constraints = vec![];
+        let asts: Vec<(Var, PatternAst<L>)> = asts
+            .into_iter()
+            .map(|(var, ast)| {
+                let (ast, cs) = crate::pattern::strip_constraints(&ast);
+                for c in cs {
+                    if !constraints.contains(&c) {
+                        constraints.push(c);
+                    }
+                }
+                (var, ast)
+            })
+            .collect();
+        let program = machine::Program::compile_from_multi_pat(&asts, &constraints);
         Self { asts, program }
     }
 }
@@ -241,6 +257,37 @@ mod tests {
 
         assert_eq!(n_matches("?x = (f a a), ?x = (f a c)"), 0);
         assert_eq!(n_matches("?x = (f a b), ?x = (f a c)"), 1);
+
+        // `()` defines no constraints, so constrained variables never match
+        assert_eq!(n_matches("?x = (f ?a:leaf ?b)"), 0);
+    }
+
+    #[derive(Default)]
+    struct Ops;
+
+    impl Analysis<S> for Ops {
+        type Data = ();
+        fn make(_: &crate::EGraph<S, Self>, _: &S) -> Self::Data {}
+        fn merge(&mut self, _: &mut Self::Data, _: Self::Data) -> DidMerge {
+            DidMerge(false, false)
+        }
+        fn check_constraint(egraph: &crate::EGraph<S, Self>, eclass: Id, op: Symbol) -> bool {
+            egraph[eclass].iter().any(|n| n.op == op)
+        }
+    }
+
+    #[test]
+    fn constrained_vars() {
+        let mut egraph = crate::EGraph::<S, Ops>::default();
+        egraph.add_expr(&"(f a b)".parse().unwrap());
+        egraph.add_expr(&"(g b a)".parse().unwrap());
+        egraph.add_expr(&"(g a a)".parse().unwrap());
+        egraph.rebuild();
+
+        let mp: MultiPattern<S> = "?x = (f ?a:a ?b), ?y = (g ?b:b ?a)".parse().unwrap();
+        assert_eq!(mp.n_matches(&egraph), 1);
+        let mp: MultiPattern<S> = "?x = (f ?a ?b:a), ?y = (g ?b ?a)".parse().unwrap();
+        assert_eq!(mp.n_matches(&egraph), 0);
     }
 
     #[test]
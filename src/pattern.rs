@@ -33,6 +33,8 @@ use crate::*;
 /// Importantly, [`Pattern`] implements [`FromStr`] if the
 /// [`Language`] does.
 /// This is probably how you'll create most [`Pattern`]s.
+/// If the `serde-1` feature is enabled, [`Pattern`]s can also be
+/// serialized and deserialized in the same s-expression form.
 ///
 /// ```
 /// use egg::*;
@@ -66,6 +68,62 @@ pub struct Pattern<L> {
     /// The actual pattern as a [`RecExpr`]
     pub ast: PatternAst<L>,
     program: machine::Program<L>,
+    /// The pattern as a query for the relational backend, if it can be one.
+    query: Option<relational::Query<L>>,
+    /// The backend chosen for this pattern, if not the egraph's.
+    backend: Option<MatchBackend>,
+    /// Whether this pattern was made [exact](Pattern::with_exact_arity()).
+    exact_arity: bool,
+    /// The pattern as given, if it had constrained variables.
+    constrained_ast: Option<PatternAst<L>>,
+}
+
+/// How a [`Pattern`] searches an egraph for matches.
+///
+/// Both backends find the same matches, they differ in how fast.
+/// The egraph's [`match_backend`](EGraph::match_backend()) is used unless
+/// a pattern chooses its own with [`Pattern::with_backend`].
+///
+/// # Example
+/// ```
+/// use egg::*;
+/// let mut egraph = EGraph::<SymbolLang, ()>::default()
+///     .with_match_backend(MatchBackend::Relational);
+/// egraph.add_expr(&"(f (g a b) (g b a) a)".parse().unwrap());
+/// egraph.add_expr(&"(f (g a b) (g a b) b)".parse().unwrap());
+/// egraph.rebuild();
+///
+/// let pat: Pattern<SymbolLang> = "(f (g ?x ?y) (g ?y ?x) ?x)".parse().unwrap();
+/// let top_down = pat.clone().with_backend(MatchBackend::TopDown);
+/// assert_eq!(pat.n_matches(&egraph), 1);
+/// assert_eq!(top_down.n_matches(&egraph), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchBackend {
+    /// Walks down the pattern from each candidate root e-class, one
+    /// e-node at a time.
+    /// This is the default, and the best for small patterns.
+    TopDown,
+    /// Compiles the pattern to a conjunctive query over the relations of
+    /// the e-nodes of each operator, and answers it with a worst-case
+    /// optimal join, binding the variables shared by the most e-nodes
+    /// first.
+    /// This can be much faster for patterns where many variables are
+    /// shared, since the equalities between them prune the search early
+    /// rather than after enumerating every combination.
+    ///
+    /// Searching a single e-class or [lazily](Searcher::search_iter()),
+    /// patterns consisting of just a variable,
+    /// and patterns with segment variables or e-nodes that may match
+    /// [longer](Language::matches_prefix()) e-nodes always use
+    /// [`TopDown`](MatchBackend::TopDown).
+    Relational,
+}
+
+impl Default for MatchBackend {
+    fn default() -> Self {
+        MatchBackend::TopDown
+    }
 }
 
 /// A [`RecExpr`] that represents a
@@ -102,10 +160,132 @@ impl<L: Language> PatternAst<L> {
 
 impl<L: Language> Pattern<L> {
     /// Creates a new pattern from the given pattern ast.
+    ///
+    /// A variable written `?x:constraint` only matches e-classes for which
+    /// [`Analysis::check_constraint`] accepts `constraint`.
+    /// The constraint is checked as soon as the variable is bound during
+    /// search, so it prunes matches much earlier than a [`Condition`].
+    /// Everywhere else, including the right-hand side of a rewrite, the
+    /// variable is just `?x`.
     pub fn new(ast: PatternAst<L>) -> Self {
         let ast = ast.compact();
-        let program = machine::Program::compile_from_pat(&ast);
-        Pattern { ast, program }
+        let (stripped, constraints) = strip_constraints(&ast);
+        if constraints.is_empty() {
+            let program = machine::Program::compile_from_pat(&ast, &[], false);
+            let query = relational::Query::compile(&ast, &[], false);
+            Pattern {
+                ast,
+                program,
+                query,
+                backend: None,
+                exact_arity: false,
+                constrained_ast: None,
+            }
+        } else {
+            let program = machine::Program::compile_from_pat(&stripped, &constraints, false);
+            let query = relational::Query::compile(&stripped, &constraints, false);
+            Pattern {
+                ast: stripped,
+                program,
+                query,
+                backend: None,
+                exact_arity: false,
+                constrained_ast: Some(ast),
+            }
+        }
+    }
+
+    /// Makes this pattern only match e-nodes with exactly as many children
+    /// as the pattern, even for operators that are
+    /// [variadic](Language::matches_prefix()).
+    pub fn with_exact_arity(mut self) -> Self {
+        self.exact_arity = true;
+        let constraints = self.constraints();
+        self.program = machine::Program::compile_from_pat(&self.ast, &constraints, true);
+        self.query = relational::Query::compile(&self.ast, &constraints, true);
+        self
+    }
+
+    /// Recompiles this pattern to match the e-nodes with the rarest
+    /// operators in `egraph` first.
+    ///
+    /// By default, a pattern is compiled without knowing anything about the
+    /// egraph it will search, so it can only guess which of the children
+    /// of an e-node are the most selective: it matches ground terms first,
+    /// then terms mentioning a variable that's already bound.
+    /// This additionally counts how many e-classes contain each operator of
+    /// the pattern, so that among the remaining children it tries the rarest
+    /// first, and most partial matches fail before having bound much.
+    /// This doesn't change the matches found, only how fast.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// for i in 0..20 {
+    ///     egraph.add_expr(&format!("(f (g {}) (h {}))", i, i % 3).parse().unwrap());
+    /// }
+    /// egraph.add_expr(&"(f (g a) (k a))".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let pat: Pattern<SymbolLang> = "(f (g ?x) (k ?y))".parse().unwrap();
+    /// let optimized = pat.clone().optimize_for(&egraph);
+    /// assert_eq!(pat.n_matches(&egraph), 1);
+    /// assert_eq!(optimized.n_matches(&egraph), 1);
+    /// ```
+    pub fn optimize_for<A: Analysis<L>>(mut self, egraph: &EGraph<L, A>) -> Self {
+        let mut op_counts = HashMap::default();
+        for node in self.ast.as_ref() {
+            if let ENodeOrVar::ENode(n) = node {
+                let op = crate::egraph::op_key(n);
+                let count = egraph.classes_by_op.get(&op).map_or(0, |ids| ids.len());
+                op_counts.insert(op, count);
+            }
+        }
+        let constraints = self.constraints();
+        self.program = machine::Program::compile_from_pat_with_counts(
+            &self.ast,
+            &constraints,
+            self.exact_arity,
+            op_counts,
+        );
+        self
+    }
+
+    fn constraints(&self) -> Vec<(Var, Symbol)> {
+        match &self.constrained_ast {
+            Some(ast) => strip_constraints(ast).1,
+            None => vec![],
+        }
+    }
+
+    /// Whether the root e-node `e` may match e-nodes with more children,
+    /// which `classes_by_op` files under a different key.
+    fn matches_longer(&self, e: &L) -> bool {
+        let has_segment = e.any(|c| matches!(&self.ast[c], ENodeOrVar::Var(v) if v.is_segment()));
+        !self.exact_arity && (e.matches_prefix(e) || has_segment)
+    }
+
+    /// The e-classes with an e-node that `e` matches or is a prefix of,
+    /// whatever its number of children, sorted.
+    fn classes_matching_prefix<A: Analysis<L>>(&self, egraph: &EGraph<L, A>, e: &L) -> Vec<Id> {
+        let op = crate::egraph::op_key(e);
+        let mut ids: Vec<Id> = egraph
+            .classes_by_op
+            .iter()
+            .filter(|(key, _)| op.matches(key) || op.matches_prefix(key))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Makes this pattern search with the given backend, rather than the
+    /// egraph's [`match_backend`](EGraph::match_backend()).
+    pub fn with_backend(mut self, backend: MatchBackend) -> Self {
+        self.backend = Some(backend);
+        self
     }
 
     /// Returns a list of the [`Var`]s in this pattern.
@@ -120,12 +300,150 @@ impl<L: Language> Pattern<L> {
         }
         vars
     }
+
+    /// Searches for matches rooted in the given e-classes only, rather than
+    /// in the whole egraph.
+    ///
+    /// This restricts matching to a region of interest, say the body of
+    /// one function in an egraph holding a whole program.
+    /// Combine it with [`EGraph::descendants`] to search everything within
+    /// some depth below a root.
+    ///
+    /// The egraph must be [clean](EGraph::clean).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let f = egraph.add_expr(&"(fun f (+ x (+ y 0)))".parse().unwrap());
+    /// let g = egraph.add_expr(&"(fun g (+ z 0))".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let pat: Pattern<SymbolLang> = "(+ ?a 0)".parse().unwrap();
+    /// assert_eq!(pat.search(&egraph).len(), 2);
+    /// let in_f = pat.search_in(&egraph, &egraph.descendants(&[f], 3));
+    /// assert_eq!(in_f.len(), 1);
+    /// // (+ y 0) is 2 e-nodes below f
+    /// assert!(pat.search_in(&egraph, &egraph.descendants(&[f], 1)).is_empty());
+    /// assert_eq!(pat.search_in(&egraph, &egraph.descendants(&[g], 1)).len(), 1);
+    /// ```
+    pub fn search_in<'a, A: Analysis<L>>(
+        &'a self,
+        egraph: &EGraph<L, A>,
+        classes: &[Id],
+    ) -> Vec<SearchMatches<'a, L>> {
+        assert!(egraph.clean, "Can only search a clean egraph");
+        let mut ids: Vec<Id> = classes.iter().map(|&id| egraph.find(id)).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        rewrite::search_eclasses_with_limit(self, egraph, ids, usize::MAX)
+    }
+
+    /// Searches for matches rooted at most a few levels above the given
+    /// e-classes, by walking their [parents](EClass::parents()).
+    ///
+    /// After adding a term or merging some e-classes, every new match of
+    /// this pattern is rooted above one of the e-classes that changed,
+    /// no higher than the pattern is deep (one level more if a variable
+    /// appears twice, since merging its e-classes can enable a match).
+    /// So rather than searching the whole egraph again, only the
+    /// [ancestors](EGraph::ancestors()) of `ids` that are close enough
+    /// are searched.
+    /// This may also return matches that already existed before the change.
+    ///
+    /// The egraph must be [clean](EGraph::clean).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// egraph.add_expr(&"(f (+ x y))".parse().unwrap());
+    /// let z = egraph.add_expr(&"z".parse().unwrap());
+    /// let y = egraph.add_expr(&"y".parse().unwrap());
+    /// egraph.union(y, z);
+    /// egraph.rebuild();
+    /// let merged = egraph.find(y);
+    ///
+    /// let pat: Pattern<SymbolLang> = "(f (+ ?a z))".parse().unwrap();
+    /// let above = pat.search_above(&egraph, &[merged]);
+    /// let everywhere = pat.search(&egraph);
+    /// assert_eq!(above.len(), 1);
+    /// assert_eq!(above[0].eclass, everywhere[0].eclass);
+    /// ```
+    pub fn search_above<'a, A: Analysis<L>>(
+        &'a self,
+        egraph: &EGraph<L, A>,
+        ids: &[Id],
+    ) -> Vec<SearchMatches<'a, L>> {
+        assert!(
+            egraph.clean,
+            "Can only search above e-classes of a clean egraph"
+        );
+        let ids = egraph.ancestors(ids, pattern_reach(&self.ast));
+        rewrite::search_eclasses_with_limit(self, egraph, ids, usize::MAX)
+    }
+}
+
+/// How many levels above a changed e-class a match of `ast` could be rooted.
+pub(crate) fn pattern_reach<L: Language>(ast: &PatternAst<L>) -> usize {
+    // a variable that appears more than once can start matching when its
+    // e-classes merge, so it counts as one more level
+    let mut var_counts = HashMap::<Var, usize>::default();
+    for node in ast.as_ref() {
+        if let ENodeOrVar::Var(v) = node {
+            *var_counts.entry(*v).or_default() += 1;
+        }
+    }
+    let mut heights: Vec<usize> = Vec::with_capacity(ast.as_ref().len());
+    for node in ast.as_ref() {
+        let height = match node {
+            ENodeOrVar::Var(v) => (var_counts[v] > 1) as usize,
+            ENodeOrVar::ENode(n) => {
+                1 + n
+                    .children()
+                    .iter()
+                    .map(|&c| heights[usize::from(c)])
+                    .max()
+                    .unwrap_or(0)
+            }
+        };
+        heights.push(height);
+    }
+    heights.last().copied().unwrap_or(0).saturating_sub(1)
+}
+
+/// Replaces the constrained variables like `?x:constraint` in `ast` with
+/// plain ones, returning the constraints.
+pub(crate) fn strip_constraints<L: Language>(
+    ast: &PatternAst<L>,
+) -> (PatternAst<L>, Vec<(Var, Symbol)>) {
+    let mut constraints = vec![];
+    let nodes: Vec<ENodeOrVar<L>> = ast
+        .as_ref()
+        .iter()
+        .map(|node| match node {
+            ENodeOrVar::Var(v) => match v.split_constraint() {
+                Some((var, constraint)) => {
+                    if !constraints.contains(&(var, constraint)) {
+                        constraints.push((var, constraint));
+                    }
+                    ENodeOrVar::Var(var)
+                }
+                None => ENodeOrVar::Var(*v),
+            },
+            ENodeOrVar::ENode(n) => ENodeOrVar::ENode(n.clone()),
+        })
+        .collect();
+    (RecExpr::from(nodes), constraints)
 }
 
 impl<L: Language + Display> Pattern<L> {
     /// Pretty print this pattern as a sexp with the given width
     pub fn pretty(&self, width: usize) -> String {
-        self.ast.pretty(width)
+        self.constrained_ast
+            .as_ref()
+            .unwrap_or(&self.ast)
+            .pretty(width)
     }
 }
 
@@ -242,7 +560,28 @@ impl<L: Language> TryFrom<Pattern<L>> for RecExpr<L> {
 
 impl<L: Language + Display> Display for Pattern<L> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        Display::fmt(&self.ast, f)
+        Display::fmt(self.constrained_ast.as_ref().unwrap_or(&self.ast), f)
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<L: Language + Display> serde::Serialize for Pattern<L> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let ast = self.constrained_ast.as_ref().unwrap_or(&self.ast);
+        serde::Serialize::serialize(ast, serializer)
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de, L: FromOp> serde::Deserialize<'de> for Pattern<L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <PatternAst<L> as serde::Deserialize>::deserialize(deserializer).map(Self::from)
     }
 }
 
@@ -268,12 +607,31 @@ impl<L: Language, A: Analysis<L>> Searcher<L, A> for Pattern<L> {
         Some(&self.ast)
     }
 
+    fn get_constrained_ast(&self) -> Option<&PatternAst<L>> {
+        Some(self.constrained_ast.as_ref().unwrap_or(&self.ast))
+    }
+
     fn search_with_limit(&self, egraph: &EGraph<L, A>, limit: usize) -> Vec<SearchMatches<L>> {
+        let backend = self.backend.unwrap_or_else(|| egraph.match_backend());
+        if let (MatchBackend::Relational, Some(query)) = (backend, &self.query) {
+            return query
+                .run(egraph, limit)
+                .into_iter()
+                .map(|(eclass, substs)| SearchMatches {
+                    eclass,
+                    substs,
+                    ast: Some(Cow::Borrowed(&self.ast)),
+                })
+                .collect();
+        }
         match self.ast.as_ref().last().unwrap() {
+            ENodeOrVar::ENode(e) if self.matches_longer(e) => {
+                let ids = self.classes_matching_prefix(egraph, e);
+                rewrite::search_eclasses_with_limit(self, egraph, ids.into_iter(), limit)
+            }
             ENodeOrVar::ENode(e) => {
-                #[allow(enum_intrinsics_non_enums)]
-                let key = std::mem::discriminant(e);
-                match egraph.classes_by_op.get(&key) {
+                // only search the e-classes with an e-node that matches the root
+                match egraph.classes_by_op.get(&crate::egraph::op_key(e)) {
                     None => vec![],
                     Some(ids) => rewrite::search_eclasses_with_limit(
                         self,
@@ -292,6 +650,28 @@ impl<L: Language, A: Analysis<L>> Searcher<L, A> for Pattern<L> {
         }
     }
 
+    fn search_iter<'a>(
+        &'a self,
+        egraph: &'a EGraph<L, A>,
+    ) -> Box<dyn Iterator<Item = (Id, Subst)> + 'a> {
+        assert!(egraph.clean, "Tried to search a dirty e-graph!");
+        // only search the e-classes with an e-node that matches the root
+        let ids: Box<dyn Iterator<Item = Id> + 'a> = match self.ast.as_ref().last().unwrap() {
+            ENodeOrVar::ENode(e) if self.matches_longer(e) => {
+                Box::new(self.classes_matching_prefix(egraph, e).into_iter())
+            }
+            ENodeOrVar::ENode(e) => match egraph.classes_by_op.get(&crate::egraph::op_key(e)) {
+                None => Box::new(std::iter::empty()),
+                Some(ids) => Box::new(ids.iter().copied()),
+            },
+            ENodeOrVar::Var(_) => Box::new(egraph.classes().map(|e| e.id)),
+        };
+        Box::new(ids.flat_map(move |eclass| {
+            let substs = self.program.run_with_limit(egraph, eclass, usize::MAX);
+            substs.into_iter().map(move |subst| (eclass, subst))
+        }))
+    }
+
     fn search_eclass_with_limit(
         &self,
         egraph: &EGraph<L, A>,
@@ -400,9 +780,11 @@ pub(crate) fn apply_pat<L: Language, A: Analysis<L>>(
 
     for (i, pat_node) in pat.iter().enumerate() {
         let id = match pat_node {
+            // segments are spliced into their parent by `instantiate_node`
+            ENodeOrVar::Var(w) if w.is_segment() => Id::default(),
             ENodeOrVar::Var(w) => subst[*w],
             ENodeOrVar::ENode(e) => {
-                let n = e.clone().map_children(|child| ids[usize::from(child)]);
+                let n = instantiate_node(e, pat, ids, subst);
                 trace!("adding: {:?}", n);
                 egraph.add(n)
             }
@@ -413,6 +795,38 @@ pub(crate) fn apply_pat<L: Language, A: Analysis<L>>(
     *ids.last().unwrap()
 }
 
+/// Instantiates the pattern e-node `node` from `pat`, given the ids of the
+/// nodes before it, splicing in the children bound to segment variables.
+pub(crate) fn instantiate_node<L: Language>(
+    node: &L,
+    pat: &[ENodeOrVar<L>],
+    ids: &[Id],
+    subst: &Subst,
+) -> L {
+    let segment = |child: Id| match &pat[usize::from(child)] {
+        ENodeOrVar::Var(v) if v.is_segment() => Some(*v),
+        _ => None,
+    };
+    if !node.any(|child| segment(child).is_some()) {
+        return node.clone().map_children(|child| ids[usize::from(child)]);
+    }
+
+    let mut children = Vec::with_capacity(node.len());
+    for &child in node.children() {
+        match segment(child) {
+            Some(v) => children.extend_from_slice(subst.segment(v)),
+            None => children.push(ids[usize::from(child)]),
+        }
+    }
+    node.with_children(&children).unwrap_or_else(|| {
+        panic!(
+            "Can't instantiate {:?} with {} children",
+            node,
+            children.len()
+        )
+    })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -516,4 +930,155 @@ mod tests {
             assert_eq!(m1.substs.len(), usize::min(limit, match_size));
         }
     }
+
+    #[test]
+    fn relational_backend_agrees() {
+        crate::init_logger();
+        let init_expr = &"(+ 1 (+ 2 (+ 3 (* 2 (+ 2 3)))))".parse().unwrap();
+        let rules: Vec<Rewrite<_, ()>> = vec![
+            rewrite!("comm"; "(+ ?x ?y)" => "(+ ?y ?x)"),
+            rewrite!("assoc"; "(+ ?x (+ ?y ?z))" => "(+ (+ ?x ?y) ?z)"),
+        ];
+        let runner = Runner::default().with_expr(init_expr).run(&rules);
+        let egraph = &runner.egraph;
+
+        let normalize = |matches: Vec<SearchMatches<S>>| {
+            let mut all: Vec<(Id, Vec<(Var, Id)>)> = vec![];
+            for m in matches {
+                for subst in m.substs {
+                    let mut pairs: Vec<(Var, Id)> = subst.iter().collect();
+                    pairs.sort();
+                    all.push((m.eclass, pairs));
+                }
+            }
+            all.sort();
+            all
+        };
+
+        for pat in [
+            "(+ ?x (+ ?y ?z))",
+            "(+ ?x (+ ?y ?x))",
+            "(+ (+ ?x ?y) (+ ?y ?x))",
+            "(* 2 (+ ?x 3))",
+            "(+ ?x ?x)",
+            "(- ?x ?y)",
+        ] {
+            let pat: Pattern<S> = pat.parse().unwrap();
+            let top_down = pat.clone().with_backend(MatchBackend::TopDown);
+            let relational = pat.with_backend(MatchBackend::Relational);
+            assert_eq!(
+                normalize(top_down.search(egraph)),
+                normalize(relational.search(egraph)),
+                "{}",
+                top_down
+            );
+            let limited = relational.search_with_limit(egraph, 5);
+            assert!(limited.iter().map(|m| m.substs.len()).sum::<usize>() <= 5);
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Variadic(S);
+
+    impl Language for Variadic {
+        fn matches(&self, other: &Self) -> bool {
+            self.0.matches(&other.0)
+        }
+
+        fn matches_prefix(&self, other: &Self) -> bool {
+            self.0.op.as_str() == "concat"
+                && self.0.op == other.0.op
+                && self.0.len() <= other.0.len()
+        }
+
+        fn children(&self) -> &[Id] {
+            self.0.children()
+        }
+
+        fn children_mut(&mut self) -> &mut [Id] {
+            self.0.children_mut()
+        }
+
+        fn with_children(&self, children: &[Id]) -> Option<Self> {
+            self.0.with_children(children).map(Variadic)
+        }
+    }
+
+    impl FromOp for Variadic {
+        type Error = std::convert::Infallible;
+
+        fn from_op(op: &str, children: Vec<Id>) -> Result<Self, Self::Error> {
+            S::from_op(op, children).map(Variadic)
+        }
+    }
+
+    #[test]
+    fn variadic_prefix_match() {
+        let mut egraph = crate::EGraph::<Variadic, ()>::default();
+        let abc = egraph.add_expr(&"(concat a b c)".parse().unwrap());
+        let ab = egraph.add_expr(&"(concat a b)".parse().unwrap());
+        egraph.add_expr(&"(f a b c)".parse().unwrap());
+        egraph.rebuild();
+        let b = egraph.lookup_expr(&"b".parse().unwrap()).unwrap();
+
+        let eclasses = |pat: &Pattern<Variadic>| -> Vec<Id> {
+            let mut ids: Vec<Id> = pat.search(&egraph).iter().map(|m| m.eclass).collect();
+            ids.sort();
+            ids
+        };
+
+        let pat: Pattern<Variadic> = "(concat a ?x)".parse().unwrap();
+        assert_eq!(eclasses(&pat), vec![abc, ab]);
+        let m = pat.search_eclass(&egraph, abc).unwrap();
+        assert_eq!(m.substs[0]["?x".parse().unwrap()], b);
+        assert_eq!(eclasses(&pat.with_exact_arity()), vec![ab]);
+
+        let ground: Pattern<Variadic> = "(concat a b)".parse().unwrap();
+        assert_eq!(eclasses(&ground), vec![abc, ab]);
+        assert_eq!(eclasses(&ground.with_exact_arity()), vec![ab]);
+
+        let segment: Pattern<Variadic> = "(concat a ?xs...)".parse().unwrap();
+        assert_eq!(eclasses(&segment), vec![abc, ab]);
+        let streamed: Vec<Id> = segment.search_iter(&egraph).map(|(id, _)| id).collect();
+        assert_eq!(streamed, vec![abc, ab]);
+
+        let fixed: Pattern<Variadic> = "(f a ?x)".parse().unwrap();
+        assert!(fixed.search(&egraph).is_empty());
+    }
+
+    #[test]
+    fn segment_variables() {
+        let mut egraph = crate::EGraph::<Variadic, ()>::default();
+        let root = egraph.add_expr(&"(concat a (concat b c))".parse().unwrap());
+        let pair = egraph.add_expr(&"(g (concat b c) (concat b c))".parse().unwrap());
+        egraph.add_expr(&"(g (concat b c) (concat b))".parse().unwrap());
+        egraph.rebuild();
+        let b = egraph.lookup_expr(&"b".parse().unwrap()).unwrap();
+        let c = egraph.lookup_expr(&"c".parse().unwrap()).unwrap();
+
+        let ys: Var = "?ys...".parse().unwrap();
+        let flatten: Rewrite<Variadic, ()> =
+            rewrite!("flatten"; "(concat ?x (concat ?ys...))" => "(concat ?x ?ys...)");
+        let matches = flatten.search(&egraph);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].substs[0].segment(ys), &[b, c]);
+        flatten.apply(&mut egraph, &matches);
+        egraph.rebuild();
+        let flat = egraph.lookup_expr(&"(concat a b c)".parse().unwrap());
+        assert_eq!(flat, Some(egraph.find(root)));
+
+        let same: Pattern<Variadic> = "(g (concat ?ys...) (concat ?ys...))".parse().unwrap();
+        let ids: Vec<Id> = same.search(&egraph).iter().map(|m| m.eclass).collect();
+        assert_eq!(ids, vec![pair]);
+    }
+
+    #[test]
+    fn constrained_vars() {
+        let pat: Pattern<S> = "(* ?c:constant (+ ?c ?x))".parse().unwrap();
+        let c: Var = "?c".parse().unwrap();
+        let x: Var = "?x".parse().unwrap();
+        assert_eq!(pat.vars(), vec![c, x]);
+        assert_eq!(pat.to_string(), "(* ?c:constant (+ ?c ?x))");
+        assert_eq!(pat.ast.to_string(), "(* ?c (+ ?c ?x))");
+    }
 }
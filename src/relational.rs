@@ -0,0 +1,243 @@
+/*!
+Relational e-matching: a pattern is compiled to a conjunctive query with one
+atom per e-node, and the query is answered with a generic join.
+
+Every pattern e-node `(f a b)` at ast index `i` becomes the atom
+`f(a, b, i)` over the relation of all the `f` e-nodes in the egraph, as
+tuples of their children followed by their e-class.
+Variables that appear more than once in the pattern become the same query
+variable, so the join checks their equality on the way instead of after
+having enumerated both sides.
+
+See [`MatchBackend::Relational`].
+!*/
+
+use crate::*;
+
+/// A conjunctive query compiled from a [`PatternAst`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Query<L> {
+    atoms: Vec<Atom<L>>,
+    /// The query variables in the order the join binds them.
+    order: Vec<usize>,
+    /// For each query variable, the constraints it must satisfy.
+    constraints: Vec<Vec<Symbol>>,
+    /// The pattern variables and their query variable.
+    vars: Vec<(Var, usize)>,
+    /// The query variable of the root of the pattern.
+    root: usize,
+    n_vars: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Atom<L> {
+    /// The e-node with zeroed children, as in `classes_by_op`.
+    node: L,
+    /// The query variables of the children, then of the e-class.
+    columns: Vec<usize>,
+    /// The distinct columns, ordered like [`Query::order`].
+    levels: Vec<usize>,
+}
+
+/// A trie over the tuples of one atom, one level per query variable.
+#[derive(Default)]
+struct Trie(HashMap<Id, Trie>);
+
+impl Trie {
+    fn insert(&mut self, key: impl Iterator<Item = Id>) {
+        let mut trie = self;
+        for id in key {
+            trie = trie.0.entry(id).or_default();
+        }
+    }
+}
+
+impl<L: Language> Query<L> {
+    /// Compiles `ast` with the given variable constraints.
+    /// Returns `None` if the pattern can't be expressed as a query: when it
+    /// is a single variable, or has e-nodes that may match e-nodes with more
+    /// children (unless `exact_arity`) or segment variables.
+    pub(crate) fn compile(
+        ast: &PatternAst<L>,
+        constraints: &[(Var, Symbol)],
+        exact_arity: bool,
+    ) -> Option<Self> {
+        let nodes = ast.as_ref();
+        let mut var_of: Vec<usize> = Vec::with_capacity(nodes.len());
+        let mut vars: Vec<(Var, usize)> = vec![];
+        let mut atoms = vec![];
+        for (i, node) in nodes.iter().enumerate() {
+            match node {
+                ENodeOrVar::Var(v) => {
+                    if v.is_segment() {
+                        return None;
+                    }
+                    match vars.iter().find(|(w, _)| w == v) {
+                        Some(&(_, q)) => var_of.push(q),
+                        None => {
+                            vars.push((*v, i));
+                            var_of.push(i);
+                        }
+                    }
+                }
+                ENodeOrVar::ENode(n) => {
+                    if !exact_arity && n.matches_prefix(n) {
+                        return None;
+                    }
+                    let mut columns: Vec<usize> = n
+                        .children()
+                        .iter()
+                        .map(|&c| var_of[usize::from(c)])
+                        .collect();
+                    columns.push(i);
+                    atoms.push(Atom {
+                        node: crate::egraph::op_key(n),
+                        columns,
+                        levels: vec![],
+                    });
+                    var_of.push(i);
+                }
+            }
+        }
+        if atoms.is_empty() {
+            return None;
+        }
+
+        // bind the variables shared by the most atoms first, as they are the
+        // most selective, breaking ties towards the root
+        let n_vars = nodes.len();
+        let mut counts = vec![0; n_vars];
+        for atom in &atoms {
+            let mut seen = atom.columns.clone();
+            seen.sort_unstable();
+            seen.dedup();
+            for q in seen {
+                counts[q] += 1;
+            }
+        }
+        let mut order: Vec<usize> = (0..n_vars).filter(|&q| counts[q] > 0).collect();
+        order.sort_by_key(|&q| (std::cmp::Reverse(counts[q]), std::cmp::Reverse(q)));
+        let position = |q: usize| order.iter().position(|&p| p == q).unwrap();
+        for atom in &mut atoms {
+            let mut levels = atom.columns.clone();
+            levels.sort_by_key(|&q| position(q));
+            levels.dedup();
+            atom.levels = levels;
+        }
+
+        let mut var_constraints = vec![vec![]; n_vars];
+        for (v, constraint) in constraints {
+            if let Some(&(_, q)) = vars.iter().find(|(w, _)| w == v) {
+                var_constraints[q].push(*constraint);
+            }
+        }
+
+        Some(Self {
+            atoms,
+            order,
+            constraints: var_constraints,
+            vars,
+            root: nodes.len() - 1,
+            n_vars,
+        })
+    }
+
+    /// Answers the query over the whole egraph, returning at most `limit`
+    /// substitutions grouped by the e-class they are rooted in, sorted by
+    /// e-class.
+    pub(crate) fn run<N: Analysis<L>>(
+        &self,
+        egraph: &EGraph<L, N>,
+        limit: usize,
+    ) -> Vec<(Id, Vec<Subst>)> {
+        assert!(egraph.clean, "Tried to search a dirty e-graph!");
+        if limit == 0 {
+            return vec![];
+        }
+        let mut tries = Vec::with_capacity(self.atoms.len());
+        for atom in &self.atoms {
+            let mut trie = Trie::default();
+            let classes = match egraph.classes_by_op.get(&atom.node) {
+                Some(classes) => classes,
+                None => return vec![],
+            };
+            for &class in classes {
+                for node in egraph[class].iter().filter(|n| atom.node.matches(n)) {
+                    let ids = node.children().iter().copied().chain(Some(class));
+                    let tuple: Vec<(usize, Id)> = atom.columns.iter().copied().zip(ids).collect();
+                    // columns repeat for repeated variables, as in (+ ?a ?a)
+                    let value = |q: usize| tuple.iter().find(|(p, _)| *p == q).unwrap().1;
+                    if tuple.iter().all(|&(q, id)| value(q) == id) {
+                        trie.insert(atom.levels.iter().map(|&q| value(q)));
+                    }
+                }
+            }
+            tries.push(trie);
+        }
+
+        let mut found: IndexMap<Id, Vec<Subst>> = Default::default();
+        let mut bound = vec![Id::from(0); self.n_vars];
+        let mut remaining = limit;
+        let roots: Vec<&Trie> = tries.iter().collect();
+        self.join(egraph, 0, roots, &mut bound, &mut |bound| {
+            let subst = self.vars.iter().map(|&(v, q)| (v, bound[q])).collect();
+            found.entry(bound[self.root]).or_default().push(subst);
+            remaining -= 1;
+            remaining > 0
+        });
+
+        let mut found: Vec<(Id, Vec<Subst>)> = found.into_iter().collect();
+        found.sort_unstable_by_key(|(id, _)| *id);
+        for (_, substs) in &mut found {
+            // different e-nodes can lead to the same substitution
+            let mut seen = HashSet::default();
+            substs.retain(|subst| seen.insert(subst.clone()));
+        }
+        found
+    }
+
+    /// Binds the query variable `order[depth]` to each value that all the
+    /// atoms containing it agree on, and recurses.
+    /// Returns `false` once `yield_fn` asks to stop.
+    fn join<N: Analysis<L>>(
+        &self,
+        egraph: &EGraph<L, N>,
+        depth: usize,
+        tries: Vec<&Trie>,
+        bound: &mut [Id],
+        yield_fn: &mut impl FnMut(&[Id]) -> bool,
+    ) -> bool {
+        let q = match self.order.get(depth) {
+            Some(&q) => q,
+            None => return yield_fn(bound),
+        };
+        let participants: Vec<usize> = (0..self.atoms.len())
+            .filter(|&a| self.atoms[a].levels.contains(&q))
+            .collect();
+        let smallest = *participants
+            .iter()
+            .min_by_key(|&&a| tries[a].0.len())
+            .unwrap();
+        for &id in tries[smallest].0.keys() {
+            if !participants.iter().all(|&a| tries[a].0.contains_key(&id)) {
+                continue;
+            }
+            if !self.constraints[q]
+                .iter()
+                .all(|&c| N::check_constraint(egraph, id, c))
+            {
+                continue;
+            }
+            bound[q] = id;
+            let mut next = tries.clone();
+            for &a in &participants {
+                let trie: &Trie = tries[a];
+                next[a] = &trie.0[&id];
+            }
+            if !self.join(egraph, depth + 1, next, bound, yield_fn) {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -1,6 +1,9 @@
 use pattern::apply_pat;
 use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use thiserror::Error;
 
 use crate::*;
 
@@ -13,6 +16,22 @@ use crate::*;
 /// It additionally stores a name used to refer to the rewrite and a
 /// long name used for debugging.
 ///
+/// If the `serde-1` feature is enabled, a [`Rewrite`] can be deserialized
+/// from a name, a searcher and an applier [`Pattern`], and an optional list
+/// of [`ConditionEqual`]s that must all hold for the rewrite to apply.
+/// This lets you load rules from a configuration file:
+///
+/// ```
+/// # #[cfg(all(feature = "serde-1", feature = "serde_json"))] {
+/// use egg::*;
+/// let rules: Vec<Rewrite<SymbolLang, ()>> = serde_json::from_str(r#"[
+///     { "name": "commute-add", "searcher": "(+ ?a ?b)", "applier": "(+ ?b ?a)" },
+///     { "name": "div-cancel", "searcher": "(/ ?a ?a)", "applier": "1",
+///       "conditions": [{ "p1": "(!= ?a 0)", "p2": "true" }] }
+/// ]"#).unwrap();
+/// assert_eq!(rules[0].name, Symbol::from("commute-add"));
+/// # }
+/// ```
 #[derive(Clone)]
 #[non_exhaustive]
 pub struct Rewrite<L, N> {
@@ -50,6 +69,123 @@ where
     }
 }
 
+/// Writes the rewrite in the rules file format read by
+/// [`Rewrite::parse`]: `name: lhs => rhs`, followed by
+/// `if a == b, c == d` for a rewrite with [`ConditionEqual`]s.
+///
+/// Searchers and appliers other than [`Pattern`]s, and conditions other
+/// than [`ConditionEqual`]s, are written as placeholders that don't parse.
+impl<L, N> Display for Rewrite<L, N>
+where
+    L: Language + Display,
+    N: Analysis<L>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        match self.searcher.get_constrained_ast() {
+            Some(ast) => write!(f, "{}", ast)?,
+            None => write!(f, "<< searcher >>")?,
+        }
+        match self.applier.get_pattern_ast() {
+            Some(ast) => write!(f, " => {}", ast)?,
+            None => write!(f, " => << applier >>")?,
+        }
+        match self.applier.get_conditions() {
+            Some(conditions) => {
+                for (i, (a, b)) in conditions.iter().enumerate() {
+                    let sep = if i == 0 { " if" } else { "," };
+                    write!(f, "{} {} == {}", sep, a, b)?;
+                }
+                Ok(())
+            }
+            None => write!(f, " if << condition >>"),
+        }
+    }
+}
+
+#[cfg(feature = "serde-1")]
+#[derive(serde::Deserialize)]
+#[serde(bound(deserialize = "L: FromOp"))]
+struct RewriteDef<L> {
+    name: String,
+    searcher: Pattern<L>,
+    applier: Pattern<L>,
+    #[serde(default)]
+    conditions: Vec<ConditionEqual<L>>,
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de, L, N> serde::Deserialize<'de> for Rewrite<L, N>
+where
+    L: FromOp + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let RewriteDef {
+            name,
+            searcher,
+            applier,
+            conditions,
+        } = <RewriteDef<L> as serde::Deserialize>::deserialize(deserializer)?;
+
+        with_conditions(name, searcher, applier, conditions).map_err(D::Error::custom)
+    }
+}
+
+/// Creates a rewrite that only applies when all of `conditions` hold.
+fn with_conditions<L, N>(
+    name: impl Into<Symbol>,
+    searcher: Pattern<L>,
+    applier: Pattern<L>,
+    conditions: Vec<ConditionEqual<L>>,
+) -> Result<Rewrite<L, N>, String>
+where
+    L: Language + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    if conditions.is_empty() {
+        Rewrite::new(name, searcher, applier)
+    } else {
+        let condition = AllEqual(conditions);
+        let applier = ConditionalApplier { condition, applier };
+        Rewrite::new(name, searcher, applier)
+    }
+}
+
+/// A [`Condition`] that holds when all of its [`ConditionEqual`]s hold.
+struct AllEqual<L>(Vec<ConditionEqual<L>>);
+
+impl<L, N> Condition<L, N> for AllEqual<L>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    fn check(&self, egraph: &mut EGraph<L, N>, eclass: Id, subst: &Subst) -> bool {
+        self.0
+            .iter()
+            .all(|c| Condition::check(c, egraph, eclass, subst))
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        self.0
+            .iter()
+            .flat_map(|c| Condition::<L, N>::vars(c))
+            .collect()
+    }
+
+    fn equalities(&self) -> Option<Vec<(&PatternAst<L>, &PatternAst<L>)>> {
+        let mut equalities = vec![];
+        for c in &self.0 {
+            equalities.extend(Condition::<L, N>::equalities(c)?);
+        }
+        Some(equalities)
+    }
+}
+
 impl<L: Language, N: Analysis<L>> Rewrite<L, N> {
     /// Create a new [`Rewrite`]. You typically want to use the
     /// [`rewrite!`] macro instead.
@@ -77,6 +213,91 @@ impl<L: Language, N: Analysis<L>> Rewrite<L, N> {
         })
     }
 
+    /// Starts building a rewrite named `name` from pattern strings, see
+    /// [`RewriteBuilder`].
+    pub fn builder(name: impl Into<Symbol>) -> RewriteBuilder<L, N> {
+        RewriteBuilder {
+            name: name.into(),
+            lhs: None,
+            rhs: None,
+            conditions: vec![],
+            error: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a pair of rewrites between `a` and `b`, one in each
+    /// direction, named `name` and `name-rev`.
+    ///
+    /// This is the same as the `<=>` form of the [`rewrite!`] macro.
+    /// Fails if one pattern uses a variable the other doesn't bind.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let rules: Vec<Rewrite<SymbolLang, ()>> = Rewrite::bidirectional(
+    ///     "add-0",
+    ///     "(+ ?a 0)".parse().unwrap(),
+    ///     "?a".parse().unwrap(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(rules[1].name, Symbol::from("add-0-rev"));
+    ///
+    /// // 0 => (* ?a 0) doesn't bind ?a
+    /// let mul_0 = Rewrite::<SymbolLang, ()>::bidirectional(
+    ///     "mul-0",
+    ///     "(* ?a 0)".parse().unwrap(),
+    ///     "0".parse().unwrap(),
+    /// );
+    /// assert!(mul_0.is_err());
+    /// ```
+    pub fn bidirectional(
+        name: impl Into<Symbol>,
+        a: Pattern<L>,
+        b: Pattern<L>,
+    ) -> Result<Vec<Self>, String>
+    where
+        L: Send + Sync + 'static,
+    {
+        let name = name.into();
+        let rev = format!("{}-rev", name);
+        Ok(vec![
+            Rewrite::new(name, a.clone(), b.clone())?,
+            Rewrite::new(rev, b, a)?,
+        ])
+    }
+
+    /// Like [`bidirectional`](Rewrite::bidirectional()), but both directions
+    /// only apply when `condition` holds.
+    ///
+    /// The same condition is checked in both directions, so it may only
+    /// refer to variables bound by both patterns; otherwise this fails.
+    pub fn bidirectional_if<C>(
+        name: impl Into<Symbol>,
+        a: Pattern<L>,
+        b: Pattern<L>,
+        condition: C,
+    ) -> Result<Vec<Self>, String>
+    where
+        L: Send + Sync + 'static,
+        C: Condition<L, N> + Clone + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let rev = format!("{}-rev", name);
+        let forward = ConditionalApplier {
+            condition: condition.clone(),
+            applier: b.clone(),
+        };
+        let backward = ConditionalApplier {
+            condition,
+            applier: a.clone(),
+        };
+        Ok(vec![
+            Rewrite::new(name, a, forward)?,
+            Rewrite::new(rev, b, backward)?,
+        ])
+    }
+
     /// Call [`search`] on the [`Searcher`].
     ///
     /// [`search`]: Searcher::search()
@@ -122,6 +343,318 @@ impl<L: Language, N: Analysis<L>> Rewrite<L, N> {
     }
 }
 
+impl<L, N> Rewrite<L, N>
+where
+    L: FromOp + Display + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    /// Parses a rewrite written as `name: lhs => rhs`, optionally followed
+    /// by conditions `if a == b, c == d`, each a [`ConditionEqual`].
+    ///
+    /// This is the format [`Display`] writes, so rules round-trip through
+    /// text, and the one read by [`parse_rules`] and [`load_rules`].
+    /// The rewrite is checked like by [`RewriteBuilder::build`].
+    /// Names can't contain a `:`.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let text = "div-cancel: (/ ?a ?a) => 1 if (!= ?a 0) == true";
+    /// let rule: Rewrite<SymbolLang, ()> = Rewrite::parse(text).unwrap();
+    /// assert_eq!(rule.name, Symbol::from("div-cancel"));
+    /// assert_eq!(rule.to_string(), text);
+    ///
+    /// let bad = Rewrite::<SymbolLang, ()>::parse("(+ ?a 0) => ?a");
+    /// assert!(matches!(bad, Err(RewriteError::Syntax { .. })));
+    /// ```
+    pub fn parse(rule: &str) -> Result<Self, RewriteError> {
+        let syntax = |message: &str| RewriteError::Syntax {
+            rule: rule.to_string(),
+            message: message.to_string(),
+        };
+        let (name, rest) = rule
+            .split_once(':')
+            .ok_or_else(|| syntax("expected `name: lhs => rhs`"))?;
+        let (rest, conditions) = match split_top_level(rest, " if ") {
+            Some((rest, conditions)) => (rest, Some(conditions)),
+            None => (rest, None),
+        };
+        let (lhs, rhs) = split_top_level(rest, "=>").ok_or_else(|| syntax("expected `=>`"))?;
+
+        let mut builder = Rewrite::builder(name.trim())
+            .lhs(lhs.trim())
+            .rhs(rhs.trim());
+        if let Some(mut conditions) = conditions {
+            loop {
+                let (condition, rest) = match split_top_level(conditions, ",") {
+                    Some((condition, rest)) => (condition, Some(rest)),
+                    None => (conditions, None),
+                };
+                let (a, b) = split_top_level(condition, "==")
+                    .ok_or_else(|| syntax("expected conditions like `a == b`"))?;
+                builder = builder.condition_eq(a.trim(), b.trim());
+                match rest {
+                    Some(rest) => conditions = rest,
+                    None => break,
+                }
+            }
+        }
+        builder.build()
+    }
+}
+
+/// Splits `s` around the first occurrence of `sep` outside of parentheses.
+fn split_top_level<'a>(s: &'a str, sep: &str) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && s[i..].starts_with(sep) => {
+                return Some((&s[..i], &s[i + sep.len()..]))
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Parses rules written one per line in the format of [`Rewrite::parse`].
+///
+/// Blank lines and lines starting with `#` are skipped.
+///
+/// # Example
+/// ```
+/// use egg::*;
+/// let rules: Vec<Rewrite<SymbolLang, ()>> = parse_rules(
+///     "# arithmetic
+///      commute-add: (+ ?a ?b) => (+ ?b ?a)
+///
+///      add-0: (+ ?a 0) => ?a",
+/// )
+/// .unwrap();
+/// assert_eq!(rules.len(), 2);
+///
+/// let err = parse_rules::<SymbolLang, ()>("ok: a => b\nbad: (f ?x) => ?y").unwrap_err();
+/// assert_eq!(err.to_string(), "line 2: rewrite bad refers to unbound var ?y");
+/// ```
+pub fn parse_rules<L, N>(text: &str) -> Result<Vec<Rewrite<L, N>>, LoadRulesError>
+where
+    L: FromOp + Display + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    let mut rules = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = Rewrite::parse(line).map_err(|source| LoadRulesError::Rule {
+            line: i + 1,
+            source,
+        })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Reads the rules in the file at `path`, see [`parse_rules`].
+///
+/// This lets rule sets live outside of Rust code, and be shared with tools
+/// written in other languages.
+pub fn load_rules<L, N>(path: impl AsRef<Path>) -> Result<Vec<Rewrite<L, N>>, LoadRulesError>
+where
+    L: FromOp + Display + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|source| LoadRulesError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_rules(&text)
+}
+
+/// An error from [`parse_rules`] or [`load_rules`].
+#[derive(Debug, Error)]
+pub enum LoadRulesError {
+    /// The rules file couldn't be read.
+    #[error("could not read {path:?}: {source}")]
+    Io {
+        /// The rules file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// A line doesn't hold a valid rule.
+    #[error("line {line}: {source}")]
+    Rule {
+        /// The line number, starting from 1.
+        line: usize,
+        /// The underlying error.
+        source: RewriteError,
+    },
+}
+
+/// An error building a [`Rewrite`] with a [`RewriteBuilder`] or
+/// [`Rewrite::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RewriteError {
+    /// A pattern of the rewrite couldn't be parsed.
+    #[error("could not parse pattern {pattern:?} of rewrite {name}: {message}")]
+    Parse {
+        /// The name of the rewrite.
+        name: Symbol,
+        /// The pattern string.
+        pattern: String,
+        /// The parse error.
+        message: String,
+    },
+    /// The text of a rule isn't in the format of [`Rewrite::parse`].
+    #[error("malformed rule {rule:?}: {message}")]
+    Syntax {
+        /// The text of the rule.
+        rule: String,
+        /// What's wrong with it.
+        message: String,
+    },
+    /// The rewrite has no left-hand or right-hand side.
+    #[error("rewrite {name} has no {side}")]
+    Missing {
+        /// The name of the rewrite.
+        name: Symbol,
+        /// `"lhs"` or `"rhs"`.
+        side: &'static str,
+    },
+    /// The right-hand side or a condition uses a variable that the
+    /// left-hand side doesn't bind.
+    #[error("rewrite {name} refers to unbound var {var}")]
+    UnboundVar {
+        /// The name of the rewrite.
+        name: Symbol,
+        /// The unbound variable.
+        var: Var,
+    },
+}
+
+/// Builds a [`Rewrite`] from pattern strings, checking it as a whole.
+///
+/// Created with [`Rewrite::builder`].
+/// Unlike the [`rewrite!`] macro, which panics on a malformed rule, all the
+/// problems are reported by [`build`](RewriteBuilder::build()) as a
+/// [`RewriteError`], so rules can come from user input.
+///
+/// # Example
+/// ```
+/// use egg::*;
+/// let rule: Rewrite<SymbolLang, ()> = Rewrite::builder("div-cancel")
+///     .lhs("(/ ?a ?a)")
+///     .rhs("1")
+///     .condition_eq("(!= ?a 0)", "true")
+///     .build()
+///     .unwrap();
+/// assert_eq!(rule.name, Symbol::from("div-cancel"));
+///
+/// let unbound = Rewrite::<SymbolLang, ()>::builder("mul-0")
+///     .lhs("0")
+///     .rhs("(* ?a 0)")
+///     .build();
+/// assert!(matches!(unbound, Err(RewriteError::UnboundVar { .. })));
+///
+/// // operators may be used with different numbers of children
+/// let neg = Rewrite::<SymbolLang, ()>::builder("neg")
+///     .lhs("(- ?a)")
+///     .rhs("(- 0 ?a)")
+///     .build();
+/// assert!(neg.is_ok());
+/// ```
+pub struct RewriteBuilder<L, N> {
+    name: Symbol,
+    lhs: Option<Pattern<L>>,
+    rhs: Option<Pattern<L>>,
+    conditions: Vec<ConditionEqual<L>>,
+    /// The first parse error, reported by `build`.
+    error: Option<RewriteError>,
+    phantom: PhantomData<N>,
+}
+
+impl<L, N> RewriteBuilder<L, N>
+where
+    L: FromOp + Display + Send + Sync + 'static,
+    N: Analysis<L> + 'static,
+{
+    fn parse(&mut self, pattern: &str) -> Option<Pattern<L>> {
+        match pattern.parse() {
+            Ok(pattern) => Some(pattern),
+            Err(err) => {
+                self.error.get_or_insert(RewriteError::Parse {
+                    name: self.name,
+                    pattern: pattern.to_string(),
+                    message: format!("{:?}", err),
+                });
+                None
+            }
+        }
+    }
+
+    /// Sets the left-hand side, which the rewrite searches for.
+    pub fn lhs(mut self, pattern: &str) -> Self {
+        self.lhs = self.parse(pattern);
+        self
+    }
+
+    /// Sets the right-hand side, which the rewrite adds.
+    pub fn rhs(mut self, pattern: &str) -> Self {
+        self.rhs = self.parse(pattern);
+        self
+    }
+
+    /// Only applies the rewrite when the two patterns are equivalent,
+    /// see [`ConditionEqual`].
+    /// When called many times, all the conditions must hold.
+    pub fn condition_eq(mut self, p1: &str, p2: &str) -> Self {
+        if let (Some(p1), Some(p2)) = (self.parse(p1), self.parse(p2)) {
+            self.conditions.push(ConditionEqual::new(p1, p2));
+        }
+        self
+    }
+
+    /// Checks the rewrite and builds it.
+    ///
+    /// This fails if a pattern doesn't parse, a side is missing, or the
+    /// right-hand side or a condition uses a variable that the left-hand
+    /// side doesn't bind.
+    ///
+    /// Arity is checked by the language's [`FromOp`] when the patterns are
+    /// parsed: an operator used with the wrong number of children is a
+    /// [`RewriteError::Parse`] for languages with fixed arities, like the
+    /// ones from [`define_language!`], while [`SymbolLang`] takes any number.
+    pub fn build(self) -> Result<Rewrite<L, N>, RewriteError> {
+        let name = self.name;
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let lhs = self
+            .lhs
+            .ok_or(RewriteError::Missing { name, side: "lhs" })?;
+        let rhs = self
+            .rhs
+            .ok_or(RewriteError::Missing { name, side: "rhs" })?;
+
+        let bound = lhs.vars();
+        let mut used = rhs.vars();
+        for c in &self.conditions {
+            used.extend(Condition::<L, N>::vars(c));
+        }
+        if let Some(&var) = used.iter().find(|v| !bound.contains(v)) {
+            return Err(RewriteError::UnboundVar { name, var });
+        }
+
+        Ok(with_conditions(name, lhs, rhs, self.conditions)
+            .expect("the variables were checked above"))
+    }
+}
+
 /// Searches the given list of e-classes with a limit.
 pub(crate) fn search_eclasses_with_limit<'a, I, S, L, N>(
     searcher: &'a S,
@@ -203,6 +736,47 @@ where
         search_eclasses_with_limit(self, egraph, egraph.classes().map(|e| e.id), limit)
     }
 
+    /// Lazily iterates over the matches in the whole [`EGraph`], along with
+    /// the eclass each one is rooted in.
+    ///
+    /// Unlike [`search`], this doesn't collect every match up front:
+    /// the eclasses are only searched as the iterator advances, so taking
+    /// just the first few matches only searches as many eclasses as it
+    /// takes to find them.
+    /// By default this calls [`search_eclass`] on each eclass in turn.
+    ///
+    /// [`search`]: Searcher::search
+    /// [`search_eclass`]: Searcher::search_eclass
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// for i in 0..100 {
+    ///     egraph.add_expr(&format!("(+ x {})", i).parse().unwrap());
+    /// }
+    /// egraph.rebuild();
+    ///
+    /// let pat: Pattern<SymbolLang> = "(+ x ?n)".parse().unwrap();
+    /// let first: Vec<(Id, Subst)> = pat.search_iter(&egraph).take(3).collect();
+    /// assert_eq!(first.len(), 3);
+    /// assert_eq!(pat.search_iter(&egraph).count(), pat.n_matches(&egraph));
+    /// ```
+    fn search_iter<'a>(
+        &'a self,
+        egraph: &'a EGraph<L, N>,
+    ) -> Box<dyn Iterator<Item = (Id, Subst)> + 'a> {
+        Box::new(
+            egraph
+                .classes()
+                .filter_map(move |e| self.search_eclass(egraph, e.id))
+                .flat_map(|m| {
+                    let eclass = m.eclass;
+                    m.substs.into_iter().map(move |subst| (eclass, subst))
+                }),
+        )
+    }
+
     /// Returns the number of matches in the e-graph
     fn n_matches(&self, egraph: &EGraph<L, N>) -> usize {
         self.search(egraph).iter().map(|m| m.substs.len()).sum()
@@ -213,6 +787,15 @@ where
         None
     }
 
+    /// Like [`get_pattern_ast`](Searcher::get_pattern_ast()), but keeps
+    /// the constraints of variables written `?x:constraint`, which the
+    /// pattern ast leaves out.
+    ///
+    /// This is used to write [`Rewrite`]s out as text.
+    fn get_constrained_ast(&self) -> Option<&PatternAst<L>> {
+        self.get_pattern_ast()
+    }
+
     /// Returns a list of the variables bound by this Searcher
     fn vars(&self) -> Vec<Var>;
 }
@@ -361,6 +944,16 @@ where
         None
     }
 
+    /// Returns the pairs of patterns that must be equivalent for this
+    /// applier to apply, see [`Condition::equalities`].
+    ///
+    /// This is used to write [`Rewrite`]s out as text.
+    /// By default this returns no conditions; appliers that check
+    /// anything else before applying should return `None`.
+    fn get_conditions(&self) -> Option<Vec<(&PatternAst<L>, &PatternAst<L>)>> {
+        Some(vec![])
+    }
+
     /// Apply a single substitution.
     ///
     /// An [`Applier`] should add things and union them with `eclass`.
@@ -398,10 +991,14 @@ where
 /// A [`ConditionalApplier`] simply calls [`check`] on the
 /// [`Condition`] before calling [`apply_one`] on the inner
 /// [`Applier`].
+/// When applying many matches, it checks all of them first and then
+/// passes the ones that hold to the inner [`Applier`]'s
+/// [`apply_matches`] at once.
 ///
 /// See the [`rewrite!`] macro documentation for an example.
 ///
 /// [`apply_one`]: Applier::apply_one()
+/// [`apply_matches`]: Applier::apply_matches()
 /// [`check`]: Condition::check()
 #[derive(Clone, Debug)]
 pub struct ConditionalApplier<C, A> {
@@ -427,6 +1024,37 @@ where
         self.applier.get_pattern_ast()
     }
 
+    fn get_conditions(&self) -> Option<Vec<(&PatternAst<L>, &PatternAst<L>)>> {
+        let mut conditions = self.condition.equalities()?;
+        conditions.extend(self.applier.get_conditions()?);
+        Some(conditions)
+    }
+
+    fn apply_matches(
+        &self,
+        egraph: &mut EGraph<L, N>,
+        matches: &[SearchMatches<L>],
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        let mut passed = vec![];
+        for mat in matches {
+            let substs: Vec<Subst> = mat
+                .substs
+                .iter()
+                .filter(|subst| self.condition.check(egraph, mat.eclass, subst))
+                .cloned()
+                .collect();
+            if !substs.is_empty() {
+                passed.push(SearchMatches {
+                    eclass: mat.eclass,
+                    substs,
+                    ast: mat.ast.clone(),
+                });
+            }
+        }
+        self.applier.apply_matches(egraph, &passed, rule_name)
+    }
+
     fn apply_one(
         &self,
         egraph: &mut EGraph<L, N>,
@@ -480,6 +1108,15 @@ where
     fn vars(&self) -> Vec<Var> {
         vec![]
     }
+
+    /// Returns the pairs of patterns that this Condition checks are
+    /// equivalent, if that's all it checks.
+    ///
+    /// This is used to write [`Rewrite`]s out as text.
+    /// By default this returns `None`, for conditions with other logic.
+    fn equalities(&self) -> Option<Vec<(&PatternAst<L>, &PatternAst<L>)>> {
+        None
+    }
 }
 
 impl<L, F, N> Condition<L, N> for F
@@ -497,17 +1134,68 @@ where
 ///
 /// This condition adds its two [`Pattern`] to the egraph and passes
 /// if and only if they are equivalent (in the same eclass).
+/// Adding the patterns lets other rules work on them, so the condition may
+/// pass in a later iteration, but it also leaves them in the egraph when the
+/// condition fails.
+/// Use [`lookup_only`](ConditionEqual::lookup_only()) to only look the
+/// patterns up instead.
+///
+/// Adding stays the default because most conditions need it: a condition
+/// like `(!= ?a 0) == true` only holds once other rules have rewritten
+/// `(!= ?a 0)` to `true`, which they can't do unless the term is in the
+/// egraph, so a lookup-only version of it never holds for terms the egraph
+/// didn't already contain.
+/// Lookup-only conditions are for when both sides are expected to be
+/// there already, and can't be written as text, see
+/// [`Condition::equalities`].
 ///
-#[derive(Debug)]
+/// If the `serde-1` feature is enabled, this can be serialized and
+/// deserialized as its two patterns and an optional `lookup_only` flag.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde-1",
+    serde(bound(serialize = "L: Language + Display", deserialize = "L: FromOp"))
+)]
 pub struct ConditionEqual<L> {
     p1: Pattern<L>,
     p2: Pattern<L>,
+    #[cfg_attr(feature = "serde-1", serde(default))]
+    lookup_only: bool,
 }
 
 impl<L: Language> ConditionEqual<L> {
     /// Create a new [`ConditionEqual`] condition given two patterns.
     pub fn new(p1: Pattern<L>, p2: Pattern<L>) -> Self {
-        ConditionEqual { p1, p2 }
+        ConditionEqual {
+            p1,
+            p2,
+            lookup_only: false,
+        }
+    }
+
+    /// Makes this condition only look up its patterns with
+    /// [`EGraph::lookup_instantiation`] instead of adding them,
+    /// so checking it never changes the egraph.
+    /// The condition then fails if either pattern isn't already in the
+    /// egraph.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("div-cancel"; "(/ ?a ?a)" => "1"
+    ///     if ConditionEqual::parse("(!= ?a 0)", "true").lookup_only())];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(/ x x)".parse().unwrap())
+    ///     .run(rules);
+    /// // (!= x 0) was never added
+    /// assert_eq!(runner.egraph.number_of_classes(), 2);
+    /// ```
+    pub fn lookup_only(self) -> Self {
+        ConditionEqual {
+            lookup_only: true,
+            ..self
+        }
     }
 }
 
@@ -516,10 +1204,7 @@ impl<L: FromOp> ConditionEqual<L> {
     ///
     /// This panics if the parsing fails.
     pub fn parse(a1: &str, a2: &str) -> Self {
-        Self {
-            p1: a1.parse().unwrap(),
-            p2: a2.parse().unwrap(),
-        }
+        Self::new(a1.parse().unwrap(), a2.parse().unwrap())
     }
 }
 
@@ -529,6 +1214,11 @@ where
     N: Analysis<L>,
 {
     fn check(&self, egraph: &mut EGraph<L, N>, _eclass: Id, subst: &Subst) -> bool {
+        if self.lookup_only {
+            let a1 = egraph.lookup_instantiation(&self.p1.ast, subst);
+            let a2 = egraph.lookup_instantiation(&self.p2.ast, subst);
+            return a1.is_some() && a1 == a2;
+        }
         let mut id_buf_1 = vec![0.into(); self.p1.ast.as_ref().len()];
         let mut id_buf_2 = vec![0.into(); self.p2.ast.as_ref().len()];
         let a1 = apply_pat(&mut id_buf_1, self.p1.ast.as_ref(), egraph, subst);
@@ -541,6 +1231,14 @@ where
         vars.extend(self.p2.vars());
         vars
     }
+
+    fn equalities(&self) -> Option<Vec<(&PatternAst<L>, &PatternAst<L>)>> {
+        // the text format has no syntax for lookup-only equalities
+        if self.lookup_only {
+            return None;
+        }
+        Some(vec![(&self.p1.ast, &self.p2.ast)])
+    }
 }
 
 #[cfg(test)]
@@ -551,6 +1249,102 @@ mod tests {
 
     type EGraph = crate::EGraph<S, ()>;
 
+    #[test]
+    fn rules_round_trip_through_text() {
+        let text = "
+            # comments and blank lines are skipped
+
+            commute-add: (+ ?a ?b) => (+ ?b ?a)
+            mul-0: (* ?a:num 0) => 0
+            div-cancel: (/ ?a ?a) => 1 if (!= ?a 0) == true, (== (== ?a 0) false) == true
+            cmp: (< ?a ?b) => (== (- ?a ?b) neg)
+        ";
+        let rules: Vec<Rewrite<S, ()>> = parse_rules(text).unwrap();
+        let written: Vec<String> = rules.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            written,
+            vec![
+                "commute-add: (+ ?a ?b) => (+ ?b ?a)",
+                // constraints are kept, or the rule would read back more general
+                "mul-0: (* ?a:num 0) => 0",
+                "div-cancel: (/ ?a ?a) => 1 if (!= ?a 0) == true, (== (== ?a 0) false) == true",
+                "cmp: (< ?a ?b) => (== (- ?a ?b) neg)",
+            ]
+        );
+        for (rule, line) in rules.iter().zip(&written) {
+            let reparsed: Rewrite<S, ()> = Rewrite::parse(line).unwrap();
+            assert_eq!(reparsed.name, rule.name);
+            assert_eq!(&reparsed.to_string(), line);
+        }
+
+        let lookup: Rewrite<S, ()> = rewrite!("div-cancel"; "(/ ?a ?a)" => "1"
+            if ConditionEqual::parse("(!= ?a 0)", "true").lookup_only());
+        assert_eq!(
+            lookup.to_string(),
+            "div-cancel: (/ ?a ?a) => 1 if << condition >>"
+        );
+
+        let err = parse_rules::<S, ()>("\nno-arrow: (+ ?a 0)").unwrap_err();
+        assert!(matches!(
+            err,
+            LoadRulesError::Rule {
+                line: 2,
+                source: RewriteError::Syntax { .. }
+            }
+        ));
+    }
+
+    #[test]
+    fn builder_checks_arity_when_parsing() {
+        define_language! {
+            enum Arith {
+                "+" = Add([Id; 2]),
+                "-" = Neg(Id),
+                Num(i32),
+            }
+        }
+
+        let rule = Rewrite::<Arith, ()>::builder("sub")
+            .lhs("(+ ?a (- ?b))")
+            .rhs("(- ?b ?a)")
+            .build();
+        match rule {
+            Err(RewriteError::Parse { name, pattern, .. }) => {
+                assert_eq!(name, Symbol::from("sub"));
+                assert_eq!(pattern, "(- ?b ?a)");
+            }
+            _ => panic!("expected a parse error"),
+        }
+        let rule = Rewrite::<Arith, ()>::builder("neg-neg")
+            .lhs("(- (- ?a))")
+            .rhs("?a")
+            .build();
+        assert!(rule.is_ok());
+    }
+
+    #[test]
+    fn bidirectional_shares_condition() {
+        let nonzero = ConditionEqual::parse("(!= ?a 0)", "true");
+        let rules: Vec<Rewrite<S, ()>> = Rewrite::bidirectional_if(
+            "div-mul",
+            "(/ ?a ?b)".parse().unwrap(),
+            "(* ?a (/ 1 ?b))".parse().unwrap(),
+            nonzero,
+        )
+        .unwrap();
+        assert_eq!(rules[0].name, Symbol::from("div-mul"));
+        assert_eq!(rules[1].name, Symbol::from("div-mul-rev"));
+
+        // ?c is bound by neither side
+        let unbound = Rewrite::<S, ()>::bidirectional_if(
+            "add-comm",
+            "(+ ?a ?b)".parse().unwrap(),
+            "(+ ?b ?a)".parse().unwrap(),
+            ConditionEqual::parse("?c", "0"),
+        );
+        assert!(unbound.is_err());
+    }
+
     #[test]
     fn conditional_rewrite() {
         crate::init_logger();
@@ -653,4 +1447,39 @@ mod tests {
         fold_add.run(&mut egraph);
         assert_eq!(egraph.equivs(&start, &goal), vec![egraph.find(root)]);
     }
+
+    #[cfg(all(feature = "serde-1", feature = "serde_json"))]
+    #[test]
+    fn deserialize_rewrites() {
+        let pat: Pattern<S> = "(+ ?a (* ?b 2))".parse().unwrap();
+        let json = serde_json::to_string(&pat).unwrap();
+        assert_eq!(json, r#""(+ ?a (* ?b 2))""#);
+        assert_eq!(serde_json::from_str::<Pattern<S>>(&json).unwrap(), pat);
+        assert!(serde_json::from_str::<Pattern<S>>(r#""(+ ?a""#).is_err());
+
+        let rules: Vec<Rewrite<S, ()>> = serde_json::from_str(
+            r#"[{ "name": "div-cancel", "searcher": "(/ ?a ?a)", "applier": "1",
+                  "conditions": [{ "p1": "(nonzero ?a)", "p2": "true" }] }]"#,
+        )
+        .unwrap();
+
+        let mut egraph = EGraph::default();
+        let xx = egraph.add_expr(&"(/ x x)".parse().unwrap());
+        let yy = egraph.add_expr(&"(/ y y)".parse().unwrap());
+        egraph.union_instantiations(
+            &"(nonzero x)".parse().unwrap(),
+            &"true".parse().unwrap(),
+            &Default::default(),
+            "assume",
+        );
+        egraph.rebuild();
+        rules[0].run(&mut egraph);
+
+        let one = egraph.lookup_expr(&"1".parse().unwrap()).unwrap();
+        assert_eq!(egraph.find(xx), one);
+        assert_ne!(egraph.find(yy), one);
+
+        let unbound = r#"{ "name": "bad", "searcher": "?a", "applier": "?b" }"#;
+        assert!(serde_json::from_str::<Rewrite<S, ()>>(unbound).is_err());
+    }
 }
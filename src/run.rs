@@ -154,10 +154,22 @@ pub struct Runner<L: Language, N: Analysis<L>, IterData = ()> {
     // limits
     iter_limit: usize,
     node_limit: usize,
+    size_metric: SizeMetric,
     time_limit: Duration,
 
     start_time: Option<Instant>,
     scheduler: Box<dyn RewriteScheduler<L, N>>,
+    /// Whether `scheduler` is a [`SimpleScheduler`].
+    simple_scheduler: bool,
+
+    cost_audit: Option<CostAudit<L, N>>,
+    rule_costs: IndexMap<Symbol, RuleCostAudit>,
+    application_limits: IndexMap<Symbol, usize>,
+    depth_limit: Option<(usize, ClassDepth<L, N>)>,
+    disabled_rules: IndexSet<Symbol>,
+    metrics: Option<MetricsSink>,
+    parallel_search: Option<ParallelSearch<L, N>>,
+    rule_names: IndexSet<Symbol>,
 }
 
 impl<L, N> Default for Runner<L, N, ()>
@@ -186,9 +198,19 @@ where
             hooks,
             iter_limit,
             node_limit,
+            size_metric,
             time_limit,
             start_time,
             scheduler: _,
+            simple_scheduler: _,
+            cost_audit,
+            rule_costs,
+            application_limits,
+            depth_limit,
+            disabled_rules,
+            metrics,
+            parallel_search,
+            rule_names,
         } = self;
 
         f.debug_struct("Runner")
@@ -199,9 +221,18 @@ where
             .field("hooks", &vec![format_args!("<dyn FnMut ..>"); hooks.len()])
             .field("iter_limit", iter_limit)
             .field("node_limit", node_limit)
+            .field("size_metric", size_metric)
             .field("time_limit", time_limit)
             .field("start_time", start_time)
             .field("scheduler", &format_args!("<dyn RewriteScheduler ..>"))
+            .field("cost_audit", &cost_audit.is_some())
+            .field("rule_costs", rule_costs)
+            .field("application_limits", application_limits)
+            .field("depth_limit", &depth_limit.map(|(limit, _)| limit))
+            .field("disabled_rules", disabled_rules)
+            .field("metrics", &metrics.as_ref().map(|m| m.format))
+            .field("parallel_search", &parallel_search.is_some())
+            .field("rule_names", rule_names)
             .finish()
     }
 }
@@ -245,6 +276,19 @@ pub struct Report {
     pub search_time: f64,
     pub apply_time: f64,
     pub rebuild_time: f64,
+    /// Rules that, according to the audit enabled by
+    /// [`with_cost_audit`](Runner::with_cost_audit()),
+    /// only ever produced terms strictly more expensive than the best term in
+    /// the e-class they matched.
+    /// Empty if no audit was enabled.
+    pub worsening_rules: Vec<Symbol>,
+    /// Rules that were run but never matched anything.
+    pub unmatched_rules: Vec<Symbol>,
+    /// Rules that matched but never added an e-node or caused a union.
+    pub unproductive_rules: Vec<Symbol>,
+    /// Rules whose number of matches grew geometrically at some point of
+    /// the run.
+    pub exploding_rules: Vec<RuleExplosion>,
 }
 
 impl std::fmt::Display for Report {
@@ -260,10 +304,104 @@ impl std::fmt::Display for Report {
         writeln!(f, "    Search:  ({:.2}) {}", self.search_time / self.total_time, self.search_time)?;
         writeln!(f, "    Apply:   ({:.2}) {}", self.apply_time / self.total_time, self.apply_time)?;
         writeln!(f, "    Rebuild: ({:.2}) {}", self.rebuild_time / self.total_time, self.rebuild_time)?;
+        if !self.worsening_rules.is_empty() {
+            writeln!(f, "  Rules that only made things worse:")?;
+            for name in &self.worsening_rules {
+                writeln!(f, "    {}", name)?;
+            }
+        }
+        if !self.unmatched_rules.is_empty() {
+            writeln!(f, "  Rules that never matched:")?;
+            for name in &self.unmatched_rules {
+                writeln!(f, "    {}", name)?;
+            }
+        }
+        if !self.unproductive_rules.is_empty() {
+            writeln!(f, "  Rules that matched but never changed anything:")?;
+            for name in &self.unproductive_rules {
+                writeln!(f, "    {}", name)?;
+            }
+        }
+        for explosion in &self.exploding_rules {
+            writeln!(f, "  {}", explosion)?;
+        }
         Ok(())
     }
 }
 
+/// A rule whose number of matches per iteration grew geometrically,
+/// the telltale sign of rules like associativity blowing up the egraph.
+///
+/// A rule is flagged once its matches at least doubled
+/// [`RuleExplosion::WINDOW`] iterations in a row.
+/// The [`Runner`] logs a warning as soon as this happens, and the rules are
+/// listed in [`Report::exploding_rules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize))]
+pub struct RuleExplosion {
+    /// The name of the rule.
+    pub rule: Symbol,
+    /// The number of matches of the rule in every iteration of the run.
+    pub matches: Vec<usize>,
+}
+
+impl RuleExplosion {
+    /// How many times in a row the matches must double.
+    pub const WINDOW: usize = 3;
+
+    fn is_exploding_at(matches: &[usize], end: usize) -> bool {
+        end > Self::WINDOW
+            && matches[end - Self::WINDOW - 1..end]
+                .windows(2)
+                .all(|w| w[0] > 0 && w[1] >= 2 * w[0])
+    }
+
+    fn detect(rule: Symbol, matches: Vec<usize>) -> Option<Self> {
+        (0..=matches.len())
+            .any(|end| Self::is_exploding_at(&matches, end))
+            .then(|| RuleExplosion { rule, matches })
+    }
+}
+
+impl std::fmt::Display for RuleExplosion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rule {} is exploding, matches per iteration: {:?}. \
+             Consider removing it, disabling it once it has done its job, \
+             or limiting it with BackoffScheduler::rule_match_limit.",
+            self.rule, self.matches
+        )
+    }
+}
+
+/// Cost statistics for a single rule, gathered by a [`Runner`] with
+/// [`with_cost_audit`](Runner::with_cost_audit()) enabled.
+///
+/// Each match of the rule compares the cost of the instantiated right-hand
+/// side against the cost of the best term in the matched e-class, both
+/// computed before the rule is applied.
+/// Only rules whose applier has a [pattern ast](Applier::get_pattern_ast())
+/// are audited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize))]
+pub struct RuleCostAudit {
+    /// Matches whose right-hand side was no more expensive than the
+    /// matched e-class.
+    pub not_worse: usize,
+    /// Matches whose right-hand side was strictly more expensive than the
+    /// matched e-class.
+    pub worse: usize,
+}
+
+impl RuleCostAudit {
+    /// Returns `true` if this rule was seen, and every time it made things
+    /// strictly worse.
+    pub fn only_worse(&self) -> bool {
+        self.worse > 0 && self.not_worse == 0
+    }
+}
+
 /// Data generated by running a [`Runner`] one iteration.
 ///
 /// If the `serde-1` feature is enabled, this implements
@@ -284,6 +422,11 @@ pub struct Iteration<IterData> {
     /// A map from rule name to number of times it was _newly_ applied
     /// in this iteration.
     pub applied: IndexMap<Symbol, usize>,
+    /// A map from rule name to the number of matches found in this
+    /// iteration, before any [application
+    /// limit](Runner::with_rule_application_limit()).
+    /// Rules with no matches are left out.
+    pub matched: IndexMap<Symbol, usize>,
     /// Seconds spent running hooks.
     pub hook_time: f64,
     /// Seconds spent searching in this iteration.
@@ -303,8 +446,151 @@ pub struct Iteration<IterData> {
     pub stop_reason: Option<StopReason>,
 }
 
+/// How a [`Runner`] measures the size of its egraph for the
+/// [node limit](Runner::with_node_limit()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMetric {
+    /// The size of the hashcons, see [`EGraph::total_size`].
+    /// Cheap, but it can count stale entries between rebuilds.
+    Hashcons,
+    /// The number of e-nodes, see [`EGraph::total_number_of_nodes`].
+    /// Takes time linear in the number of e-classes to measure.
+    Nodes,
+    /// The number of e-classes, see [`EGraph::number_of_classes`].
+    Classes,
+}
+
+impl SizeMetric {
+    /// Measures `egraph` by this metric.
+    pub fn measure<L: Language, N: Analysis<L>>(self, egraph: &EGraph<L, N>) -> usize {
+        match self {
+            SizeMetric::Hashcons => egraph.total_size(),
+            SizeMetric::Nodes => egraph.total_number_of_nodes(),
+            SizeMetric::Classes => egraph.number_of_classes(),
+        }
+    }
+}
+
+/// The format of the metrics streamed by
+/// [`with_metrics`](Runner::with_metrics()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// Comma-separated values with a header row.
+    /// The `matched` and `applied` columns hold `rule=count` pairs
+    /// separated by `;`.
+    Csv,
+    /// One JSON object per line, with `matched` and `applied` as objects
+    /// from rule name to count.
+    JsonLines,
+}
+
+struct MetricsSink {
+    writer: Box<dyn std::io::Write>,
+    format: MetricsFormat,
+    wrote_header: bool,
+}
+
+impl MetricsSink {
+    fn write<IterData>(&mut self, i: usize, iter: &Iteration<IterData>) -> std::io::Result<()> {
+        let stop_reason = iter.stop_reason.as_ref().map(|r| format!("{:?}", r));
+        let w = &mut self.writer;
+        match self.format {
+            MetricsFormat::Csv => {
+                if !self.wrote_header {
+                    writeln!(w, "iteration,egraph_nodes,egraph_classes,hook_time,search_time,apply_time,rebuild_time,total_time,n_rebuilds,stop_reason,matched,applied")?;
+                    self.wrote_header = true;
+                }
+                let counts = |map: &IndexMap<Symbol, usize>| -> String {
+                    let pairs: Vec<String> = map
+                        .iter()
+                        .map(|(name, n)| format!("{}={}", name, n))
+                        .collect();
+                    csv_field(&pairs.join(";"))
+                };
+                writeln!(
+                    w,
+                    "{},{},{},{},{},{},{},{},{},{},{},{}",
+                    i,
+                    iter.egraph_nodes,
+                    iter.egraph_classes,
+                    iter.hook_time,
+                    iter.search_time,
+                    iter.apply_time,
+                    iter.rebuild_time,
+                    iter.total_time,
+                    iter.n_rebuilds,
+                    csv_field(stop_reason.as_deref().unwrap_or("")),
+                    counts(&iter.matched),
+                    counts(&iter.applied),
+                )?;
+            }
+            MetricsFormat::JsonLines => {
+                let counts = |map: &IndexMap<Symbol, usize>| -> String {
+                    let pairs: Vec<String> = map
+                        .iter()
+                        .map(|(name, n)| format!("{}:{}", json_string(name.as_str()), n))
+                        .collect();
+                    pairs.join(",")
+                };
+                writeln!(
+                    w,
+                    "{{\"iteration\":{},\"egraph_nodes\":{},\"egraph_classes\":{},\"hook_time\":{},\"search_time\":{},\"apply_time\":{},\"rebuild_time\":{},\"total_time\":{},\"n_rebuilds\":{},\"stop_reason\":{},\"matched\":{{{}}},\"applied\":{{{}}}}}",
+                    i,
+                    iter.egraph_nodes,
+                    iter.egraph_classes,
+                    iter.hook_time,
+                    iter.search_time,
+                    iter.apply_time,
+                    iter.rebuild_time,
+                    iter.total_time,
+                    iter.n_rebuilds,
+                    stop_reason.as_deref().map_or("null".into(), json_string),
+                    counts(&iter.matched),
+                    counts(&iter.applied),
+                )?;
+            }
+        }
+        w.flush()
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+type ParallelSearch<L, N> =
+    for<'a> fn(&EGraph<L, N>, &[&'a Rewrite<L, N>]) -> Vec<Vec<SearchMatches<'a, L>>>;
+
+type ClassDepth<L, N> = fn(&EGraph<L, N>, Id) -> usize;
+
 type RunnerResult<T> = std::result::Result<T, StopReason>;
 
+type CostAudit<L, N> = Box<
+    dyn FnMut(&EGraph<L, N>, &[&Rewrite<L, N>], &[Vec<SearchMatches<L>>]) -> Vec<RuleCostAudit>,
+>;
+
 impl<L, N, IterData> Runner<L, N, IterData>
 where
     L: Language,
@@ -316,6 +602,7 @@ where
         Self {
             iter_limit: 30,
             node_limit: 10_000,
+            size_metric: SizeMetric::Hashcons,
             time_limit: Duration::from_secs(5),
 
             egraph: EGraph::new(analysis),
@@ -326,6 +613,16 @@ where
 
             start_time: None,
             scheduler: Box::new(BackoffScheduler::default()),
+            simple_scheduler: false,
+
+            cost_audit: None,
+            rule_costs: Default::default(),
+            application_limits: Default::default(),
+            depth_limit: None,
+            disabled_rules: Default::default(),
+            metrics: None,
+            parallel_search: None,
+            rule_names: Default::default(),
         }
     }
 
@@ -335,15 +632,145 @@ where
     }
 
     /// Sets the egraph size limit (in enodes). Default: 10,000
+    ///
+    /// What is compared against the limit is set by
+    /// [`with_size_metric`](Runner::with_size_metric()).
     pub fn with_node_limit(self, node_limit: usize) -> Self {
         Self { node_limit, ..self }
     }
 
+    /// Sets how the egraph size is measured for the
+    /// [node limit](Runner::with_node_limit()).
+    /// Default: [`SizeMetric::Hashcons`]
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(+ x y)".parse().unwrap())
+    ///     .with_size_metric(SizeMetric::Classes)
+    ///     .with_node_limit(3)
+    ///     .run(rules);
+    /// // commuting adds nodes, but never a new e-class
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// ```
+    pub fn with_size_metric(self, size_metric: SizeMetric) -> Self {
+        Self {
+            size_metric,
+            ..self
+        }
+    }
+
     /// Sets the runner time limit. Default: 5 seconds
     pub fn with_time_limit(self, time_limit: Duration) -> Self {
         Self { time_limit, ..self }
     }
 
+    /// Applies at most `limit` matches of the rewrite named `name` in each
+    /// iteration.
+    ///
+    /// This keeps a rule that matches explosively, like associativity,
+    /// from using up the whole node limit before the other rules get a
+    /// chance to apply.
+    /// Unlike the bans of the [`BackoffScheduler`], the rule still applies
+    /// every iteration.
+    /// The matches that are applied are those in the e-classes with the
+    /// lowest ids, so the choice doesn't depend on search order.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(+ (+ a b) (+ c d))".parse().unwrap())
+    ///     .with_rule_application_limit("commute-add", 1)
+    ///     .with_iter_limit(1)
+    ///     .with_scheduler(SimpleScheduler)
+    ///     .run(rules);
+    /// assert_eq!(runner.iterations[0].applied[&Symbol::from("commute-add")], 1);
+    /// ```
+    pub fn with_rule_application_limit(mut self, name: impl Into<Symbol>, limit: usize) -> Self {
+        self.application_limits.insert(name.into(), limit);
+        self
+    }
+
+    /// Skips the matches whose instantiated right-hand side would be deeper
+    /// than `limit`.
+    ///
+    /// `depth` gives the depth of the shallowest term in an e-class;
+    /// the [`MinDepth`] analysis tracks exactly that, or a custom analysis
+    /// can track it alongside its other data.
+    /// A variable of the right-hand side is as deep as the e-class it's
+    /// bound to, and every e-node adds one level.
+    /// This bounds the terms that expansion rules like `?a => (* ?a 1)`
+    /// can build, without having to add a condition to each of them.
+    /// Only rules whose applier is a [`Pattern`] are limited.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, MinDepth>] = &[rw!("wrap"; "(g ?a)" => "(g (f ?a))")];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(g x)".parse().unwrap())
+    ///     .with_depth_limit(3, |egraph, id| egraph[id].data)
+    ///     .run(rules);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// assert!(runner.egraph.lookup_expr(&"(g (f x))".parse().unwrap()).is_some());
+    /// // (g (f (f x))) is 4 deep
+    /// assert!(runner.egraph.lookup_expr(&"(f (f x))".parse().unwrap()).is_none());
+    /// ```
+    pub fn with_depth_limit(self, limit: usize, depth: fn(&EGraph<L, N>, Id) -> usize) -> Self {
+        Self {
+            depth_limit: Some((limit, depth)),
+            ..self
+        }
+    }
+
+    /// Stops searching and applying the rewrite named `name` until it is
+    /// re-enabled with [`enable_rule`](Runner::enable_rule()).
+    /// Returns `false` if it was already disabled.
+    ///
+    /// This can be called from a [hook](Runner::with_hook()), for example
+    /// to turn off expansion rules once a term of the desired shape shows
+    /// up and continue with only the simplification rules.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("expand"; "(double ?a)" => "(+ ?a ?a)"),
+    ///     rw!("grow"; "?a" => "(id ?a)"),
+    /// ];
+    /// let goal: Pattern<SymbolLang> = "(+ ?a ?a)".parse().unwrap();
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(double x)".parse().unwrap())
+    ///     .with_hook(move |runner| {
+    ///         if !goal.search(&runner.egraph).is_empty() {
+    ///             runner.disable_rule("grow");
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .run(rules);
+    /// assert!(!runner.is_rule_enabled("grow"));
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// ```
+    pub fn disable_rule(&mut self, name: impl Into<Symbol>) -> bool {
+        self.disabled_rules.insert(name.into())
+    }
+
+    /// Re-enables a rewrite disabled with
+    /// [`disable_rule`](Runner::disable_rule()).
+    /// Returns `false` if it wasn't disabled.
+    pub fn enable_rule(&mut self, name: impl Into<Symbol>) -> bool {
+        self.disabled_rules.remove(&name.into())
+    }
+
+    /// Returns `false` if the rewrite named `name` is disabled.
+    pub fn is_rule_enabled(&self, name: impl Into<Symbol>) -> bool {
+        !self.disabled_rules.contains(&name.into())
+    }
+
     /// Add a hook to instrument or modify the behavior of a [`Runner`].
     /// Each hook will run at the beginning of each iteration, i.e. before
     /// all the rewrites.
@@ -379,8 +806,57 @@ where
     /// The default one is [`BackoffScheduler`].
     ///
     pub fn with_scheduler(self, scheduler: impl RewriteScheduler<L, N> + 'static) -> Self {
+        let simple_scheduler = (&scheduler as &dyn std::any::Any).is::<SimpleScheduler>();
         let scheduler = Box::new(scheduler);
-        Self { scheduler, ..self }
+        Self {
+            scheduler,
+            simple_scheduler,
+            ..self
+        }
+    }
+
+    /// Audit the rules this runner applies with the given [`CostFunction`].
+    ///
+    /// Before applying each rule, the runner compares the cost of every
+    /// instantiated right-hand side against the best cost of the e-class it
+    /// matched. The results are available from
+    /// [`rule_costs`](Runner::rule_costs()), and rules that only ever made
+    /// things strictly worse are listed in
+    /// [`Report::worsening_rules`].
+    ///
+    /// This runs a full extraction every iteration, so it's meant as a
+    /// debugging aid for ruleset authors, not something to leave on.
+    ///
+    /// # Example
+    /// ```
+    /// # use egg::*;
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+    ///     rewrite!("expand"; "?a" => "(+ ?a 0)"),
+    /// ];
+    ///
+    /// let runner = Runner::<SymbolLang, ()>::default()
+    ///     .with_iter_limit(3)
+    ///     .with_expr(&"(+ x 0)".parse().unwrap())
+    ///     .with_cost_audit(AstSize)
+    ///     .run(rules);
+    ///
+    /// assert_eq!(runner.report().worsening_rules, vec![Symbol::from("expand")]);
+    /// ```
+    pub fn with_cost_audit<CF>(mut self, mut cost_function: CF) -> Self
+    where
+        CF: CostFunction<L> + 'static,
+    {
+        self.cost_audit = Some(Box::new(move |egraph, rules, matches| {
+            audit_rule_costs(egraph, &mut cost_function, rules, matches)
+        }));
+        self
+    }
+
+    /// The per-rule cost statistics gathered so far by the audit enabled with
+    /// [`with_cost_audit`](Runner::with_cost_audit()).
+    pub fn rule_costs(&self) -> &IndexMap<Symbol, RuleCostAudit> {
+        &self.rule_costs
     }
 
     /// Add an expression to the egraph to be run.
@@ -394,6 +870,80 @@ where
         self
     }
 
+    /// Add groups of expressions that are already known to be equal.
+    ///
+    /// The members of each group are added to the egraph and unioned
+    /// together up front with
+    /// [`assert_equalities`](EGraph::assert_equalities()), justified by
+    /// `"equiv-exprs"`. The eclass id of the first member of each nonempty
+    /// group is recorded in the [`roots`](Runner::roots) field.
+    ///
+    /// # Example
+    /// ```
+    /// # use egg::*;
+    /// let runner = Runner::<SymbolLang, ()>::default().with_equiv_exprs(vec![
+    ///     vec!["(+ a b)".parse().unwrap(), "(add a b)".parse().unwrap()],
+    ///     vec!["(* a 2)".parse().unwrap(), "(shl a 1)".parse().unwrap()],
+    /// ]);
+    /// assert_eq!(runner.roots.len(), 2);
+    /// let add = runner.egraph.lookup_expr(&"(add a b)".parse().unwrap());
+    /// assert_eq!(add, Some(runner.egraph.find(runner.roots[0])));
+    /// ```
+    pub fn with_equiv_exprs(mut self, groups: Vec<Vec<RecExpr<L>>>) -> Self {
+        let mut pairs = vec![];
+        for group in groups {
+            let mut group = group.into_iter();
+            if let Some(first) = group.next() {
+                let id = self.egraph.add_expr(&first);
+                self.roots.push(id);
+                pairs.extend(group.map(|expr| (first.clone(), expr)));
+            }
+        }
+        self.egraph.assert_equalities(&pairs, "equiv-exprs");
+        self
+    }
+
+    /// Streams the statistics of each [`Iteration`] to `writer` as soon as
+    /// the iteration finishes, so a long run can be monitored while it
+    /// is still going.
+    ///
+    /// Each iteration is written as one line in the given
+    /// [`MetricsFormat`] and the writer is flushed after every line.
+    /// If writing fails, a warning is logged and streaming stops; the run
+    /// itself carries on.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let path = std::env::temp_dir().join(format!("egg-metrics-{}.csv", std::process::id()));
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[rw!("commute"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+    /// let runner = Runner::default()
+    ///     .with_metrics(std::fs::File::create(&path).unwrap(), MetricsFormat::Csv)
+    ///     .with_expr(&"(+ x y)".parse().unwrap())
+    ///     .run(rules);
+    ///
+    /// let csv = std::fs::read_to_string(&path).unwrap();
+    /// let lines: Vec<&str> = csv.lines().collect();
+    /// assert!(lines[0].starts_with("iteration,egraph_nodes,egraph_classes,"));
+    /// assert_eq!(lines.len(), runner.iterations.len() + 1);
+    /// assert!(lines[1].starts_with("0,3,3,"));
+    /// // the matched and applied columns
+    /// assert!(lines[1].ends_with(",commute=1,commute=1"));
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn with_metrics(
+        mut self,
+        writer: impl std::io::Write + 'static,
+        format: MetricsFormat,
+    ) -> Self {
+        self.metrics = Some(MetricsSink {
+            writer: Box::new(writer),
+            format,
+            wrote_header: false,
+        });
+        self
+    }
+
     /// Replace the [`EGraph`] of this `Runner`.
     pub fn with_egraph(self, egraph: EGraph<L, N>) -> Self {
         Self { egraph, ..self }
@@ -411,10 +961,17 @@ where
     {
         let rules: Vec<&Rewrite<L, N>> = rules.into_iter().collect();
         check_rules(&rules);
+        assert!(
+            self.parallel_search.is_none() || self.simple_scheduler,
+            "Parallel search only works with the SimpleScheduler"
+        );
+        self.rule_names.extend(rules.iter().map(|rw| rw.name));
         self.egraph.rebuild();
         loop {
             let iter = self.run_one(&rules);
+            self.write_metrics(&iter);
             self.iterations.push(iter);
+            self.warn_about_explosions();
             let stop_reason = self.iterations.last().unwrap().stop_reason.clone();
             // we need to check_limits after the iteration is complete to check for iter_limit
             if let Some(stop_reason) = stop_reason.or_else(|| self.check_limits().err()) {
@@ -429,6 +986,99 @@ where
         self
     }
 
+    /// Runs this `Runner` until `goal` matches one of the
+    /// [`roots`](Runner::roots), instead of until saturation.
+    ///
+    /// The roots are searched before the first iteration and after each
+    /// one; once `goal` matches, the run stops with
+    /// [`StopReason::Other`] and the first matching substitution is
+    /// returned.
+    /// If the run stops for another reason first, the result is `None`.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rw!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    /// ];
+    /// let goal: Pattern<SymbolLang> = "(+ (+ z ?a) y)".parse().unwrap();
+    /// let (runner, subst) = Runner::default()
+    ///     .with_expr(&"(+ x (+ y z))".parse().unwrap())
+    ///     .run_until(rules, &goal);
+    /// let subst = subst.unwrap();
+    /// assert_eq!(subst["?a".parse().unwrap()], runner.egraph.lookup(SymbolLang::leaf("x")).unwrap());
+    /// ```
+    pub fn run_until<'a, R>(mut self, rules: R, goal: &Pattern<L>) -> (Self, Option<Subst>)
+    where
+        R: IntoIterator<Item = &'a Rewrite<L, N>>,
+        L: 'static,
+        N: 'a,
+    {
+        let hook_goal = goal.clone();
+        let roots = self.roots.clone();
+        self = self.with_hook(move |runner| {
+            let found = roots
+                .iter()
+                .any(|&root| hook_goal.search_eclass(&runner.egraph, root).is_some());
+            if found {
+                Err("reached goal".into())
+            } else {
+                Ok(())
+            }
+        });
+        self = self.run(rules);
+        self.hooks.pop();
+
+        let subst = self.roots.iter().find_map(|&root| {
+            goal.search_eclass(&self.egraph, root)
+                .map(|mut m| m.substs.swap_remove(0))
+        });
+        (self, subst)
+    }
+
+    /// Runs ordered groups of rewrites one after the other, saturating each
+    /// group before moving on to the next with the same egraph.
+    ///
+    /// This is useful for staged rule sets, for example first normalizing,
+    /// then optimizing, and finally lowering.
+    /// The limits of this `Runner` apply to the run as a whole, so
+    /// [`iterations`](Runner::iterations) holds the iterations of all
+    /// phases and the time limit covers all of them.
+    /// If a phase stops for any reason other than
+    /// [`Saturated`](StopReason::Saturated), the later phases don't run
+    /// and [`stop_reason`](Runner::stop_reason) says why.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let normalize: &[Rewrite<SymbolLang, ()>] = &[rw!("sub-to-add"; "(- ?a ?b)" => "(+ ?a (neg ?b))")];
+    /// let optimize: &[Rewrite<SymbolLang, ()>] = &[rw!("add-neg"; "(+ ?a (neg ?a))" => "0")];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(- x x)".parse().unwrap())
+    ///     .run_phases(vec![normalize, optimize]);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// let zero = runner.egraph.lookup_expr(&"0".parse().unwrap());
+    /// assert_eq!(zero, Some(runner.egraph.find(runner.roots[0])));
+    /// ```
+    pub fn run_phases<'a, P, R>(mut self, phases: P) -> Self
+    where
+        P: IntoIterator<Item = R>,
+        R: IntoIterator<Item = &'a Rewrite<L, N>>,
+        L: 'a,
+        N: 'a,
+    {
+        for phase in phases {
+            self = self.run(phase);
+            match self.stop_reason {
+                Some(StopReason::Saturated) => self.stop_reason = None,
+                _ => return self,
+            }
+        }
+        self.stop_reason = Some(StopReason::Saturated);
+        self
+    }
+
     /// Enable explanations for this runner's egraph.
     /// This allows the runner to explain why two expressions are
     /// equivalent with the [`explain_equivalence`](Runner::explain_equivalence) function.
@@ -486,12 +1136,118 @@ where
         self.egraph.explain_matches(left, right, subst)
     }
 
+    /// Hands the best term of each root to an external simplifier, and unions
+    /// back in every result that can be verified.
+    ///
+    /// For each of the [`roots`](Runner::roots), this extracts the best term
+    /// according to `cost_function` and calls `simplifier` on it.
+    /// If the simplifier returns a new term, it is only trusted if `rules`
+    /// can prove it equal to the extracted term within `iter_limit`
+    /// iterations of a fresh [`Runner`] (which otherwise uses this runner's
+    /// node limit). Verified terms are added to the egraph and unioned with
+    /// their root, justified by `"external-simplifier"`.
+    ///
+    /// Returns the number of roots that were unioned with a verified term.
+    /// The egraph is rebuilt afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// # use egg::*;
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+    /// ];
+    ///
+    /// let mut runner = Runner::default()
+    ///     .with_expr(&"(+ 0 (+ x 0))".parse().unwrap())
+    ///     .with_iter_limit(1)
+    ///     .run(rules);
+    ///
+    /// // a "CAS" that knows how to drop every zero, and one that is wrong
+    /// let cas = |_: &RecExpr<SymbolLang>| Some("x".parse().unwrap());
+    /// let bad_cas = |_: &RecExpr<SymbolLang>| Some("y".parse().unwrap());
+    ///
+    /// assert_eq!(runner.reinsert_simplified(AstSize, rules, 5, bad_cas), 0);
+    /// assert_eq!(runner.reinsert_simplified(AstSize, rules, 5, cas), 1);
+    /// let x = runner.egraph.lookup_expr(&"x".parse().unwrap());
+    /// assert_eq!(x, Some(runner.egraph.find(runner.roots[0])));
+    /// ```
+    pub fn reinsert_simplified<CF, F>(
+        &mut self,
+        cost_function: CF,
+        rules: &[Rewrite<L, N>],
+        iter_limit: usize,
+        mut simplifier: F,
+    ) -> usize
+    where
+        CF: CostFunction<L>,
+        F: FnMut(&RecExpr<L>) -> Option<RecExpr<L>>,
+        N: Default,
+    {
+        let best: Vec<(Id, RecExpr<L>)> = {
+            let extractor = Extractor::new(&self.egraph, cost_function);
+            let best = |&root: &Id| (root, extractor.find_best(root).1);
+            self.roots.iter().map(best).collect()
+        };
+
+        let mut n_unions = 0;
+        for (root, expr) in best {
+            let simplified = match simplifier(&expr) {
+                Some(simplified) if simplified != expr => simplified,
+                _ => continue,
+            };
+
+            let same = |r: &Runner<L, N>| r.egraph.find(r.roots[0]) == r.egraph.find(r.roots[1]);
+            let verifier = Runner::<L, N>::new(N::default())
+                .with_iter_limit(iter_limit)
+                .with_node_limit(self.node_limit)
+                .with_time_limit(self.time_limit)
+                .with_expr(&expr)
+                .with_expr(&simplified)
+                .with_hook(move |r| {
+                    if same(r) {
+                        Err("verified".into())
+                    } else {
+                        Ok(())
+                    }
+                })
+                .run(rules);
+
+            if same(&verifier) {
+                debug!("Verified external simplification of {}", root);
+                let id = self.egraph.add_expr(&simplified);
+                n_unions += self.egraph.union_trusted(root, id, "external-simplifier") as usize;
+            } else {
+                debug!("Could not verify external simplification of {}", root);
+            }
+        }
+
+        self.egraph.rebuild();
+        n_unions
+    }
+
     /// Prints some information about a runners run.
     pub fn print_report(&self) {
         println!("{}", self.report())
     }
 
     /// Creates a [`Report`] summarizing this `Runner`s run.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("add-0"; "(+ ?a 0)" => "?a"),
+    ///     rw!("mul-0"; "(* ?a 0)" => "0"),
+    ///     rw!("add-self"; "(+ ?a ?a)" => "(+ ?a ?a)"),
+    /// ];
+    /// let runner = Runner::default()
+    ///     .with_expr(&"(+ (+ x 0) (+ x 0))".parse().unwrap())
+    ///     .run(rules);
+    /// let report = runner.report();
+    /// assert_eq!(report.unmatched_rules, [Symbol::from("mul-0")]);
+    /// assert_eq!(report.unproductive_rules, [Symbol::from("add-self")]);
+    /// ```
     pub fn report(&self) -> Report {
         Report {
             stop_reason: self.stop_reason.clone().unwrap(),
@@ -504,6 +1260,68 @@ where
             apply_time: self.iterations.iter().map(|i| i.apply_time).sum(),
             rebuild_time: self.iterations.iter().map(|i| i.rebuild_time).sum(),
             total_time: self.iterations.iter().map(|i| i.total_time).sum(),
+            worsening_rules: self
+                .rule_costs
+                .iter()
+                .filter(|(_, audit)| audit.only_worse())
+                .map(|(name, _)| *name)
+                .collect(),
+            unmatched_rules: self
+                .rule_names
+                .iter()
+                .filter(|name| {
+                    !self
+                        .iterations
+                        .iter()
+                        .any(|i| i.matched.contains_key(*name))
+                })
+                .copied()
+                .collect(),
+            unproductive_rules: self
+                .rule_names
+                .iter()
+                .filter(|name| {
+                    self.iterations
+                        .iter()
+                        .any(|i| i.matched.contains_key(*name))
+                        && !self
+                            .iterations
+                            .iter()
+                            .any(|i| i.applied.contains_key(*name))
+                })
+                .copied()
+                .collect(),
+            exploding_rules: self
+                .rule_names
+                .iter()
+                .filter_map(|&name| RuleExplosion::detect(name, self.match_counts(name)))
+                .collect(),
+        }
+    }
+
+    /// The number of matches of the rule named `name` in each iteration.
+    fn match_counts(&self, name: Symbol) -> Vec<usize> {
+        self.iterations
+            .iter()
+            .map(|i| i.matched.get(&name).copied().unwrap_or(0))
+            .collect()
+    }
+
+    fn warn_about_explosions(&self) {
+        let n = self.iterations.len();
+        for &name in &self.rule_names {
+            let matches = self.match_counts(name);
+            if RuleExplosion::is_exploding_at(&matches, n)
+                && !RuleExplosion::is_exploding_at(&matches, n - 1)
+            {
+                warn!(
+                    "{}",
+                    RuleExplosion {
+                        rule: name,
+                        matches
+                    }
+                );
+            }
         }
     }
 
@@ -513,6 +1331,7 @@ where
         info!("\nIteration {}", self.iterations.len());
 
         self.try_start();
+        self.egraph.set_stamp(self.iterations.len());
         let mut result = self.check_limits();
 
         let egraph_nodes = self.egraph.total_size();
@@ -534,25 +1353,72 @@ where
         let i = self.iterations.len();
         trace!("EGraph {:?}", self.egraph.dump());
 
+        let enabled_rules: Vec<&Rewrite<L, N>>;
+        let rules = if self.disabled_rules.is_empty() {
+            rules
+        } else {
+            let disabled = &self.disabled_rules;
+            enabled_rules = rules
+                .iter()
+                .copied()
+                .filter(|rw| !disabled.contains(&rw.name))
+                .collect();
+            &enabled_rules
+        };
+
         let start_time = Instant::now();
 
         let mut matches = Vec::new();
         let mut applied = IndexMap::default();
-        result = result.and_then(|_| {
-            rules.iter().try_for_each(|rw| {
+        result = result.and_then(|_| match self.parallel_search {
+            Some(search) => {
+                matches = search(&self.egraph, rules);
+                self.check_limits()
+            }
+            None => rules.iter().try_for_each(|rw| {
                 let ms = self.scheduler.search_rewrite(i, &self.egraph, rw);
                 matches.push(ms);
                 self.check_limits()
-            })
+            }),
         });
 
         let search_time = start_time.elapsed().as_secs_f64();
         info!("Search time: {}", search_time);
 
+        let mut matched = IndexMap::default();
+        for (rw, ms) in rules.iter().zip(&matches) {
+            let n: usize = ms.iter().map(|m| m.substs.len()).sum();
+            if n > 0 {
+                *matched.entry(rw.name).or_insert(0) += n;
+            }
+        }
+
+        if let Some(audit) = self.cost_audit.as_mut() {
+            let audits = audit(&self.egraph, rules, &matches);
+            for (rw, counts) in rules.iter().zip(audits) {
+                let total = self.rule_costs.entry(rw.name).or_default();
+                total.not_worse += counts.not_worse;
+                total.worse += counts.worse;
+            }
+        }
+
         let apply_time = Instant::now();
 
         result = result.and_then(|_| {
-            rules.iter().zip(matches).try_for_each(|(rw, ms)| {
+            rules.iter().zip(matches).try_for_each(|(rw, mut ms)| {
+                if let (Some((limit, depth)), Some(ast)) =
+                    (self.depth_limit, rw.applier.get_pattern_ast())
+                {
+                    for m in ms.iter_mut() {
+                        m.substs.retain(|subst| {
+                            instantiated_depth(&self.egraph, ast, subst, depth) <= limit
+                        });
+                    }
+                    ms.retain(|m| !m.substs.is_empty());
+                }
+                if let Some(&limit) = self.application_limits.get(&rw.name) {
+                    limit_matches(&mut ms, limit);
+                }
                 let total_matches: usize = ms.iter().map(|m| m.substs.len()).sum();
                 debug!("Applying {} {} times", rw.name, total_matches);
 
@@ -602,6 +1468,7 @@ where
 
         Iteration {
             applied,
+            matched,
             egraph_nodes,
             egraph_classes,
             hook_time,
@@ -615,6 +1482,15 @@ where
         }
     }
 
+    fn write_metrics(&mut self, iter: &Iteration<IterData>) {
+        if let Some(sink) = self.metrics.as_mut() {
+            if let Err(err) = sink.write(self.iterations.len(), iter) {
+                warn!("Failed to write runner metrics, disabling them: {}", err);
+                self.metrics = None;
+            }
+        }
+    }
+
     fn try_start(&mut self) {
         self.start_time.get_or_insert_with(Instant::now);
     }
@@ -625,7 +1501,7 @@ where
             return Err(StopReason::TimeLimit(elapsed.as_secs_f64()));
         }
 
-        let size = self.egraph.total_size();
+        let size = self.size_metric.measure(&self.egraph);
         if size > self.node_limit {
             return Err(StopReason::NodeLimit(size));
         }
@@ -638,6 +1514,194 @@ where
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<L, N, IterData> Runner<L, N, IterData>
+where
+    L: Language + Send + Sync,
+    N: Analysis<L> + Sync,
+    N::Data: Sync,
+    IterData: IterationData<L, N>,
+{
+    /// Searches all rules in parallel at each iteration using
+    /// [`rayon`](https://docs.rs/rayon).
+    ///
+    /// The egraph is only read during the search, so every rule can be
+    /// searched on its own core.
+    /// The matches are then applied one rule at a time, followed by a single
+    /// [`rebuild`](EGraph::rebuild()), just like the sequential runner.
+    ///
+    /// A scheduler's [`search_rewrite`](RewriteScheduler::search_rewrite())
+    /// needs to see the rules one at a time, so this only works with the
+    /// [`SimpleScheduler`], which searches every enabled rule every
+    /// iteration; [`run`](Runner::run()) panics with any other scheduler.
+    /// The limits are only checked once all rules have been searched.
+    ///
+    /// Requires the `parallel` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{rewrite as rw, *};
+    /// let rules: &[Rewrite<SymbolLang, ()>] = &[
+    ///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rw!("add-0"; "(+ ?a 0)" => "?a"),
+    /// ];
+    /// let runner = Runner::default()
+    ///     .with_scheduler(SimpleScheduler)
+    ///     .with_parallel_search()
+    ///     .with_expr(&"(+ 0 (+ x y))".parse().unwrap())
+    ///     .run(rules);
+    /// let root = runner.egraph.find(runner.roots[0]);
+    /// assert_eq!(runner.egraph.lookup_expr(&"(+ y x)".parse().unwrap()), Some(root));
+    /// ```
+    pub fn with_parallel_search(mut self) -> Self {
+        self.parallel_search = Some(search_in_parallel::<L, N>);
+        self
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn search_in_parallel<'a, L, N>(
+    egraph: &EGraph<L, N>,
+    rules: &[&'a Rewrite<L, N>],
+) -> Vec<Vec<SearchMatches<'a, L>>>
+where
+    L: Language + Send + Sync,
+    N: Analysis<L> + Sync,
+    N::Data: Sync,
+{
+    use rayon::prelude::*;
+    rules.par_iter().map(|rw| rw.search(egraph)).collect()
+}
+
+/// Checks whether `a` and `b` can be proven equal by running `rules`.
+///
+/// Both expressions are added to the egraph of `runner`, which is then run
+/// with `rules` until the two are in the same e-class or one of the runner's
+/// limits is hit.
+/// So configure `runner` with the limits (and analysis) you want;
+/// returning `false` only means that no proof was found within them.
+///
+/// # Example
+/// ```
+/// use egg::{rewrite as rw, *};
+/// let rules: &[Rewrite<SymbolLang, ()>] = &[
+///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rw!("add-0"; "(+ ?a 0)" => "?a"),
+/// ];
+/// let a = "(+ 0 (+ x y))".parse().unwrap();
+/// let b = "(+ y x)".parse().unwrap();
+/// let c = "(+ y y)".parse().unwrap();
+/// assert!(prove_equal(rules, &a, &b, Runner::default()));
+/// assert!(!prove_equal(rules, &a, &c, Runner::default().with_iter_limit(5)));
+/// ```
+pub fn prove_equal<L, N, IterData>(
+    rules: &[Rewrite<L, N>],
+    a: &RecExpr<L>,
+    b: &RecExpr<L>,
+    runner: Runner<L, N, IterData>,
+) -> bool
+where
+    L: Language + 'static,
+    N: Analysis<L> + 'static,
+    IterData: IterationData<L, N> + 'static,
+{
+    let runner = runner.with_expr(a).with_expr(b);
+    let (id_a, id_b) = (
+        runner.roots[runner.roots.len() - 2],
+        *runner.roots.last().unwrap(),
+    );
+    let runner = runner
+        .with_hook(move |runner| {
+            if runner.egraph.find(id_a) == runner.egraph.find(id_b) {
+                Err("proved equal".into())
+            } else {
+                Ok(())
+            }
+        })
+        .run(rules);
+    runner.egraph.find(id_a) == runner.egraph.find(id_b)
+}
+
+/// Keeps at most `limit` substitutions in `matches`, preferring those in
+/// e-classes with lower ids.
+fn limit_matches<L: Language>(matches: &mut Vec<SearchMatches<L>>, limit: usize) {
+    matches.sort_by_key(|m| m.eclass);
+    let mut remaining = limit;
+    for m in matches.iter_mut() {
+        m.substs.truncate(remaining);
+        remaining -= m.substs.len();
+    }
+    matches.retain(|m| !m.substs.is_empty());
+}
+
+fn instantiated_depth<L: Language, N: Analysis<L>>(
+    egraph: &EGraph<L, N>,
+    ast: &PatternAst<L>,
+    subst: &Subst,
+    depth: ClassDepth<L, N>,
+) -> usize {
+    let mut depths: Vec<usize> = Vec::with_capacity(ast.as_ref().len());
+    for node in ast.as_ref() {
+        let d = match node {
+            // a segment variable is as deep as the deepest e-class it binds
+            ENodeOrVar::Var(v) if v.is_segment() => subst
+                .get_segment(*v)
+                .unwrap_or_default()
+                .iter()
+                .map(|&id| depth(egraph, id))
+                .max()
+                .unwrap_or(0),
+            ENodeOrVar::Var(v) => depth(egraph, subst[*v]),
+            ENodeOrVar::ENode(n) => {
+                n.fold(0, |max: usize, id| max.max(depths[usize::from(id)])) + 1
+            }
+        };
+        depths.push(d);
+    }
+    *depths.last().unwrap()
+}
+
+fn audit_rule_costs<L, N, CF>(
+    egraph: &EGraph<L, N>,
+    cost_function: &mut CF,
+    rules: &[&Rewrite<L, N>],
+    matches: &[Vec<SearchMatches<L>>],
+) -> Vec<RuleCostAudit>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: CostFunction<L>,
+{
+    let mut extractor = Extractor::new(egraph, cost_function);
+    rules
+        .iter()
+        .zip(matches)
+        .map(|(rw, ms)| {
+            let mut audit = RuleCostAudit::default();
+            let rhs = match rw.applier.get_pattern_ast() {
+                Some(rhs) => rhs,
+                None => return audit,
+            };
+            for m in ms {
+                let lhs_cost = match extractor.try_find_best_cost(m.eclass) {
+                    Some(cost) => cost.clone(),
+                    None => continue,
+                };
+                for subst in &m.substs {
+                    if let Some(rhs_cost) = extractor.instantiation_cost(rhs, subst) {
+                        if rhs_cost > lhs_cost {
+                            audit.worse += 1;
+                        } else {
+                            audit.not_worse += 1;
+                        }
+                    }
+                }
+            }
+            audit
+        })
+        .collect()
+}
+
 fn check_rules<L, N>(rules: &[&Rewrite<L, N>]) {
     let mut name_counts = IndexMap::default();
     for rw in rules {
@@ -730,6 +1794,185 @@ where
 {
 }
 
+/// A [`RewriteScheduler`] that only searches e-classes that may have new
+/// matches since a rewrite was last searched.
+///
+/// The egraph keeps track of when each e-class was created or last merged.
+/// A [`Pattern`] only looks at the e-class it is rooted in and a bounded
+/// number of levels below it, so its new matches can only be rooted in the
+/// e-classes that changed since the last search, or in their ancestors
+/// up to the height of the pattern.
+/// This scheduler searches only those e-classes, which avoids rediscovering
+/// the same matches over and over in large egraphs where each iteration
+/// changes only a small part.
+///
+/// Rewrites are searched in full the first time, and always if their
+/// [`Searcher`] isn't a [`Pattern`], since other searchers may look
+/// anywhere in the egraph.
+/// Like [`SimpleScheduler`], this never bans rules, so every match found is
+/// applied.
+/// Conditions on a rewrite are only checked when a match is found,
+/// so don't use this scheduler with conditions that can go from `false` to
+/// `true` without the matched e-classes changing.
+///
+/// This is not the default scheduler; choose it with the
+/// [`with_scheduler`](Runner::with_scheduler())
+/// method.
+///
+/// # Example
+/// ```
+/// use egg::{rewrite as rw, *};
+/// let rules: &[Rewrite<SymbolLang, ()>] = &[
+///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rw!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+/// ];
+/// let start = "(+ a (+ b (+ c d)))".parse().unwrap();
+/// let full = Runner::default()
+///     .with_scheduler(SimpleScheduler)
+///     .with_expr(&start)
+///     .run(rules);
+/// let incremental = Runner::default()
+///     .with_scheduler(IncrementalScheduler::default())
+///     .with_expr(&start)
+///     .run(rules);
+/// assert_eq!(full.egraph.total_size(), incremental.egraph.total_size());
+/// assert_eq!(
+///     full.egraph.number_of_classes(),
+///     incremental.egraph.number_of_classes()
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct IncrementalScheduler {
+    last_searched: IndexMap<Symbol, usize>,
+}
+
+impl<L, N> RewriteScheduler<L, N> for IncrementalScheduler
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    fn search_rewrite<'a>(
+        &mut self,
+        _iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &'a Rewrite<L, N>,
+    ) -> Vec<SearchMatches<'a, L>> {
+        let last = self.last_searched.insert(rewrite.name, egraph.version);
+        let ast = rewrite.searcher.get_pattern_ast();
+        match (last, ast) {
+            (Some(version), Some(ast)) => {
+                let ids =
+                    egraph.classes_modified_since(version, crate::pattern::pattern_reach(ast));
+                debug!(
+                    "Searching {} in {} changed e-classes",
+                    rewrite.name,
+                    ids.len()
+                );
+                crate::rewrite::search_eclasses_with_limit(
+                    &*rewrite.searcher,
+                    egraph,
+                    ids,
+                    usize::MAX,
+                )
+            }
+            _ => rewrite.search(egraph),
+        }
+    }
+}
+
+/// A [`RewriteScheduler`] that applies at most a fixed number of matches of
+/// each rewrite per iteration, preferring the matches rooted in e-classes
+/// with recently added e-nodes.
+///
+/// Every e-node is [stamped](EGraph::stamp()) with the iteration at which
+/// it was added, so this focuses the effort on the frontier of the egraph,
+/// rather than re-deriving facts about old terms over and over.
+/// Matches rooted in e-classes whose [newest e-node](EClass::newest_stamp())
+/// is equally recent are kept in search order.
+/// Unlike [`BackoffScheduler`], this never bans rules, but the runner won't
+/// stop as saturated after an iteration that dropped matches.
+///
+/// This is not the default scheduler; choose it with the
+/// [`with_scheduler`](Runner::with_scheduler())
+/// method.
+///
+/// # Example
+/// ```
+/// use egg::{rewrite as rw, *};
+/// let rules: &[Rewrite<SymbolLang, ()>] = &[
+///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rw!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+/// ];
+/// let runner = Runner::default()
+///     .with_scheduler(RecencyScheduler::default().with_match_limit(4))
+///     .with_expr(&"(+ a (+ b (+ c d)))".parse().unwrap())
+///     .with_iter_limit(3)
+///     .run(rules);
+/// for iteration in &runner.iterations {
+///     assert!(iteration.applied.values().all(|&n| n <= 4));
+/// }
+/// // the e-nodes added in the last iteration have its index as stamp
+/// assert!(runner.egraph.classes().any(|c| c.newest_stamp() == 2));
+/// ```
+#[derive(Debug)]
+pub struct RecencyScheduler {
+    match_limit: usize,
+    /// The last iteration in which some matches were dropped.
+    truncated_at: Option<usize>,
+}
+
+impl Default for RecencyScheduler {
+    fn default() -> Self {
+        Self {
+            match_limit: 1_000,
+            truncated_at: None,
+        }
+    }
+}
+
+impl RecencyScheduler {
+    /// Sets the number of matches applied per rewrite and iteration.
+    /// Default: 1,000
+    pub fn with_match_limit(self, match_limit: usize) -> Self {
+        Self {
+            match_limit,
+            ..self
+        }
+    }
+}
+
+impl<L, N> RewriteScheduler<L, N> for RecencyScheduler
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    fn can_stop(&mut self, iteration: usize) -> bool {
+        // the dropped matches may still change the egraph
+        self.truncated_at != Some(iteration)
+    }
+
+    fn search_rewrite<'a>(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &'a Rewrite<L, N>,
+    ) -> Vec<SearchMatches<'a, L>> {
+        let mut matches = rewrite.search(egraph);
+        matches.sort_by_key(|m| std::cmp::Reverse(egraph[m.eclass].newest_stamp()));
+        let total: usize = matches.iter().map(|m| m.substs.len()).sum();
+        if total > self.match_limit {
+            self.truncated_at = Some(iteration);
+        }
+        let mut remaining = self.match_limit;
+        for m in matches.iter_mut() {
+            m.substs.truncate(remaining);
+            remaining -= m.substs.len();
+        }
+        matches.retain(|m| !m.substs.is_empty());
+        matches
+    }
+}
+
 /// A [`RewriteScheduler`] that implements exponentional rule backoff.
 ///
 /// For each rewrite, there exists a configurable initial match limit.
@@ -933,3 +2176,104 @@ where
 {
     fn make(_: &Runner<L, N, Self>) -> Self {}
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn detects_geometric_growth() {
+        let name = Symbol::from("assoc");
+        let explosion = RuleExplosion::detect(name, vec![1, 2, 3, 7, 15, 31, 40]);
+        assert_eq!(
+            explosion.map(|e| e.matches),
+            Some(vec![1, 2, 3, 7, 15, 31, 40])
+        );
+        assert_eq!(RuleExplosion::detect(name, vec![4, 8, 16, 20, 40]), None);
+        assert_eq!(RuleExplosion::detect(name, vec![0, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    #[should_panic = "only works with the SimpleScheduler"]
+    fn parallel_search_needs_simple_scheduler() {
+        let rules: &[Rewrite<SymbolLang, ()>] = &[rewrite!("comm"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+        Runner::default()
+            .with_parallel_search()
+            .with_expr(&"(+ x y)".parse().unwrap())
+            .run(rules);
+    }
+
+    #[test]
+    fn depth_limit_counts_segments() {
+        let rules: &[Rewrite<SymbolLang, MinDepth>] =
+            &[rewrite!("drop-first"; "(f ?a ?xs...)" => "(k ?xs...)")];
+        let start = "(f a (g (g b)))".parse().unwrap();
+        let rhs = "(k (g (g b)))".parse().unwrap();
+        for &(limit, applied) in &[(3, false), (4, true)] {
+            let runner = Runner::default()
+                .with_expr(&start)
+                .with_depth_limit(limit, |egraph, id| egraph[id].data)
+                .run(rules);
+            assert_eq!(runner.egraph.lookup_expr(&rhs).is_some(), applied);
+        }
+    }
+
+    #[test]
+    fn recency_scheduler_cant_stop_after_dropping_matches() {
+        let rule: Rewrite<SymbolLang, ()> = rewrite!("wrap"; "(f ?a)" => "(g ?a)");
+        let mut egraph = EGraph::default();
+        egraph.add_expr(&"(f a)".parse().unwrap());
+        egraph.add_expr(&"(f b)".parse().unwrap());
+        egraph.rebuild();
+
+        let mut scheduler = RecencyScheduler::default().with_match_limit(1);
+        let matches = scheduler.search_rewrite(0, &egraph, &rule);
+        assert_eq!(matches.len(), 1);
+        assert!(!RewriteScheduler::<SymbolLang, ()>::can_stop(
+            &mut scheduler,
+            0
+        ));
+
+        let mut scheduler = RecencyScheduler::default().with_match_limit(2);
+        scheduler.search_rewrite(0, &egraph, &rule);
+        assert!(RewriteScheduler::<SymbolLang, ()>::can_stop(
+            &mut scheduler,
+            0
+        ));
+    }
+
+    #[test]
+    fn metrics_count_matches_and_applications() {
+        use std::{cell::RefCell, io, rc::Rc};
+
+        #[derive(Clone, Default)]
+        struct Buffer(Rc<RefCell<Vec<u8>>>);
+        impl io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        fn never(_: &mut EGraph<SymbolLang, ()>, _: Id, _: &Subst) -> bool {
+            false
+        }
+
+        let rules: &[Rewrite<SymbolLang, ()>] = &[
+            rewrite!("comm"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("never"; "(+ ?a ?b)" => "?a" if never),
+        ];
+        let buffer = Buffer::default();
+        Runner::default()
+            .with_metrics(buffer.clone(), MetricsFormat::JsonLines)
+            .with_expr(&"(+ x y)".parse().unwrap())
+            .run(rules);
+        let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let first = output.lines().next().unwrap();
+        assert!(first.starts_with("{\"iteration\":0,\"egraph_nodes\":3,"));
+        assert!(first.ends_with(",\"matched\":{\"comm\":1,\"never\":1},\"applied\":{\"comm\":1}}"));
+    }
+}
@@ -0,0 +1,100 @@
+/*!
+Sampling random terms from an [`EGraph`].
+
+See [`EGraph::sample`].
+!*/
+
+use rand::Rng;
+
+use crate::*;
+
+impl<L: Language, N: Analysis<L>> EGraph<L, N> {
+    /// Picks a random term of depth at most `max_depth` represented by an
+    /// eclass, or returns `None` if it has no such term.
+    ///
+    /// Leaves have depth 1, like with [`AstDepth`].
+    /// Starting at the eclass, an enode is picked uniformly among those
+    /// that fit in the remaining depth, and so on for its children.
+    /// That makes the sample uniform over the choices rather than over the
+    /// terms, since the terms of bigger enodes are less likely, but it only
+    /// takes a pass over the eclasses reachable from `id`.
+    ///
+    /// Requires the `rand` feature.
+    ///
+    /// # Panics
+    /// Panics if the egraph isn't [`clean`](EGraph::clean).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut egraph = EGraph::<SymbolLang, ()>::default();
+    /// let x = egraph.add_expr(&"x".parse().unwrap());
+    /// let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// egraph.union(x, fx);
+    /// egraph.rebuild();
+    ///
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    /// for _ in 0..10 {
+    ///     let term = egraph.sample(x, &mut rng, 3).unwrap();
+    ///     assert!(["x", "(f x)", "(f (f x))"].contains(&term.to_string().as_str()));
+    ///     assert_eq!(egraph.lookup_expr(&term), Some(egraph.find(x)));
+    /// }
+    /// assert_eq!(egraph.sample(x, &mut rng, 0), None);
+    /// ```
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        id: Id,
+        rng: &mut R,
+        max_depth: usize,
+    ) -> Option<RecExpr<L>> {
+        assert!(self.clean, "Must rebuild the egraph before sampling");
+        let depths = self.min_depths(id);
+        let mut expr = RecExpr::default();
+        self.sample_into(&mut expr, &depths, id, rng, max_depth)?;
+        Some(expr)
+    }
+
+    /// The depth of the shallowest term of each eclass reachable from `id`
+    /// that has a finite term, according to [`AstDepth`].
+    fn min_depths(&self, id: Id) -> HashMap<Id, usize> {
+        let reachable = self.descendants(&[id], usize::MAX);
+        let constraints = ExtractionConstraints::default();
+        crate::extract::find_costs(self, &reachable, &mut AstDepth, &constraints)
+            .into_iter()
+            .map(|(id, (depth, _))| (id, depth))
+            .collect()
+    }
+
+    fn sample_into<R: Rng + ?Sized>(
+        &self,
+        expr: &mut RecExpr<L>,
+        depths: &HashMap<Id, usize>,
+        id: Id,
+        rng: &mut R,
+        max_depth: usize,
+    ) -> Option<Id> {
+        if max_depth == 0 {
+            return None;
+        }
+        let fits = |node: &&L| {
+            node.all(|child| {
+                depths
+                    .get(&self.find(child))
+                    .map_or(false, |&d| d < max_depth)
+            })
+        };
+        let candidates: Vec<&L> = self[id].iter().filter(fits).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let node = candidates[rng.gen_range(0..candidates.len())];
+        let mut children = vec![];
+        for &child in node.children() {
+            children.push(self.sample_into(expr, depths, child, rng, max_depth - 1)?);
+        }
+        let mut children = children.into_iter();
+        Some(expr.add(node.clone().map_children(|_| children.next().unwrap())))
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::Debug;
+
+use crate::*;
+
+/** A [`Language`] whose e-nodes have sorts (types).
+
+Implementing this trait gives your language an optional typing layer:
+[`SortAnalysis`] tracks the sort of every e-class and, in debug builds,
+panics as soon as an ill-sorted e-node is added or two e-classes of
+different sorts are merged, so a bad rewrite is caught where it fires.
+The [`has_sort`] and [`well_sorted`] conditions let rewrites check sorts
+before they apply, and a pattern variable annotated with a sort, like
+`?a:Int`, only matches e-classes of that sort (see [`Sorted::parse_sort`]).
+
+# Example
+```
+use egg::{rewrite as rw, *};
+
+define_language! {
+    enum Lang {
+        "true" = True,
+        "+" = Add([Id; 2]),
+        "<" = Lt([Id; 2]),
+        "if" = If([Id; 3]),
+        Num(i32),
+        Symbol(Symbol),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Sort {
+    Int,
+    Bool,
+}
+
+impl Sorted for Lang {
+    type Sort = Sort;
+    fn sort(&self, children: &[Sort]) -> Option<Sort> {
+        match (self, children) {
+            (Lang::True, []) => Some(Sort::Bool),
+            (Lang::Num(_), []) | (Lang::Symbol(_), []) => Some(Sort::Int),
+            (Lang::Add(_), [Sort::Int, Sort::Int]) => Some(Sort::Int),
+            (Lang::Lt(_), [Sort::Int, Sort::Int]) => Some(Sort::Bool),
+            (Lang::If(_), [Sort::Bool, a, b]) if a == b => Some(a.clone()),
+            _ => None,
+        }
+    }
+    fn parse_sort(name: &str) -> Option<Sort> {
+        match name {
+            "Int" => Some(Sort::Int),
+            "Bool" => Some(Sort::Bool),
+            _ => None,
+        }
+    }
+}
+
+let rules: &[Rewrite<Lang, SortAnalysis>] = &[
+    rw!("if-true"; "(if true ?a ?b)" => "?a"),
+    rw!("if-same"; "(if ?c ?a:Int ?a)" => "?a"),
+    rw!("add-comm"; "(+ ?a ?b)" => "(+ ?b ?a)" if has_sort("?a", Sort::Int)),
+];
+let runner = Runner::default()
+    .with_expr(&"(if true (+ x 1) 2)".parse().unwrap())
+    .with_expr(&"(if (< x 1) y y)".parse().unwrap())
+    .with_expr(&"(if (< x 1) true true)".parse().unwrap())
+    .run(rules);
+let egraph = &runner.egraph;
+let root = egraph.find(runner.roots[0]);
+assert_eq!(egraph[root].data, Some(Sort::Int));
+assert_eq!(egraph.lookup_expr(&"(+ 1 x)".parse().unwrap()), Some(root));
+assert_eq!(egraph.lookup_expr(&"y".parse().unwrap()), Some(egraph.find(runner.roots[1])));
+// `?a:Int` doesn't match `true`
+assert_eq!(egraph[runner.roots[2]].len(), 1);
+```
+**/
+pub trait Sorted: Language {
+    /// The sorts of this language.
+    type Sort: Debug + Clone + PartialEq;
+
+    /// Returns the sort of this e-node given the sorts of its children,
+    /// or `None` if the e-node is ill-sorted.
+    fn sort(&self, children: &[Self::Sort]) -> Option<Self::Sort>;
+
+    /// Parses a sort annotation on a pattern variable, like the `Int` in
+    /// `?a:Int`, or returns `None` if `name` isn't a sort.
+    ///
+    /// [`SortAnalysis`] uses this to check those annotations during search.
+    /// By default no sort can be parsed, so annotated variables never match.
+    #[allow(unused_variables)]
+    fn parse_sort(name: &str) -> Option<Self::Sort> {
+        None
+    }
+}
+
+/** An [`Analysis`] tracking the sort of each e-class of a [`Sorted`]
+language.
+
+The data of an e-class is its sort, or `None` if it is ill-sorted.
+A pattern variable annotated with a sort, as in `?a:Int`, only matches
+e-classes of that sort, see [`Sorted::parse_sort`].
+In debug builds, adding an ill-sorted e-node or merging e-classes of
+different sorts panics instead.
+
+See [`has_sort`] and [`well_sorted`] for [`Condition`]s using this
+analysis.
+*/
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SortAnalysis;
+
+impl<L: Sorted> Analysis<L> for SortAnalysis {
+    type Data = Option<L::Sort>;
+
+    fn make(egraph: &EGraph<L, Self>, enode: &L) -> Self::Data {
+        let children: Vec<L::Sort> = enode
+            .children()
+            .iter()
+            .map(|&id| egraph[id].data.clone())
+            .collect::<Option<_>>()?;
+        let sort = enode.sort(&children);
+        if cfg!(debug_assertions) && sort.is_none() {
+            panic!(
+                "Ill-sorted e-node {:?} with children of sorts {:?}",
+                enode, children
+            );
+        }
+        sort
+    }
+
+    fn merge(&mut self, to: &mut Self::Data, from: Self::Data) -> DidMerge {
+        merge_option(to, from, |a, b| {
+            debug_assert_eq!(*a, b, "Merged e-classes of different sorts");
+            DidMerge(false, *a != b)
+        })
+    }
+
+    fn check_constraint(egraph: &EGraph<L, Self>, eclass: Id, constraint: Symbol) -> bool {
+        match L::parse_sort(constraint.as_str()) {
+            Some(sort) => egraph[eclass].data.as_ref() == Some(&sort),
+            None => false,
+        }
+    }
+}
+
+/// A [`Condition`] that holds if the e-class bound to `var` has sort
+/// `sort`, according to [`SortAnalysis`].
+pub fn has_sort<L: Sorted>(
+    var: &str,
+    sort: L::Sort,
+) -> impl Fn(&mut EGraph<L, SortAnalysis>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| egraph[subst[var]].data.as_ref() == Some(&sort)
+}
+
+/// A [`Condition`] that holds if instantiating `pattern` gives a
+/// well-sorted term of the same sort as the matched e-class.
+///
+/// Checking this doesn't add anything to the egraph, so put the
+/// right-hand side of a rewrite here to make sure the rewrite never
+/// creates ill-sorted terms.
+pub fn well_sorted<L: Sorted + FromOp>(
+    pattern: &str,
+) -> impl Fn(&mut EGraph<L, SortAnalysis>, Id, &Subst) -> bool {
+    let pattern: Pattern<L> = pattern.parse().unwrap();
+    move |egraph, eclass, subst| {
+        let mut sorts: Vec<L::Sort> = Vec::with_capacity(pattern.ast.as_ref().len());
+        for node in pattern.ast.as_ref() {
+            let sort = match node {
+                ENodeOrVar::Var(v) => egraph[subst[*v]].data.clone(),
+                ENodeOrVar::ENode(n) => {
+                    let children: Vec<L::Sort> = n
+                        .children()
+                        .iter()
+                        .map(|&c| sorts[usize::from(c)].clone())
+                        .collect();
+                    n.sort(&children)
+                }
+            };
+            match sort {
+                Some(sort) => sorts.push(sort),
+                None => return false,
+            }
+        }
+        sorts.last() == egraph[eclass].data.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    define_language! {
+        enum Lang {
+            "true" = True,
+            "not" = Not(Id),
+            Num(i32),
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Sort {
+        Int,
+        Bool,
+    }
+
+    impl Sorted for Lang {
+        type Sort = Sort;
+        fn sort(&self, children: &[Sort]) -> Option<Sort> {
+            match (self, children) {
+                (Lang::True, []) => Some(Sort::Bool),
+                (Lang::Not(_), [Sort::Bool]) => Some(Sort::Bool),
+                (Lang::Num(_), []) => Some(Sort::Int),
+                _ => None,
+            }
+        }
+        fn parse_sort(name: &str) -> Option<Sort> {
+            match name {
+                "Int" => Some(Sort::Int),
+                "Bool" => Some(Sort::Bool),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn sorts_and_conditions() {
+        let mut egraph = EGraph::<Lang, SortAnalysis>::default();
+        let t = egraph.add_expr(&"(not true)".parse().unwrap());
+        let one = egraph.add_expr(&"1".parse().unwrap());
+        egraph.rebuild();
+        assert_eq!(egraph[t].data, Some(Sort::Bool));
+        assert_eq!(egraph[one].data, Some(Sort::Int));
+
+        let subst: Subst = vec![("?a".parse().unwrap(), one)].into_iter().collect();
+        assert!(has_sort::<Lang>("?a", Sort::Int)(&mut egraph, one, &subst));
+        assert!(!has_sort::<Lang>("?a", Sort::Bool)(
+            &mut egraph,
+            one,
+            &subst
+        ));
+        assert!(well_sorted::<Lang>("?a")(&mut egraph, one, &subst));
+        assert!(!well_sorted::<Lang>("(not ?a)")(&mut egraph, one, &subst));
+        assert!(!well_sorted::<Lang>("true")(&mut egraph, one, &subst));
+    }
+
+    #[test]
+    fn sort_annotations() {
+        let mut egraph = EGraph::<Lang, SortAnalysis>::default();
+        egraph.add_expr(&"(not true)".parse().unwrap());
+        egraph.add_expr(&"(not (not true))".parse().unwrap());
+        egraph.add_expr(&"1".parse().unwrap());
+        egraph.rebuild();
+        let n_matches = |s: &str| s.parse::<Pattern<Lang>>().unwrap().n_matches(&egraph);
+        assert_eq!(n_matches("?a:Bool"), 3);
+        assert_eq!(n_matches("?a:Int"), 1);
+        assert_eq!(n_matches("(not ?a:Bool)"), 2);
+        assert_eq!(n_matches("(not ?a:Int)"), 0);
+        // unknown sorts never match
+        assert_eq!(n_matches("?a:Real"), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Ill-sorted")]
+    fn ill_sorted_add_panics() {
+        let mut egraph = EGraph::<Lang, SortAnalysis>::default();
+        egraph.add_expr(&"(not 1)".parse().unwrap());
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::iter::FromIterator;
 use std::str::FromStr;
 
 use crate::*;
@@ -9,6 +10,8 @@ use thiserror::Error;
 ///
 /// This implements [`FromStr`], and will only parse if it has a
 /// leading `?`.
+/// A variable with a trailing `...`, like `?xs...`, is a
+/// [segment](Var::is_segment()) variable.
 ///
 /// [`FromStr`]: std::str::FromStr
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -34,6 +37,44 @@ impl FromStr for Var {
     }
 }
 
+impl Var {
+    /// Returns `true` if this is a segment variable like `?xs...`.
+    ///
+    /// In a [`Pattern`], a segment variable may only be the last child of an
+    /// e-node.
+    /// It binds the rest of the children of the matched e-node, which is
+    /// one or more of them for a [variadic](Language::matches_prefix())
+    /// operator, and exactly one otherwise.
+    /// When the pattern is instantiated, the bound children are spliced back
+    /// in place of the variable using [`Language::with_children`].
+    /// Segment bindings are stored separately in a [`Subst`]; see
+    /// [`Subst::segment`].
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// assert!("?xs...".parse::<Var>().unwrap().is_segment());
+    /// assert!(!"?x".parse::<Var>().unwrap().is_segment());
+    /// ```
+    pub fn is_segment(&self) -> bool {
+        self.0.as_str().ends_with("...")
+    }
+}
+
+impl Var {
+    /// Splits a constrained variable like `?c:constant` into the variable
+    /// `?c` and its constraint `constant`.
+    pub(crate) fn split_constraint(&self) -> Option<(Var, Symbol)> {
+        let s = self.0.as_str();
+        let i = s.find(':')?;
+        if i > 1 && i + 1 < s.len() {
+            Some((Var(s[..i].into()), s[i + 1..].into()))
+        } else {
+            None
+        }
+    }
+}
+
 impl Display for Var {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Display::fmt(&self.0, f)
@@ -51,6 +92,7 @@ impl Debug for Var {
 #[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Subst {
     pub(crate) vec: smallvec::SmallVec<[(Var, Id); 3]>,
+    pub(crate) segments: Vec<(Var, Vec<Id>)>,
 }
 
 impl Subst {
@@ -58,6 +100,7 @@ impl Subst {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             vec: smallvec::SmallVec::with_capacity(capacity),
+            segments: vec![],
         }
     }
 
@@ -79,6 +122,84 @@ impl Subst {
             .iter()
             .find_map(|(v, id)| if *v == var { Some(id) } else { None })
     }
+
+    /// Binds a [segment](Var::is_segment()) variable to a sequence of
+    /// eclasses, returning the old sequence if present.
+    pub fn insert_segment(&mut self, var: Var, ids: Vec<Id>) -> Option<Vec<Id>> {
+        debug_assert!(var.is_segment(), "{} is not a segment variable", var);
+        for pair in &mut self.segments {
+            if pair.0 == var {
+                return Some(std::mem::replace(&mut pair.1, ids));
+            }
+        }
+        self.segments.push((var, ids));
+        None
+    }
+
+    /// Retrieve the sequence bound to a [segment](Var::is_segment())
+    /// variable, returning `None` if not present.
+    pub fn get_segment(&self, var: Var) -> Option<&[Id]> {
+        self.segments
+            .iter()
+            .find_map(|(v, ids)| if *v == var { Some(&ids[..]) } else { None })
+    }
+
+    /// Like [`get_segment`](Subst::get_segment()), but panics if `var` is
+    /// not bound.
+    pub fn segment(&self, var: Var) -> &[Id] {
+        match self.get_segment(var) {
+            Some(ids) => ids,
+            None => panic!("Segment '{}' not found in {:?}", var, self),
+        }
+    }
+
+    /// Returns the number of variables bound by this `Subst`,
+    /// including segment variables.
+    pub fn len(&self) -> usize {
+        self.vec.len() + self.segments.len()
+    }
+
+    /// Returns `true` if this `Subst` binds no variables.
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty() && self.segments.is_empty()
+    }
+
+    /// Iterates over the variables and the eclass they are bound to,
+    /// in the order they were inserted.
+    /// This does not include segment variables.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::*;
+    /// let a: Var = "?a".parse().unwrap();
+    /// let b: Var = "?b".parse().unwrap();
+    /// let subst: Subst = vec![(a, Id::from(3)), (b, Id::from(1))].into_iter().collect();
+    /// assert_eq!(subst.len(), 2);
+    /// assert_eq!(subst[&b], Id::from(1));
+    /// assert_eq!(subst.iter().map(|(v, _)| v).collect::<Vec<_>>(), vec![a, b]);
+    /// assert_eq!(subst.to_string(), "{?a: 3, ?b: 1}");
+    /// ```
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (Var, Id)> + '_ {
+        self.vec.iter().copied()
+    }
+}
+
+impl FromIterator<(Var, Id)> for Subst {
+    fn from_iter<I: IntoIterator<Item = (Var, Id)>>(iter: I) -> Self {
+        let mut subst = Subst::default();
+        for (var, id) in iter {
+            subst.insert(var, id);
+        }
+        subst
+    }
+}
+
+impl std::ops::Index<&Var> for Subst {
+    type Output = Id;
+
+    fn index(&self, var: &Var) -> &Self::Output {
+        &self[*var]
+    }
 }
 
 impl std::ops::Index<Var> for Subst {
@@ -103,10 +224,22 @@ impl Debug for Subst {
                 write!(f, ", ")?;
             }
         }
+        for (i, (var, ids)) in self.segments.iter().enumerate() {
+            if len > 0 || i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {:?}", var, ids)?;
+        }
         write!(f, "}}")
     }
 }
 
+impl Display for Subst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,5 +251,6 @@ mod tests {
         assert!(Var::from_str("a").is_err());
         assert!(Var::from_str("a?").is_err());
         assert!(Var::from_str("?").is_err());
+        assert!(Var::from_str("?xs...").unwrap().is_segment());
     }
 }
@@ -0,0 +1,344 @@
+/*!
+Rewrite rule synthesis, in the style of
+[Ruler](https://dl.acm.org/doi/10.1145/3485496).
+
+Requires the `synthesis` feature.
+
+A [`Synthesizer`] enumerates the small terms of a language, evaluates each
+of them on the same random inputs, and proposes a rule between every two
+terms that agree on all of them (their _characteristic vectors_, or cvecs,
+match).
+Candidates are considered smallest first, and one is only kept if the rules
+kept so far can't already prove it with equality saturation, so the result
+is a small rule set rather than every identity up to some size.
+
+Agreeing on random inputs doesn't make a rule sound, so the rules should be
+reviewed, or checked further with something like `test_util::check_rewrite`
+(from the `proptest` feature) before being trusted.
+!*/
+
+use std::fmt::Display;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use crate::*;
+
+/// Synthesizes rewrite rules for a language from an evaluator, see the
+/// [module docs](crate::synthesis).
+///
+/// [`Synthesizer`] is configurable in the builder-pattern style.
+///
+/// # Example
+/// ```
+/// use egg::{synthesis::Synthesizer, *};
+/// use rand::{rngs::StdRng, Rng, SeedableRng};
+///
+/// fn eval(node: &SymbolLang, args: &[i64]) -> Option<i64> {
+///     match node.op.as_str() {
+///         "+" => args[0].checked_add(args[1]),
+///         "*" => args[0].checked_mul(args[1]),
+///         n => n.parse().ok(),
+///     }
+/// }
+///
+/// let mut rng = StdRng::seed_from_u64(0);
+/// let rules: Vec<Rewrite<SymbolLang, ()>> = Synthesizer::new(&[("+", 2), ("*", 2), ("0", 0)], &["a", "b"])
+///     .with_max_depth(1)
+///     .synthesize(&mut rng, |rng| rng.gen_range(-100..100), eval);
+/// let names: Vec<String> = rules.iter().map(|r| r.name.to_string()).collect();
+/// assert!(names.contains(&"(+ ?a 0) => ?a".to_string()));
+/// assert!(names.contains(&"(* ?a 0) => 0".to_string()));
+/// // commutativity, in one direction or the other
+/// assert!(names.iter().any(|n| n == "(+ ?a ?b) => (+ ?b ?a)" || n == "(+ ?b ?a) => (+ ?a ?b)"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Synthesizer {
+    ops: Vec<(String, usize)>,
+    vars: Vec<String>,
+    max_depth: usize,
+    samples: usize,
+    term_limit: usize,
+    iter_limit: usize,
+    node_limit: usize,
+}
+
+impl Synthesizer {
+    /// Creates a synthesizer for the terms built from `ops`, each an
+    /// operator and its number of children, and the variables `vars`.
+    ///
+    /// Operators of arity 0 are constants; the e-nodes are built with
+    /// [`FromOp`], and so are the variables, as leaves.
+    pub fn new(ops: &[(&str, usize)], vars: &[&str]) -> Self {
+        Self {
+            ops: ops.iter().map(|(op, n)| (op.to_string(), *n)).collect(),
+            vars: vars.iter().map(|v| v.to_string()).collect(),
+            max_depth: 2,
+            samples: 32,
+            term_limit: 10_000,
+            iter_limit: 5,
+            node_limit: 10_000,
+        }
+    }
+
+    /// Sets the depth of the largest terms enumerated.
+    /// Default: 2
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// Sets the number of random inputs every term is evaluated on.
+    /// Default: 32
+    pub fn with_samples(self, samples: usize) -> Self {
+        Self { samples, ..self }
+    }
+
+    /// Sets the number of terms after which enumeration stops early.
+    /// Default: 10,000
+    pub fn with_term_limit(self, term_limit: usize) -> Self {
+        Self { term_limit, ..self }
+    }
+
+    /// Sets the iteration limit of the equality saturation used to check
+    /// whether a candidate follows from the rules found so far.
+    /// Default: 5
+    pub fn with_iter_limit(self, iter_limit: usize) -> Self {
+        Self { iter_limit, ..self }
+    }
+
+    /// Sets the node limit of the equality saturation used to check
+    /// whether a candidate follows from the rules found so far.
+    /// Default: 10,000
+    pub fn with_node_limit(self, node_limit: usize) -> Self {
+        Self { node_limit, ..self }
+    }
+
+    /// Synthesizes the rules.
+    ///
+    /// Every variable is bound to a value drawn with `sample` for each of
+    /// the random inputs, and `eval` computes the value of an e-node from
+    /// the values of its children, or `None` if it has none (say, for a
+    /// division by zero).
+    /// Terms that evaluate to `None` on every input are ignored, and two
+    /// terms only agree when they evaluate to the same values, including
+    /// `None`, everywhere.
+    ///
+    /// Each rule is named after itself, like `"(+ ?a 0) => ?a"`.
+    /// Rules whose two sides use the same variables are returned in both
+    /// directions, except that no rule has just a variable on its left.
+    ///
+    /// # Panics
+    /// Panics if [`FromOp`] rejects an operator or a variable.
+    pub fn synthesize<L, N, V, R>(
+        &self,
+        rng: &mut R,
+        mut sample: impl FnMut(&mut R) -> V,
+        eval: impl Fn(&L, &[V]) -> Option<V>,
+    ) -> Vec<Rewrite<L, N>>
+    where
+        L: FromOp + Display + Send + Sync + 'static,
+        N: Analysis<L> + Default + 'static,
+        V: Clone + Eq + Hash,
+        R: Rng + ?Sized,
+    {
+        let var_nodes: Vec<(L, Var)> = self
+            .vars
+            .iter()
+            .map(|name| {
+                let node = make_node(name, 0);
+                let var = format!("?{}", name).parse().unwrap();
+                (node, var)
+            })
+            .collect();
+        let inputs: Vec<Vec<V>> = (0..self.samples)
+            .map(|_| var_nodes.iter().map(|_| sample(rng)).collect())
+            .collect();
+        let cvec = |expr: &RecExpr<L>| -> Vec<Option<V>> {
+            inputs
+                .iter()
+                .map(|input| evaluate(expr, &var_nodes, input, &eval))
+                .collect()
+        };
+
+        // enumerate the terms layer by layer, only building new terms from
+        // one representative of each cvec
+        let mut reps: Vec<(RecExpr<L>, usize)> = vec![];
+        let mut by_cvec: HashMap<Vec<Option<V>>, usize> = Default::default();
+        let mut candidates: Vec<(RecExpr<L>, RecExpr<L>)> = vec![];
+        let mut consider = |expr: RecExpr<L>, layer: usize, reps: &mut Vec<(RecExpr<L>, usize)>| {
+            let cvec = cvec(&expr);
+            if cvec.iter().all(|v| v.is_none()) {
+                return;
+            }
+            match by_cvec.get(&cvec) {
+                Some(&i) => candidates.push((reps[i].0.clone(), expr)),
+                None => {
+                    by_cvec.insert(cvec, reps.len());
+                    reps.push((expr, layer));
+                }
+            }
+        };
+
+        for (node, _) in &var_nodes {
+            consider(RecExpr::from(vec![node.clone()]), 0, &mut reps);
+        }
+        for (op, _) in self.ops.iter().filter(|(_, arity)| *arity == 0) {
+            consider(RecExpr::from(vec![make_node(op, 0)]), 0, &mut reps);
+        }
+        let mut n_terms = reps.len();
+        'enumerate: for layer in 1..=self.max_depth {
+            let previous = reps.clone();
+            if previous.is_empty() {
+                break;
+            }
+            for (op, arity) in self.ops.iter().filter(|(_, arity)| *arity > 0) {
+                let node: L = make_node(op, *arity);
+                let mut choice = vec![0; *arity];
+                loop {
+                    // only build terms with a child from the previous layer,
+                    // the others were built before
+                    if choice.iter().any(|&i| previous[i].1 == layer - 1) {
+                        let children: Vec<&RecExpr<L>> =
+                            choice.iter().map(|&i| &previous[i].0).collect();
+                        consider(compose(&node, &children), layer, &mut reps);
+                        n_terms += 1;
+                        if n_terms >= self.term_limit {
+                            break 'enumerate;
+                        }
+                    }
+                    if !next_choice(&mut choice, previous.len()) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        candidates.sort_by_key(|(a, b)| {
+            let (a, b) = (a.as_ref().len(), b.as_ref().len());
+            (a.max(b), a + b)
+        });
+
+        let mut rules: Vec<Rewrite<L, N>> = vec![];
+        for (a, b) in candidates {
+            if self.derivable(&rules, &a, &b) {
+                continue;
+            }
+            let a = to_pattern(&a, &var_nodes);
+            let b = to_pattern(&b, &var_nodes);
+            for (lhs, rhs) in [(&a, &b), (&b, &a)] {
+                // a variable on the left would match every e-class
+                if let Some(ENodeOrVar::Var(_)) = lhs.ast.as_ref().last() {
+                    continue;
+                }
+                let name = format!("{} => {}", lhs, rhs);
+                if let Ok(rule) = Rewrite::new(name, lhs.clone(), rhs.clone()) {
+                    rules.push(rule);
+                }
+            }
+        }
+        rules
+    }
+
+    /// Whether `rules` prove `a` and `b` equal.
+    fn derivable<L, N>(&self, rules: &[Rewrite<L, N>], a: &RecExpr<L>, b: &RecExpr<L>) -> bool
+    where
+        L: Language + 'static,
+        N: Analysis<L> + Default + 'static,
+    {
+        let mut egraph = EGraph::<L, N>::default();
+        let a = egraph.add_expr(a);
+        let b = egraph.add_expr(b);
+        egraph.rebuild();
+        if egraph.find(a) == egraph.find(b) {
+            return true;
+        }
+        let runner = Runner::default()
+            .with_egraph(egraph)
+            .with_iter_limit(self.iter_limit)
+            .with_node_limit(self.node_limit)
+            .with_hook(move |runner| {
+                if runner.egraph.find(a) == runner.egraph.find(b) {
+                    Err("proved".into())
+                } else {
+                    Ok(())
+                }
+            })
+            .run(rules);
+        runner.egraph.find(a) == runner.egraph.find(b)
+    }
+}
+
+fn make_node<L: FromOp>(op: &str, arity: usize) -> L {
+    match L::from_op(op, vec![Id::from(0); arity]) {
+        Ok(node) => node,
+        Err(err) => panic!("Synthesizer: {:?}", err),
+    }
+}
+
+/// Advances `choice` to the next tuple of indices below `n`, returning
+/// `false` after the last one.
+fn next_choice(choice: &mut [usize], n: usize) -> bool {
+    for i in choice.iter_mut() {
+        *i += 1;
+        if *i < n {
+            return true;
+        }
+        *i = 0;
+    }
+    false
+}
+
+/// Builds the term `node` applied to `children`.
+fn compose<L: Language>(node: &L, children: &[&RecExpr<L>]) -> RecExpr<L> {
+    let mut expr = RecExpr::default();
+    let mut roots = Vec::with_capacity(children.len());
+    for child in children {
+        let mut copied: Vec<Id> = Vec::with_capacity(child.as_ref().len());
+        for n in child.as_ref() {
+            let n = n.clone().map_children(|c| copied[usize::from(c)]);
+            copied.push(expr.add(n));
+        }
+        roots.push(*copied.last().unwrap());
+    }
+    let mut roots = roots.into_iter();
+    expr.add(node.clone().map_children(|_| roots.next().unwrap()));
+    expr
+}
+
+fn evaluate<L: Language, V: Clone>(
+    expr: &RecExpr<L>,
+    var_nodes: &[(L, Var)],
+    input: &[V],
+    eval: &impl Fn(&L, &[V]) -> Option<V>,
+) -> Option<V> {
+    let mut values: Vec<V> = Vec::with_capacity(expr.as_ref().len());
+    let mut args: Vec<V> = vec![];
+    for node in expr.as_ref() {
+        let value = match var_nodes.iter().position(|(v, _)| v == node) {
+            Some(i) => input[i].clone(),
+            None => {
+                args.clear();
+                args.extend(
+                    node.children()
+                        .iter()
+                        .map(|&c| values[usize::from(c)].clone()),
+                );
+                eval(node, &args)?
+            }
+        };
+        values.push(value);
+    }
+    values.pop()
+}
+
+fn to_pattern<L: Language>(expr: &RecExpr<L>, var_nodes: &[(L, Var)]) -> Pattern<L> {
+    let ast: Vec<ENodeOrVar<L>> = expr
+        .as_ref()
+        .iter()
+        .map(|node| match var_nodes.iter().find(|(v, _)| v == node) {
+            Some((_, var)) => ENodeOrVar::Var(*var),
+            None => ENodeOrVar::ENode(node.clone()),
+        })
+        .collect();
+    Pattern::new(RecExpr::from(ast))
+}
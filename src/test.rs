@@ -31,6 +31,7 @@ pub fn test_runner<L, A>(
     rules: &[Rewrite<L, A>],
     start: RecExpr<L>,
     goals: &[Pattern<L>],
+    best: Option<RecExpr<L>>,
     check_fn: Option<fn(Runner<L, A, ()>)>,
     should_check: bool,
 ) where
@@ -82,6 +83,15 @@ pub fn test_runner<L, A>(
         println!("{report}");
         runner.egraph.check_goals(id, goals);
 
+        if let Some(best) = best {
+            let (_, extracted) = Extractor::new(&runner.egraph, AstSize).find_best(id);
+            assert_eq!(
+                extracted.to_string(),
+                best.to_string(),
+                "Extracted a different best term"
+            );
+        }
+
         if let Some(filename) = env_var::<PathBuf>("EGG_BENCH_CSV") {
             let mut file = File::options()
                 .create(true)
@@ -243,6 +253,19 @@ where
 ///     "(baz 1)",
 /// }
 /// ```
+///
+/// Add `@best` after the goals to also check the smallest term
+/// ([`AstSize`]) extracted from the initial expression's e-class:
+///
+/// ```
+/// # use egg::*;
+/// egg::test_fn! {
+///     simplifies_to_x,
+///     [rewrite!("add-0"; "(+ ?a 0)" => "?a")],
+///     "(+ (+ x 0) 0)" => "(+ x 0)", "x"
+///     @best "x"
+/// }
+/// ```
 #[macro_export]
 macro_rules! test_fn {
     (
@@ -252,6 +275,7 @@ macro_rules! test_fn {
         $start:literal
         =>
         $($goal:literal),+ $(,)?
+        $(@best $best:literal)?
         $(@check $check_fn:expr)?
     ) => {
 
@@ -266,6 +290,7 @@ macro_rules! test_fn {
             &$rules,
             $start.parse().unwrap(),
             &[$( $goal.parse().unwrap() ),+],
+            None $(.or(Some($best.parse().unwrap())))?,
             None $(.or(Some($check_fn)))?,
             check,
         )
@@ -0,0 +1,239 @@
+/*!
+Property-based testing helpers built on [`proptest`](https://docs.rs/proptest).
+
+Requires the `proptest` feature.
+
+[`arb_expr`] generates random [`RecExpr`]s from a table of operators and
+their arities, and [`check_rewrite`] uses it to fuzz a [`Rewrite`] against
+an evaluator, so a rule set can be checked before trusting what
+saturation finds with it.
+!*/
+
+use std::fmt::{Debug, Display};
+
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Union};
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+use crate::*;
+
+#[derive(Debug, Clone)]
+struct Tree(String, Vec<Tree>);
+
+/// A [`Strategy`] generating random [`RecExpr`]s up to `max_depth` deep.
+///
+/// Each entry of `ops` is an operator and its number of children; the
+/// e-nodes are built with [`FromOp`], so operators of arity 0 are the
+/// leaves (variables and constants).
+///
+/// # Panics
+/// Panics if `ops` has no operator of arity 0, and when generating an
+/// e-node that [`FromOp`] rejects.
+pub fn arb_expr<L>(ops: &[(&str, usize)], max_depth: u32) -> BoxedStrategy<RecExpr<L>>
+where
+    L: FromOp + 'static,
+{
+    let leaves: Vec<String> = ops
+        .iter()
+        .filter(|(_, arity)| *arity == 0)
+        .map(|(op, _)| op.to_string())
+        .collect();
+    assert!(!leaves.is_empty(), "arb_expr needs an operator of arity 0");
+    let nodes: Vec<(String, usize)> = ops
+        .iter()
+        .filter(|(_, arity)| *arity > 0)
+        .map(|(op, arity)| (op.to_string(), *arity))
+        .collect();
+
+    let leaf = proptest::sample::select(leaves).prop_map(|op| Tree(op, vec![]));
+    let tree = if nodes.is_empty() {
+        leaf.boxed()
+    } else {
+        leaf.prop_recursive(max_depth, 256, 8, move |inner| {
+            Union::new(nodes.clone().into_iter().map(|(op, arity)| {
+                proptest::collection::vec(inner.clone(), arity)
+                    .prop_map(move |children| Tree(op.clone(), children))
+            }))
+        })
+        .boxed()
+    };
+
+    tree.prop_map(|tree| {
+        let mut expr = RecExpr::default();
+        add_tree(&mut expr, &tree);
+        expr
+    })
+    .boxed()
+}
+
+fn add_tree<L: FromOp>(expr: &mut RecExpr<L>, tree: &Tree) -> Id {
+    let children = tree.1.iter().map(|t| add_tree(expr, t)).collect();
+    match L::from_op(&tree.0, children) {
+        Ok(node) => expr.add(node),
+        Err(err) => panic!("arb_expr: {}", err),
+    }
+}
+
+/// Checks that `rewrite` preserves the value of terms under `eval`.
+///
+/// Both sides of the rewrite must be [`Pattern`]s.
+/// Each of `cases` times, the variables of the left-hand side are bound to
+/// random expressions from [`arb_expr`]`(ops, max_depth)`, and both
+/// instantiated sides are evaluated.
+/// `eval` can return `None` for terms it can't evaluate (say, a division
+/// by zero); such cases are discarded.
+/// Conditions of the rewrite are not checked, so for a conditional rewrite
+/// `eval` should also return `None` when the condition doesn't hold.
+///
+/// # Panics
+/// Panics with the smallest counterexample found if the two sides evaluate
+/// differently.
+///
+/// # Example
+/// ```
+/// use egg::{rewrite as rw, test_util::check_rewrite, *};
+///
+/// fn eval(expr: &RecExpr<SymbolLang>) -> Option<i64> {
+///     let mut values: Vec<i64> = vec![];
+///     for node in expr.as_ref() {
+///         let arg = |i: usize| values[usize::from(node.children[i])];
+///         let value = match node.op.as_str() {
+///             "+" => arg(0).checked_add(arg(1))?,
+///             "*" => arg(0).checked_mul(arg(1))?,
+///             "x" => 3,
+///             "y" => -2,
+///             n => n.parse().unwrap(),
+///         };
+///         values.push(value);
+///     }
+///     values.last().copied()
+/// }
+///
+/// let ops = &[("+", 2), ("*", 2), ("x", 0), ("y", 0), ("0", 0), ("1", 0)];
+/// let distribute: Rewrite<SymbolLang, ()> =
+///     rw!("distribute"; "(* ?a (+ ?b ?c))" => "(+ (* ?a ?b) (* ?a ?c))");
+/// check_rewrite(&distribute, ops, 3, 100, eval);
+/// ```
+pub fn check_rewrite<L, N, T, E>(
+    rewrite: &Rewrite<L, N>,
+    ops: &[(&str, usize)],
+    max_depth: u32,
+    cases: u32,
+    eval: E,
+) where
+    L: Language + FromOp + Display + 'static,
+    N: Analysis<L>,
+    T: PartialEq + Debug,
+    E: Fn(&RecExpr<L>) -> Option<T>,
+{
+    let lhs = rewrite
+        .searcher
+        .get_pattern_ast()
+        .unwrap_or_else(|| panic!("Rewrite {} has no left-hand side pattern", rewrite.name));
+    let rhs = rewrite
+        .applier
+        .get_pattern_ast()
+        .unwrap_or_else(|| panic!("Rewrite {} has no right-hand side pattern", rewrite.name));
+    let vars = rewrite.searcher.vars();
+
+    let mut runner = TestRunner::new(Config {
+        cases,
+        ..Config::default()
+    });
+    let strategy = proptest::collection::vec(arb_expr::<L>(ops, max_depth), vars.len());
+    let result = runner.run(&strategy, |exprs| {
+        let bindings: Vec<(Var, &RecExpr<L>)> = vars.iter().copied().zip(&exprs).collect();
+        let before = instantiate(lhs, &bindings);
+        let after = instantiate(rhs, &bindings);
+        match (eval(&before), eval(&after)) {
+            (Some(a), Some(b)) => {
+                prop_assert_eq!(a, b, "{} => {}", before, after);
+                Ok(())
+            }
+            _ => Err(TestCaseError::reject("couldn't evaluate")),
+        }
+    });
+    if let Err(err) = result {
+        panic!(
+            "Rewrite {} doesn't preserve semantics: {}",
+            rewrite.name, err
+        );
+    }
+}
+
+fn instantiate<L: Language>(
+    pattern: &PatternAst<L>,
+    bindings: &[(Var, &RecExpr<L>)],
+) -> RecExpr<L> {
+    let mut expr = RecExpr::default();
+    let mut ids: Vec<Id> = Vec::with_capacity(pattern.as_ref().len());
+    for node in pattern.as_ref() {
+        let id = match node {
+            ENodeOrVar::Var(v) => {
+                let bound = bindings
+                    .iter()
+                    .find(|(var, _)| var == v)
+                    .unwrap_or_else(|| panic!("Unbound variable {}", v))
+                    .1;
+                let mut copied: Vec<Id> = Vec::with_capacity(bound.as_ref().len());
+                for n in bound.as_ref() {
+                    let n = n.clone().map_children(|c| copied[usize::from(c)]);
+                    copied.push(expr.add(n));
+                }
+                *copied.last().unwrap()
+            }
+            ENodeOrVar::ENode(n) => expr.add(n.clone().map_children(|c| ids[usize::from(c)])),
+        };
+        ids.push(id);
+    }
+    expr
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_util::*, *};
+    use proptest::strategy::{Strategy, ValueTree};
+
+    fn eval(expr: &RecExpr<SymbolLang>) -> Option<i64> {
+        let mut values: Vec<i64> = vec![];
+        for node in expr.as_ref() {
+            let arg = |i: usize| values[usize::from(node.children[i])];
+            let value = match node.op.as_str() {
+                "-" => arg(0).checked_sub(arg(1))?,
+                "x" => 5,
+                "y" => 7,
+                n => n.parse().unwrap(),
+            };
+            values.push(value);
+        }
+        values.last().copied()
+    }
+
+    const OPS: &[(&str, usize)] = &[("-", 2), ("x", 0), ("y", 0), ("2", 0)];
+
+    #[test]
+    fn arb_expr_respects_arities() {
+        let mut runner = proptest::test_runner::TestRunner::default();
+        let strategy = arb_expr::<SymbolLang>(OPS, 4);
+        for _ in 0..50 {
+            let expr = strategy.new_tree(&mut runner).unwrap().current();
+            for node in expr.as_ref() {
+                let arity = if node.op.as_str() == "-" { 2 } else { 0 };
+                assert_eq!(node.children.len(), arity);
+            }
+        }
+    }
+
+    #[test]
+    fn sound_rewrite_passes() {
+        let rw: Rewrite<SymbolLang, ()> = rewrite!("sub-self"; "(- ?a ?a)" => "(- 2 2)");
+        check_rewrite(&rw, OPS, 3, 50, eval);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't preserve semantics")]
+    fn unsound_rewrite_fails() {
+        let rw: Rewrite<SymbolLang, ()> = rewrite!("sub-comm"; "(- ?a ?b)" => "(- ?b ?a)");
+        check_rewrite(&rw, OPS, 3, 50, eval);
+    }
+}
@@ -5,6 +5,9 @@ use std::fmt::Debug;
 #[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnionFind {
     parents: Vec<Id>,
+    /// The old parents of the ids whose parent changed, if enabled.
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    log: Option<Vec<(Id, Id)>>,
 }
 
 impl UnionFind {
@@ -22,8 +25,40 @@ impl UnionFind {
         self.parents[usize::from(query)]
     }
 
-    fn parent_mut(&mut self, query: Id) -> &mut Id {
-        &mut self.parents[usize::from(query)]
+    fn set_parent(&mut self, query: Id, parent: Id) {
+        let old = std::mem::replace(&mut self.parents[usize::from(query)], parent);
+        if let Some(log) = &mut self.log {
+            log.push((query, old));
+        }
+    }
+
+    /// Starts logging changes, so they can be undone with `rollback`.
+    pub fn enable_log(&mut self) {
+        self.log.get_or_insert_with(Vec::new);
+    }
+
+    /// Stops logging changes and drops the log, so earlier states can no
+    /// longer be restored.
+    pub fn disable_log(&mut self) {
+        self.log = None;
+    }
+
+    /// The length of the log, to pass to `rollback` later, or 0 if logging
+    /// is disabled.
+    pub fn log_len(&self) -> usize {
+        self.log.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Restores the state from when there were `size` sets and the log
+    /// was `log_len` long.
+    pub fn rollback(&mut self, size: usize, log_len: usize) {
+        let log = self.log.as_mut().expect("union-find log is disabled");
+        for (id, old) in log.drain(log_len..).rev() {
+            if usize::from(id) < size {
+                self.parents[usize::from(id)] = old;
+            }
+        }
+        self.parents.truncate(size);
     }
 
     pub fn find(&self, mut current: Id) -> Id {
@@ -36,7 +71,7 @@ impl UnionFind {
     pub fn find_mut(&mut self, mut current: Id) -> Id {
         while current != self.parent(current) {
             let grandparent = self.parent(self.parent(current));
-            *self.parent_mut(current) = grandparent;
+            self.set_parent(current, grandparent);
             current = grandparent;
         }
         current
@@ -44,7 +79,7 @@ impl UnionFind {
 
     /// Given two leader ids, unions the two eclasses making root1 the leader.
     pub fn union(&mut self, root1: Id, root2: Id) -> Id {
-        *self.parent_mut(root2) = root1;
+        self.set_parent(root2, root1);
         root1
     }
 }
@@ -89,4 +124,25 @@ mod tests {
         let expected = vec![0, 0, 0, 0, 4, 5, 6, 6, 6, 6];
         assert_eq!(uf.parents, ids(expected));
     }
+
+    #[test]
+    fn rollback() {
+        let id = Id::from;
+        let mut uf = UnionFind::default();
+        for _ in 0..4 {
+            uf.make_set();
+        }
+        uf.union(id(0), id(1));
+        uf.enable_log();
+        let (size, log_len) = (uf.size(), uf.log_len());
+
+        uf.make_set();
+        uf.union(id(2), id(0));
+        uf.union(id(2), id(4));
+        uf.find_mut(id(1));
+        assert_eq!(uf.find(id(1)), id(2));
+
+        uf.rollback(size, log_len);
+        assert_eq!(uf.parents, ids(vec![0, 0, 2, 3]));
+    }
 }
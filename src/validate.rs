@@ -0,0 +1,317 @@
+/*!
+Checking rule sets for likely mistakes.
+
+See [`validate_rules`].
+!*/
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::pattern::strip_constraints;
+use crate::*;
+
+/// A likely mistake in a rule set, found by [`validate_rules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleWarning {
+    /// More than one rule is named `rule`, so they can't be told apart in
+    /// [`Iteration::applied`] or by schedulers.
+    DuplicateName {
+        /// The name shared by the rules.
+        rule: Symbol,
+    },
+    /// `rule` has the same left- and right-hand sides as `of`, up to the
+    /// names of their variables.
+    Duplicate {
+        /// The name of the later rule.
+        rule: Symbol,
+        /// The name of the earlier rule.
+        of: Symbol,
+    },
+    /// `rule` is an instance of the more general `by`: substituting terms
+    /// for the variables of `by` gives `rule`, so `rule` never adds anything
+    /// `by` doesn't.
+    Subsumed {
+        /// The name of the specific rule.
+        rule: Symbol,
+        /// The name of the general rule.
+        by: Symbol,
+    },
+    /// `rule` rewrites terms to themselves, which never changes the egraph.
+    Identity {
+        /// The name of the rule.
+        rule: Symbol,
+    },
+}
+
+impl Display for RuleWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleWarning::DuplicateName { rule } => {
+                write!(f, "More than one rule is named {}", rule)
+            }
+            RuleWarning::Duplicate { rule, of } => {
+                write!(f, "Rule {} is the same as rule {}", rule, of)
+            }
+            RuleWarning::Subsumed { rule, by } => {
+                write!(
+                    f,
+                    "Rule {} is an instance of the more general rule {}",
+                    rule, by
+                )
+            }
+            RuleWarning::Identity { rule } => {
+                write!(f, "Rule {} rewrites terms to themselves", rule)
+            }
+        }
+    }
+}
+
+/// Checks a rule set for likely mistakes before running it.
+///
+/// This flags rules sharing a name, rules that are the same as another up to
+/// renaming their variables, rules that are instances of a more general
+/// rule, and rules rewriting terms to themselves.
+/// Unbound variables are already rejected by [`Rewrite::new`].
+/// Only rules with a [`Pattern`] on both sides are compared with each
+/// other, and a variable constrained as `?x:constraint` is only compared
+/// with variables with the same constraint.
+/// [`Condition`]s are not taken into account, so a conditional rule may be
+/// flagged as an instance of an unconditional one with good reason.
+///
+/// None of these stop a [`Runner`] from running, but they make it do
+/// useless work or make its reports confusing, so it's worth checking rule
+/// sets once at startup.
+///
+/// # Example
+/// ```
+/// use egg::{rewrite as rw, *};
+/// let rules: &[Rewrite<SymbolLang, ()>] = &[
+///     rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rw!("add-comm"; "(+ ?x ?y)" => "(+ ?y ?x)"),
+/// ];
+/// let warnings: Vec<String> = validate_rules(rules).iter().map(|w| w.to_string()).collect();
+/// assert_eq!(warnings, ["Rule add-comm is the same as rule commute-add"]);
+/// ```
+pub fn validate_rules<L, N>(rules: &[Rewrite<L, N>]) -> Vec<RuleWarning>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    let mut warnings = vec![];
+
+    let mut names = HashSet::default();
+    for rule in rules {
+        if !names.insert(rule.name) {
+            warnings.push(RuleWarning::DuplicateName { rule: rule.name });
+        }
+    }
+
+    let sides: Vec<Option<Sides<L>>> = rules
+        .iter()
+        .map(|rule| {
+            let (lhs, constraints) = strip_constraints(rule.searcher.get_constrained_ast()?);
+            let rhs = rule.applier.get_pattern_ast()?;
+            Some(Sides {
+                lhs,
+                rhs,
+                constraints,
+            })
+        })
+        .collect();
+
+    for (i, rule) in rules.iter().enumerate() {
+        let specific = match &sides[i] {
+            Some(sides) => sides,
+            None => continue,
+        };
+        let (lhs, rhs) = (&specific.lhs, specific.rhs);
+        if same(lhs, root(lhs), rhs, root(rhs)) {
+            warnings.push(RuleWarning::Identity { rule: rule.name });
+        }
+        for (j, other) in rules.iter().enumerate().filter(|&(j, _)| j != i) {
+            let general = match &sides[j] {
+                Some(sides) => sides,
+                None => continue,
+            };
+            if !is_instance(general, specific) {
+                continue;
+            }
+            if is_instance(specific, general) {
+                // only flag the later of two equivalent rules
+                if j < i {
+                    warnings.push(RuleWarning::Duplicate {
+                        rule: rule.name,
+                        of: other.name,
+                    });
+                }
+            } else {
+                warnings.push(RuleWarning::Subsumed {
+                    rule: rule.name,
+                    by: other.name,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+fn root<L: Language>(ast: &PatternAst<L>) -> Id {
+    Id::from(ast.as_ref().len() - 1)
+}
+
+/// Whether the subterm `i` of `a` and the subterm `j` of `b` are the same.
+fn same<L: Language>(a: &PatternAst<L>, i: Id, b: &PatternAst<L>, j: Id) -> bool {
+    match (&a[i], &b[j]) {
+        (ENodeOrVar::Var(v), ENodeOrVar::Var(w)) => v == w,
+        (ENodeOrVar::ENode(n), ENodeOrVar::ENode(m)) => {
+            n.matches(m)
+                && n.children()
+                    .iter()
+                    .zip(m.children())
+                    .all(|(&c, &d)| same(a, c, b, d))
+        }
+        _ => false,
+    }
+}
+
+/// The two sides of a rule, with the constraints of its left-hand side
+/// split off its variables.
+struct Sides<'a, L> {
+    lhs: PatternAst<L>,
+    rhs: &'a PatternAst<L>,
+    constraints: Vec<(Var, Symbol)>,
+}
+
+impl<L> Sides<'_, L> {
+    fn constraint(&self, var: Var) -> Option<Symbol> {
+        self.constraints
+            .iter()
+            .find(|(v, _)| *v == var)
+            .map(|(_, constraint)| *constraint)
+    }
+}
+
+/// Whether substituting subterms of `specific` for the variables of
+/// `general` gives `specific`, on both sides at once.
+///
+/// Variables only generalize variables with the same constraint, or none,
+/// and constrained variables don't generalize e-nodes.
+fn is_instance<L: Language>(general: &Sides<L>, specific: &Sides<L>) -> bool {
+    let mut bindings = vec![];
+    let (lhs, rhs) = (&specific.lhs, specific.rhs);
+    let (general_lhs, general_rhs) = (&general.lhs, general.rhs);
+    generalizes(
+        general,
+        general_lhs,
+        root(general_lhs),
+        lhs,
+        root(lhs),
+        specific,
+        &mut bindings,
+    ) && generalizes(
+        general,
+        general_rhs,
+        root(general_rhs),
+        rhs,
+        root(rhs),
+        specific,
+        &mut bindings,
+    )
+}
+
+fn generalizes<'a, L: Language>(
+    general: &Sides<L>,
+    pattern: &PatternAst<L>,
+    p: Id,
+    term: &'a PatternAst<L>,
+    t: Id,
+    specific: &Sides<L>,
+    bindings: &mut Vec<(Var, &'a PatternAst<L>, Id)>,
+) -> bool {
+    match &pattern[p] {
+        ENodeOrVar::Var(v) => match bindings.iter().find(|(w, _, _)| w == v) {
+            Some(&(_, ast, id)) => same(ast, id, term, t),
+            None => {
+                let constraint = general.constraint(*v);
+                match &term[t] {
+                    ENodeOrVar::Var(w) if specific.constraint(*w) != constraint => return false,
+                    ENodeOrVar::ENode(_) if constraint.is_some() => return false,
+                    _ => (),
+                }
+                bindings.push((*v, term, t));
+                true
+            }
+        },
+        ENodeOrVar::ENode(n) => match &term[t] {
+            ENodeOrVar::ENode(m) if n.matches(m) => n
+                .children()
+                .iter()
+                .zip(m.children())
+                .all(|(&c, &d)| generalizes(general, pattern, c, term, d, specific, bindings)),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn flags_identities_and_names() {
+        let rules: Vec<Rewrite<S, ()>> = vec![
+            rewrite!("noop"; "(* ?a 1)" => "(* ?a 1)"),
+            rewrite!("mul-1"; "(* ?a 1)" => "?a"),
+            rewrite!("mul-1"; "(* 1 ?a)" => "?a"),
+            rewrite!("double"; "(+ ?a ?a)" => "(* 2 ?a)"),
+            rewrite!("add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+        ];
+        let warnings = validate_rules(&rules);
+        assert_eq!(
+            warnings,
+            vec![
+                RuleWarning::DuplicateName {
+                    rule: "mul-1".into()
+                },
+                RuleWarning::Identity {
+                    rule: "noop".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_duplicates_and_instances() {
+        let rules: Vec<Rewrite<S, ()>> = vec![
+            rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("add-comm"; "(+ ?x ?y)" => "(+ ?y ?x)"),
+            rewrite!("commute-add-0"; "(+ ?a 0)" => "(+ 0 ?a)"),
+            // same left-hand side, but a different right-hand side
+            rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+            // constrained variables are told apart from plain ones
+            rewrite!("commute-num"; "(+ ?a:num ?b)" => "(+ ?b ?a)"),
+            rewrite!("commute-num-0"; "(+ ?a:num 0)" => "(+ 0 ?a)"),
+        ];
+        assert_eq!(
+            validate_rules(&rules),
+            vec![
+                RuleWarning::Duplicate {
+                    rule: "add-comm".into(),
+                    of: "commute-add".into()
+                },
+                RuleWarning::Subsumed {
+                    rule: "commute-add-0".into(),
+                    by: "commute-add".into()
+                },
+                RuleWarning::Subsumed {
+                    rule: "commute-add-0".into(),
+                    by: "add-comm".into()
+                },
+                RuleWarning::Subsumed {
+                    rule: "commute-num-0".into(),
+                    by: "commute-num".into()
+                },
+            ]
+        );
+    }
+}
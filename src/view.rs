@@ -0,0 +1,142 @@
+use std::fmt::{self, Debug};
+
+use crate::*;
+
+/// A read-only view of an [`EGraph`] restricted to the e-classes that
+/// satisfy a predicate.
+///
+/// Create one with [`EGraph::view`].
+/// Searching through a view only finds matches rooted in e-classes that
+/// are part of the view, so you can run a group of rules over some part of
+/// the egraph (for example, the e-classes your analysis marks as integers)
+/// without copying the egraph or adding the same [`Condition`] to every
+/// rule.
+///
+/// Only the root of each match is filtered; the e-classes matched by
+/// the children of a pattern may be outside the view.
+/// Since a view borrows the egraph, collect the matches first and then
+/// [`apply`](Rewrite::apply()) them once you are done with the view:
+///
+/// ```
+/// use egg::{rewrite as rw, *};
+///
+/// #[derive(Default)]
+/// struct IsInt;
+/// impl Analysis<SymbolLang> for IsInt {
+///     type Data = bool;
+///     fn merge(&mut self, to: &mut bool, from: bool) -> DidMerge {
+///         merge_max(to, from)
+///     }
+///     fn make(egraph: &EGraph<SymbolLang, Self>, enode: &SymbolLang) -> bool {
+///         match enode.op.as_str() {
+///             "int" => true,
+///             "+" => enode.children.iter().all(|&c| egraph[c].data),
+///             _ => false,
+///         }
+///     }
+/// }
+///
+/// let mut egraph = EGraph::<SymbolLang, IsInt>::default();
+/// let int = egraph.add_expr(&"(+ (int a) (int b))".parse().unwrap());
+/// let other = egraph.add_expr(&"(+ x y)".parse().unwrap());
+/// egraph.rebuild();
+///
+/// let rules: &[Rewrite<SymbolLang, IsInt>] = &[rw!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+/// let ints = egraph.view(|class| class.data);
+/// let matches: Vec<_> = rules.iter().map(|rule| ints.search(&*rule.searcher)).collect();
+/// for (rule, matches) in rules.iter().zip(&matches) {
+///     rule.apply(&mut egraph, matches);
+/// }
+/// egraph.rebuild();
+///
+/// assert_eq!(egraph[int].len(), 2);
+/// assert_eq!(egraph[other].len(), 1);
+/// ```
+pub struct EGraphView<'a, L: Language, N: Analysis<L>, F> {
+    egraph: &'a EGraph<L, N>,
+    filter: F,
+}
+
+impl<'a, L, N, F> EGraphView<'a, L, N, F>
+where
+    L: Language,
+    N: Analysis<L>,
+    F: Fn(&EClass<L, N::Data>) -> bool,
+{
+    pub(crate) fn new(egraph: &'a EGraph<L, N>, filter: F) -> Self {
+        Self { egraph, filter }
+    }
+
+    /// Returns the underlying egraph.
+    pub fn egraph(&self) -> &'a EGraph<L, N> {
+        self.egraph
+    }
+
+    /// Returns `true` if the e-class of `id` is part of this view.
+    pub fn contains(&self, id: Id) -> bool {
+        (self.filter)(&self.egraph[id])
+    }
+
+    /// Returns an iterator over the e-classes in this view.
+    pub fn classes(&self) -> impl Iterator<Item = &'a EClass<L, N::Data>> + '_ {
+        self.egraph
+            .classes()
+            .filter(move |class| (self.filter)(class))
+    }
+
+    /// Like [`Searcher::search`], but only searches the e-classes in this
+    /// view.
+    pub fn search<'s, S>(&self, searcher: &'s S) -> Vec<SearchMatches<'s, L>>
+    where
+        S: Searcher<L, N> + ?Sized,
+    {
+        self.search_with_limit(searcher, usize::MAX)
+    }
+
+    /// Like [`Searcher::search_with_limit`], but only searches the e-classes
+    /// in this view.
+    pub fn search_with_limit<'s, S>(
+        &self,
+        searcher: &'s S,
+        limit: usize,
+    ) -> Vec<SearchMatches<'s, L>>
+    where
+        S: Searcher<L, N> + ?Sized,
+    {
+        let ids = self.classes().map(|class| class.id);
+        rewrite::search_eclasses_with_limit(searcher, self.egraph, ids, limit)
+    }
+}
+
+impl<'a, L: Language, N: Analysis<L>, F> Debug for EGraphView<'a, L, N, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EGraphView")
+            .field("egraph", &self.egraph)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn view_limits_search_roots() {
+        let mut egraph = EGraph::<S, ()>::default();
+        let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+        let fy = egraph.add_expr(&"(f y)".parse().unwrap());
+        egraph.rebuild();
+
+        let pat: Pattern<S> = "(f ?a)".parse().unwrap();
+        let view = egraph.view(|class| class.id == fx);
+        assert!(view.contains(fx));
+        assert!(!view.contains(fy));
+        assert_eq!(view.classes().count(), 1);
+
+        let matches = view.search(&pat);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].eclass, fx);
+        assert_eq!(pat.search(&egraph).len(), 2);
+        assert!(view.search_with_limit(&pat, 0).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt::Display;
+
+use egg::*;
+
+// The same variadic language twice, once with heap-allocated children and
+// once with up to 4 children stored inline.
+define_language! {
+    enum VecLang {
+        "f" = F(Vec<Id>),
+        Symbol(Symbol),
+    }
+}
+
+define_language! {
+    enum InlineLang {
+        "f" = F(SmallVec<[Id; 4]>),
+        Symbol(Symbol),
+    }
+}
+
+fn exprs() -> Vec<String> {
+    (0..200)
+        .map(|k| {
+            let args = |n: usize, offset: usize| -> Vec<String> {
+                (0..n)
+                    .map(|j| format!("x{}", (k + j + offset) % 7))
+                    .collect()
+            };
+            let inner = format!("(f {})", args(2 + k % 5, 0).join(" "));
+            format!("(f {} {})", inner, args(1 + k % 4, 3).join(" "))
+        })
+        .collect()
+}
+
+// rotate the children of `f` for every arity that occurs
+fn rules<L: Language + FromOp + Sync + Send + 'static>() -> Vec<Rewrite<L, ()>> {
+    (2..=6)
+        .map(|n| {
+            let vars: Vec<String> = (0..n).map(|i| format!("?a{}", i)).collect();
+            let mut rotated = vars.clone();
+            rotated.rotate_left(1);
+            let lhs: Pattern<L> = format!("(f {})", vars.join(" ")).parse().unwrap();
+            let rhs: Pattern<L> = format!("(f {})", rotated.join(" ")).parse().unwrap();
+            Rewrite::new(format!("rotate-{}", n), lhs, rhs).unwrap()
+        })
+        .collect()
+}
+
+fn run<L>() -> EGraph<L, ()>
+where
+    L: Language + FromOp + Display + Sync + Send + 'static,
+{
+    let mut runner = Runner::default()
+        .with_scheduler(SimpleScheduler)
+        .with_node_limit(100_000);
+    for expr in exprs() {
+        runner = runner.with_expr(&expr.parse().unwrap());
+    }
+    let runner = runner.run(&rules());
+    assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    runner.egraph
+}
+
+#[test]
+fn inline_children_spill_rate() {
+    let heap = run::<VecLang>();
+    let inline = run::<InlineLang>();
+    assert_eq!(heap.total_number_of_nodes(), inline.total_number_of_nodes());
+
+    let mut nodes = 0;
+    let mut wide = 0;
+    let mut spilled = 0;
+    for class in inline.classes() {
+        for node in &class.nodes {
+            if let InlineLang::F(children) = node {
+                nodes += 1;
+                wide += (children.len() > 4) as usize;
+                spilled += children.spilled() as usize;
+            }
+        }
+    }
+    assert_eq!(spilled, wide);
+    assert!(spilled < nodes);
+}
+
+#[test]
+fn children_ematching_bench() {
+    let exprs = exprs();
+    let exprs: Vec<&str> = exprs.iter().map(|e| e.as_str()).collect();
+    let extra_patterns = &["(f (f ?a ?b) ?c)", "(f ?a (f ?b ?c) ?d)"];
+    egg::test::bench_egraph("Vec<Id>", rules::<VecLang>(), &exprs, extra_patterns);
+    egg::test::bench_egraph(
+        "SmallVec<[Id; 4]>",
+        rules::<InlineLang>(),
+        &exprs,
+        extra_patterns,
+    );
+}
@@ -238,6 +238,19 @@ egg::test_fn! {
     "(+ 1 (- a (* (- 2 1) a)))" => "1"
 }
 
+egg::test_fn! {
+    math_simplify_const_best, rules(),
+    "(+ 1 (- a (* (- 2 1) a)))" => "1"
+    @best "1"
+}
+
+egg::test_fn! {
+    #[should_panic(expected = "Extracted a different best term")]
+    math_simplify_const_wrong_best, rules(),
+    "(+ 1 (- a (* (- 2 1) a)))" => "1"
+    @best "(+ 1 0)"
+}
+
 egg::test_fn! {
     math_simplify_root, rules(),
     runner = Runner::default().with_node_limit(75_000),
@@ -84,6 +84,8 @@ rule! {lem,         "(| ?a (~ ?a))",    "true"                      }
 rule! {or_true,     "(| ?a true)",         "true"                      }
 rule! {and_true,    "(& ?a true)",         "?a"                     }
 rule! {contrapositive, "(-> ?a ?b)",    "(-> (~ ?b) (~ ?a))"     }
+rule! {de_morgan_and, de_morgan_and_flip, "(~ (& ?a ?b))", "(| (~ ?a) (~ ?b))" }
+rule! {de_morgan_or,  de_morgan_or_flip,  "(~ (| ?a ?b))", "(& (~ ?a) (~ ?b))" }
 
 // this has to be a multipattern since (& (-> ?a ?b) (-> (~ ?a) ?c))  !=  (| ?b ?c)
 // see https://github.com/egraphs-good/egg/issues/185
@@ -172,6 +174,40 @@ fn prove_chain() {
     );
 }
 
+fn prove_tautology(expr: &str, rewrites: &[Rewrite]) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let expr = expr.parse().unwrap();
+    let runner = Runner::default().with_iter_limit(20).with_node_limit(5_000);
+    assert!(
+        prove_equal(rewrites, &expr, &"true".parse().unwrap(), runner),
+        "Couldn't prove {}",
+        expr
+    );
+}
+
+#[test]
+fn prove_tautologies() {
+    let rules = &[
+        def_imply(),
+        de_morgan_and(),
+        de_morgan_and_flip(),
+        de_morgan_or(),
+        de_morgan_or_flip(),
+        double_neg(),
+        assoc_or(),
+        comm_or(),
+        comm_and(),
+        lem(),
+        or_true(),
+        and_true(),
+        contrapositive(),
+    ];
+    prove_tautology("(| (& x y) (| (~ x) (~ y)))", rules);
+    prove_tautology("(| (~ (| x y)) (| x y))", rules);
+    prove_tautology("(-> (& x y) x)", rules);
+    prove_tautology("(-> x (| x y))", rules);
+}
+
 #[test]
 fn const_fold() {
     let start = "(| (& false true) (& true false))";